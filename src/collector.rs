@@ -0,0 +1,203 @@
+/*****
+
+Implementation of Collector.
+Collector reads a resource on its own worker thread with its own interval,
+and publishes the latest snapshot so that the renderer never waits for it.
+
+*******/
+
+use crate::config::CollectorSetting;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct Snapshot<T> {
+  pub value: T,
+  pub seq: u64,          // sequence number of this snapshot. starts from 1.
+  pub taken_at: Instant, // time the read started.
+  pub elapsed: f64,      // [sec] since the snapshot previously taken by the renderer.
+  pub took: Duration,    // time the read took.
+}
+
+struct Shared<T> {
+  latest: Option<Snapshot<T>>, // newest snapshot not yet taken by the renderer.
+  running_since: Option<Instant>, // start time of the read in flight.
+}
+
+pub struct Collector<T> {
+  pub name: String,
+  timeout: Duration,
+  shared: Arc<Mutex<Shared<T>>>,
+  must_kill: Arc<AtomicBool>,
+  last_taken_at: Option<Instant>,
+}
+
+impl<T: Send + 'static> Collector<T> {
+  // spawn a worker thread which calls `read` every `setting.interval_ms`.
+  // a snapshot not yet taken is replaced by the newer one.
+  pub fn spawn<F>(name: &str, setting: &CollectorSetting, read: F) -> Self
+  where
+    F: FnMut() -> T + Send + 'static,
+  {
+    Self::spawn_with_merge(name, setting, read, |old, new| *old = new)
+  }
+
+  // same as `spawn`, but a snapshot not yet taken is merged with the newer one by `merge`.
+  // used for resources which are read incrementally.
+  pub fn spawn_with_merge<F, M>(
+    name: &str,
+    setting: &CollectorSetting,
+    mut read: F,
+    merge: M,
+  ) -> Self
+  where
+    F: FnMut() -> T + Send + 'static,
+    M: Fn(&mut T, T) + Send + 'static,
+  {
+    let shared = Arc::new(Mutex::new(Shared {
+      latest: None,
+      running_since: None,
+    }));
+    let must_kill = Arc::new(AtomicBool::new(false));
+    let interval = Duration::from_millis(setting.interval_ms);
+
+    let worker_shared = shared.clone();
+    let worker_must_kill = must_kill.clone();
+    let _worker = thread::Builder::new()
+      .name(format!("collector-{}", name))
+      .spawn(move || {
+        let mut seq = 0;
+        loop {
+          if worker_must_kill.load(Ordering::Relaxed) {
+            break;
+          }
+
+          let start = Instant::now();
+          worker_shared.lock().unwrap().running_since = Some(start);
+          let value = read();
+          let took = start.elapsed();
+
+          seq += 1;
+          {
+            let mut shared = worker_shared.lock().unwrap();
+            shared.running_since = None;
+            match shared.latest.as_mut() {
+              Some(latest) => {
+                merge(&mut latest.value, value);
+                latest.seq = seq;
+                latest.took = took;
+              }
+              None => {
+                shared.latest = Some(Snapshot {
+                  value,
+                  seq,
+                  taken_at: start,
+                  elapsed: interval.as_secs_f64(),
+                  took,
+                })
+              }
+            }
+          }
+
+          if let Some(rest) = interval.checked_sub(took) {
+            thread::sleep(rest);
+          }
+        }
+      })
+      .unwrap();
+
+    Self {
+      name: name.into(),
+      timeout: Duration::from_millis(setting.timeout_ms),
+      shared,
+      must_kill,
+      last_taken_at: None,
+    }
+  }
+
+  // returns the snapshot published after the last call, if any.
+  pub fn take(&mut self) -> Option<Snapshot<T>> {
+    let mut snapshot = self.shared.lock().unwrap().latest.take()?;
+    if let Some(last) = self.last_taken_at {
+      snapshot.elapsed = snapshot.taken_at.duration_since(last).as_secs_f64();
+    }
+    self.last_taken_at = Some(snapshot.taken_at);
+
+    Some(snapshot)
+  }
+
+  // whether the read in flight has been running longer than the timeout.
+  pub fn is_timed_out(&self) -> bool {
+    match self.shared.lock().unwrap().running_since {
+      Some(since) => since.elapsed() > self.timeout,
+      None => false,
+    }
+  }
+}
+
+impl<T> Drop for Collector<T> {
+  fn drop(&mut self) {
+    // worker exits after the read in flight finishes.
+    self.must_kill.store(true, Ordering::Relaxed);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn wait_snapshot<T: Send + 'static>(collector: &mut Collector<T>) -> Snapshot<T> {
+    for _ in 0..200 {
+      if let Some(s) = collector.take() {
+        return s;
+      }
+      thread::sleep(Duration::from_millis(5));
+    }
+    panic!("collector {} published nothing.", collector.name);
+  }
+
+  #[test]
+  fn test_collector_publishes() {
+    let mut n = 0;
+    let mut collector = Collector::spawn("test", &CollectorSetting::new(1, 1000), move || {
+      n += 1;
+      n
+    });
+    let first = wait_snapshot(&mut collector);
+    let second = wait_snapshot(&mut collector);
+    assert!(first.seq < second.seq);
+    assert!(second.value >= 2);
+  }
+
+  #[test]
+  fn test_collector_merge() {
+    let mut n = 0;
+    let mut collector = Collector::spawn_with_merge(
+      "merge",
+      &CollectorSetting::new(1, 1000),
+      move || {
+        n += 1;
+        vec![n]
+      },
+      |old: &mut Vec<u32>, new| old.extend(new),
+    );
+    thread::sleep(Duration::from_millis(50));
+    let first = wait_snapshot(&mut collector);
+    let second = wait_snapshot(&mut collector);
+    // nothing is lost between takes.
+    assert_eq!(first.value[0], 1);
+    assert_eq!(second.value[0], *first.value.last().unwrap() + 1);
+  }
+
+  #[test]
+  fn test_collector_timeout() {
+    let mut collector = Collector::spawn("slow", &CollectorSetting::new(1, 10), || {
+      thread::sleep(Duration::from_millis(100));
+    });
+    thread::sleep(Duration::from_millis(50));
+    assert!(collector.is_timed_out());
+    let _ = wait_snapshot(&mut collector);
+  }
+}
@@ -1,3 +1,8 @@
 pub mod commander;
+pub mod cpu;
+pub mod dmesg;
 pub mod input;
+pub mod io;
+pub mod net;
 pub mod process;
+pub mod screen;
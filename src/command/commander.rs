@@ -1,10 +1,19 @@
-use super::{input, process};
-use crate::render::component::processmeter_manager::ProcessMeterManager;
+use super::{cpu, dmesg, input, io, net, process, screen};
+use crate::render::component::{
+  cpugraph::CpuGraph, iometer::IoMeter, netmeter::NetMeter,
+  processmeter_manager::ProcessMeterManager,
+};
+use crate::render::executer::manager::WinManager;
 
 #[derive(Debug)]
 pub enum CommandType {
   Input,
   Process,
+  Dmesg,
+  Net,
+  Io,
+  Cpu,
+  Screen,
   Invalid,
 }
 
@@ -14,6 +23,11 @@ impl CommandType {
     match s {
       "i" => Input,
       "p" => Process,
+      "d" => Dmesg,
+      "n" => Net,
+      "io" => Io,
+      "c" => Cpu,
+      "s" => Screen,
       _ => Invalid,
     }
   }
@@ -23,6 +37,11 @@ impl CommandType {
     match self {
       Input => "i: xinput operation".into(),
       Process => "p: process list operation".into(),
+      Dmesg => "d: dmesg list operation".into(),
+      Net => "n: net meter operation".into(),
+      Io => "io: io meter operation".into(),
+      Cpu => "c: cpu graph operation".into(),
+      Screen => "s: switch the screen".into(),
       Invalid => "".into(),
     }
   }
@@ -37,7 +56,7 @@ pub struct CommanderUsage {}
 impl CommanderUsage {
   pub fn all_usage() -> Vec<String> {
     use CommandType::*;
-    let types = [Input, Process];
+    let types = [Input, Process, Dmesg, Net, Io, Cpu, Screen];
     types.iter().map(|t| t.to_usage()).collect()
   }
 }
@@ -66,6 +85,21 @@ impl Commander {
       CommandType::Process => {
         completions.extend(process::ProcCommand::all_usage().iter().cloned());
       }
+      CommandType::Dmesg => {
+        completions.extend(dmesg::DmesgCommand::all_usage().iter().cloned());
+      }
+      CommandType::Net => {
+        completions.extend(net::NetCommand::all_usage().iter().cloned());
+      }
+      CommandType::Io => {
+        completions.extend(io::IoCommand::all_usage().iter().cloned());
+      }
+      CommandType::Cpu => {
+        completions.extend(cpu::CpuCommand::all_usage().iter().cloned());
+      }
+      CommandType::Screen => {
+        completions.extend(screen::all_usage());
+      }
       CommandType::Invalid => {
         completions.extend(CommanderUsage::all_usage().iter().cloned());
       }
@@ -74,7 +108,7 @@ impl Commander {
     completions
   }
 
-  pub fn execute(&mut self, command: &str, procmanager: &mut ProcessMeterManager) -> String {
+  pub fn execute(&mut self, command: &str, wm: &mut WinManager) -> String {
     use self::CommandType::*;
     self.is_active = false;
     let tokens = command.split_whitespace().collect::<Vec<&str>>();
@@ -85,7 +119,24 @@ impl Commander {
     let typ = CommandType::from(tokens[0]);
     match typ {
       Input => input::execute(tokens[1..].to_vec()),
-      Process => process::execute(tokens[1..].to_vec(), procmanager),
+      Process => match wm.meters.find_mut::<ProcessMeterManager>(wm.focused) {
+        Some(procmanager) => process::execute(tokens[1..].to_vec(), procmanager),
+        None => "process list is not in the layout".into(),
+      },
+      Dmesg => dmesg::execute(tokens[1..].to_vec(), wm),
+      Net => match wm.meters.find_mut::<NetMeter>(wm.focused) {
+        Some(netmeter) => net::execute(tokens[1..].to_vec(), netmeter),
+        None => "net meter is not in the layout".into(),
+      },
+      Io => match wm.meters.find_mut::<IoMeter>(wm.focused) {
+        Some(iometer) => io::execute(tokens[1..].to_vec(), iometer),
+        None => "io meter is not in the layout".into(),
+      },
+      Cpu => match wm.meters.find_mut::<CpuGraph>(wm.focused) {
+        Some(cpu_graph) => cpu::execute(tokens[1..].to_vec(), cpu_graph),
+        None => "cpu graph is not in the layout".into(),
+      },
+      Screen => screen::execute(tokens[1..].to_vec(), wm),
       Invalid => "invalid command".into(),
     }
   }
@@ -0,0 +1,97 @@
+/*********
+
+CPU graph subcommands
+
+*********/
+
+use crate::config::CpuGraphView;
+use crate::render::component::cpugraph::{CoreSelection, CpuGraph};
+use crate::util::parse_cpu_list;
+
+pub enum CpuCommand {
+  Select,
+  Busiest,
+  View,
+  UnsetFilter,
+  Invalid,
+}
+
+impl CpuCommand {
+  pub fn from(s: &str) -> Self {
+    use CpuCommand::*;
+    match s {
+      "s" | "select" => Select,
+      "b" | "busiest" => Busiest,
+      "v" | "view" => View,
+      "u" | "unset" => UnsetFilter,
+      _ => Invalid,
+    }
+  }
+
+  pub fn to_usage(&self) -> String {
+    use CpuCommand::*;
+    match self {
+      Select => "s <cpulist>: plot only these cores (eg: 0-3,8)".into(),
+      Busiest => "b <num>: plot the busiest cores".into(),
+      View => "v [average | overlay | multiple]: switch the view".into(),
+      UnsetFilter => "u: plot all cores".into(),
+      Invalid => "".into(),
+    }
+  }
+
+  pub fn all_usage() -> Vec<String> {
+    use CpuCommand::*;
+    let subs = [Select, Busiest, View, UnsetFilter];
+    subs.iter().map(|s| s.to_usage()).collect()
+  }
+}
+
+pub fn execute(_command: Vec<&str>, cpu_graph: &mut CpuGraph) -> String {
+  use CpuCommand::*;
+  if _command.is_empty() {
+    return "invalid subcommand".into();
+  }
+  let mut command = _command.iter();
+
+  let subcommand = CpuCommand::from(command.next().unwrap());
+  match subcommand {
+    Select => {
+      let ids = parse_cpu_list(&command.copied().collect::<Vec<&str>>().join(","));
+      if ids.is_empty() {
+        "invalid subcommand".into()
+      } else {
+        let result = format!("Plot cores: {:?}", ids);
+        cpu_graph.select(CoreSelection::Cores(ids));
+        result
+      }
+    }
+    Busiest => match command.next().map(|s| s.parse::<usize>()) {
+      Some(Ok(n)) if n > 0 => {
+        cpu_graph.select(CoreSelection::Busiest(n));
+        format!("Plot {} busiest cores", n)
+      }
+      _ => "invalid subcommand".into(),
+    },
+    View => {
+      let view = match command.next().copied() {
+        None => Some(cpu_graph.toggle_view()),
+        Some("a") | Some("average") => Some(CpuGraphView::Average),
+        Some("o") | Some("overlay") => Some(CpuGraphView::Overlay),
+        Some("m") | Some("multiple") => Some(CpuGraphView::Multiple),
+        Some(_) => None,
+      };
+      match view {
+        Some(view) => {
+          cpu_graph.set_view(view);
+          format!("Set cpu graph view: {:?}", view)
+        }
+        None => "invalid subcommand".into(),
+      }
+    }
+    UnsetFilter => {
+      cpu_graph.select(CoreSelection::All);
+      "Plot all cores".into()
+    }
+    Invalid => "invalid subcommand".into(),
+  }
+}
@@ -0,0 +1,154 @@
+/*********
+
+Dmesg subcommands
+
+*********/
+
+use crate::config::LogSource;
+use crate::render::component::dmesglist::{DmesgList, Pattern, TimeFormat};
+use crate::render::executer::{init::spawn_dmesg_collector, manager::WinManager, update};
+use crate::resource::dmesg::LogLevel;
+
+pub enum DmesgCommand {
+  Level,
+  Search,
+  Regex,
+  Time,
+  UnsetFilter,
+  Source,
+  Invalid,
+}
+
+impl DmesgCommand {
+  pub fn from(s: &str) -> Self {
+    use DmesgCommand::*;
+    match s {
+      "l" | "level" => Level,
+      "s" | "search" => Search,
+      "r" | "regex" => Regex,
+      "t" | "time" => Time,
+      "u" | "unset" => UnsetFilter,
+      "o" | "source" => Source,
+      _ => Invalid,
+    }
+  }
+
+  pub fn to_usage(&self) -> String {
+    use DmesgCommand::*;
+    match self {
+      Level => "l <level>: show level and above".into(),
+      Search => "s <word>: filter by substring".into(),
+      Regex => "r <regex>: filter by regex".into(),
+      Time => "t [boot | wall]: timestamp format".into(),
+      UnsetFilter => "u: unset all filters".into(),
+      Source => "o <auto | kmsg | journal> [unit]: log source".into(),
+      Invalid => "".into(),
+    }
+  }
+
+  pub fn all_usage() -> Vec<String> {
+    use DmesgCommand::*;
+    let subs = [Level, Search, Regex, Time, UnsetFilter, Source];
+    subs.iter().map(|s| s.to_usage()).collect()
+  }
+}
+
+pub fn execute(_command: Vec<&str>, wm: &mut WinManager) -> String {
+  use DmesgCommand::*;
+  if _command.is_empty() {
+    return "invalid subcommand".into();
+  }
+  let mut command = _command.iter();
+
+  let subcommand = DmesgCommand::from(command.next().unwrap());
+  if let Source = subcommand {
+    return set_source(command.cloned().collect(), wm);
+  }
+  let dmesglist = match wm.meters.find_mut::<DmesgList>(wm.focused) {
+    Some(dmesglist) => dmesglist,
+    None => return "dmesg list is not in the layout".into(),
+  };
+  match subcommand {
+    Level => {
+      if command.len() == 1 {
+        match LogLevel::from(command.next().unwrap()) {
+          Some(level) => {
+            dmesglist.set_level(Some(level));
+            format!("Show logs of level {} and above", level)
+          }
+          None => "invalid level: emerg|alert|crit|err|warn|notice|info|debug".into(),
+        }
+      } else {
+        "invalid subcommand".into()
+      }
+    }
+    Search => {
+      if command.len() >= 1 {
+        // allow spaces in the keyword
+        let keyword = command.cloned().collect::<Vec<&str>>().join(" ");
+        dmesglist.set_pattern(Pattern::Substring(keyword.clone()));
+        format!("Set filter by word: {}", keyword)
+      } else {
+        "invalid subcommand".into()
+      }
+    }
+    Regex => {
+      if command.len() >= 1 {
+        let pattern = command.cloned().collect::<Vec<&str>>().join(" ");
+        match regex::Regex::new(&pattern) {
+          Ok(re) => {
+            dmesglist.set_pattern(Pattern::Regex(re));
+            format!("Set filter by regex: {}", pattern)
+          }
+          Err(_) => format!("invalid regex: {}", pattern),
+        }
+      } else {
+        "invalid subcommand".into()
+      }
+    }
+    Time => {
+      let format = match command.next() {
+        Some(&"boot") => TimeFormat::Boot,
+        Some(&"wall") => TimeFormat::Wall,
+        Some(_) => return "invalid subcommand: d t [boot | wall]".into(),
+        None => {
+          let format = dmesglist.toggle_time_format();
+          return format!("Set timestamp format: {:?}", format);
+        }
+      };
+      dmesglist.set_time_format(format);
+      format!("Set timestamp format: {:?}", format)
+    }
+    UnsetFilter => {
+      dmesglist.set_level(None);
+      dmesglist.set_pattern(Pattern::Nothing);
+      "Unset all filters".into()
+    }
+    Source | Invalid => "invalid subcommand".into(),
+  }
+}
+
+// respawn the collector of the log pane with a new source.
+fn set_source(args: Vec<&str>, wm: &mut WinManager) -> String {
+  let slot = match wm.meters.find::<DmesgList>(wm.focused) {
+    Some(id) => wm.meters.slot_mut(id).unwrap(),
+    None => return "dmesg list is not in the layout".into(),
+  };
+  let source = match args.first() {
+    Some(&"auto") => LogSource::Auto,
+    Some(&"kmsg") => LogSource::Kmsg,
+    Some(&"journal") => LogSource::Journal,
+    _ => return "invalid subcommand: d o <auto | kmsg | journal> [unit]".into(),
+  };
+  slot.config.log.source = source;
+  slot.config.log.unit = args.get(1).map(|unit| unit.to_string());
+
+  let collector = spawn_dmesg_collector(&slot.config);
+  let name = collector.name.clone();
+  slot.get_mut::<DmesgList>().unwrap().set_source(&name);
+  slot.set_update(update::update_dmesglist(collector));
+  match &slot.config.log.unit {
+    Some(unit) if name == "journal" => format!("Read logs from {} of {}", name, unit),
+    _ => format!("Read logs from {}", name),
+  }
+}
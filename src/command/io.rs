@@ -0,0 +1,97 @@
+/*********
+
+IO subcommands
+
+*********/
+
+use crate::render::component::iometer::IoMeter;
+
+pub enum IoCommand {
+  Select,
+  Exclude,
+  All,
+  Table,
+  UnsetFilter,
+  Invalid,
+}
+
+impl IoCommand {
+  pub fn from(s: &str) -> Self {
+    use IoCommand::*;
+    match s {
+      "s" | "select" => Select,
+      "x" | "exclude" => Exclude,
+      "a" | "all" => All,
+      "t" | "table" => Table,
+      "u" | "unset" => UnsetFilter,
+      _ => Invalid,
+    }
+  }
+
+  pub fn to_usage(&self) -> String {
+    use IoCommand::*;
+    match self {
+      Select => "s <device>...: sum only these devices".into(),
+      Exclude => "x <regex>: exclude devices".into(),
+      All => "a: toggle partitions and virtual devices".into(),
+      Table => "t: toggle table of devices".into(),
+      UnsetFilter => "u: unset all filters".into(),
+      Invalid => "".into(),
+    }
+  }
+
+  pub fn all_usage() -> Vec<String> {
+    use IoCommand::*;
+    let subs = [Select, Exclude, All, Table, UnsetFilter];
+    subs.iter().map(|s| s.to_usage()).collect()
+  }
+}
+
+pub fn execute(_command: Vec<&str>, iometer: &mut IoMeter) -> String {
+  use IoCommand::*;
+  if _command.is_empty() {
+    return "invalid subcommand".into();
+  }
+  let mut command = _command.iter();
+
+  let subcommand = IoCommand::from(command.next().unwrap());
+  match subcommand {
+    Select => {
+      if command.len() >= 1 {
+        let names: Vec<String> = command.map(|s| s.to_string()).collect();
+        let result = format!("Sum devices: {}", names.join(" "));
+        iometer.select(names);
+        result
+      } else {
+        "invalid subcommand".into()
+      }
+    }
+    Exclude => {
+      if command.len() == 1 {
+        let pattern = command.next().unwrap();
+        match regex::Regex::new(pattern) {
+          Ok(re) => {
+            iometer.set_exclude(Some(re));
+            format!("Exclude devices: {}", pattern)
+          }
+          Err(_) => format!("invalid regex: {}", pattern),
+        }
+      } else {
+        "invalid subcommand".into()
+      }
+    }
+    All => {
+      if iometer.toggle_all() {
+        "Sum partitions and virtual devices".into()
+      } else {
+        "Exclude partitions and virtual devices".into()
+      }
+    }
+    Table => format!("Set io view: {:?}", iometer.toggle_view()),
+    UnsetFilter => {
+      iometer.select(vec![]);
+      "Unset all filters".into()
+    }
+    Invalid => "invalid subcommand".into(),
+  }
+}
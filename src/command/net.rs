@@ -0,0 +1,97 @@
+/*********
+
+Net subcommands
+
+*********/
+
+use crate::render::component::netmeter::NetMeter;
+
+pub enum NetCommand {
+  Select,
+  Exclude,
+  Virtual,
+  Table,
+  UnsetFilter,
+  Invalid,
+}
+
+impl NetCommand {
+  pub fn from(s: &str) -> Self {
+    use NetCommand::*;
+    match s {
+      "s" | "select" => Select,
+      "x" | "exclude" => Exclude,
+      "v" | "virtual" => Virtual,
+      "t" | "table" => Table,
+      "u" | "unset" => UnsetFilter,
+      _ => Invalid,
+    }
+  }
+
+  pub fn to_usage(&self) -> String {
+    use NetCommand::*;
+    match self {
+      Select => "s <interface>...: sum only these interfaces".into(),
+      Exclude => "x <regex>: exclude interfaces".into(),
+      Virtual => "v: toggle virtual interfaces".into(),
+      Table => "t: toggle table of interfaces".into(),
+      UnsetFilter => "u: unset all filters".into(),
+      Invalid => "".into(),
+    }
+  }
+
+  pub fn all_usage() -> Vec<String> {
+    use NetCommand::*;
+    let subs = [Select, Exclude, Virtual, Table, UnsetFilter];
+    subs.iter().map(|s| s.to_usage()).collect()
+  }
+}
+
+pub fn execute(_command: Vec<&str>, netmeter: &mut NetMeter) -> String {
+  use NetCommand::*;
+  if _command.is_empty() {
+    return "invalid subcommand".into();
+  }
+  let mut command = _command.iter();
+
+  let subcommand = NetCommand::from(command.next().unwrap());
+  match subcommand {
+    Select => {
+      if command.len() >= 1 {
+        let names: Vec<String> = command.map(|s| s.to_string()).collect();
+        let result = format!("Sum interfaces: {}", names.join(" "));
+        netmeter.select(names);
+        result
+      } else {
+        "invalid subcommand".into()
+      }
+    }
+    Exclude => {
+      if command.len() == 1 {
+        let pattern = command.next().unwrap();
+        match regex::Regex::new(pattern) {
+          Ok(re) => {
+            netmeter.set_exclude(Some(re));
+            format!("Exclude interfaces: {}", pattern)
+          }
+          Err(_) => format!("invalid regex: {}", pattern),
+        }
+      } else {
+        "invalid subcommand".into()
+      }
+    }
+    Virtual => {
+      if netmeter.toggle_virtual() {
+        "Sum virtual interfaces".into()
+      } else {
+        "Exclude virtual interfaces".into()
+      }
+    }
+    Table => format!("Set net view: {:?}", netmeter.toggle_view()),
+    UnsetFilter => {
+      netmeter.select(vec![]);
+      "Unset all filters".into()
+    }
+    Invalid => "invalid subcommand".into(),
+  }
+}
@@ -0,0 +1,37 @@
+/*********
+
+Screen subcommands
+
+*********/
+
+use crate::render::executer::manager::WinManager;
+
+pub fn all_usage() -> Vec<String> {
+  vec!["s <name | number>: switch the screen".into()]
+}
+
+pub fn execute(command: Vec<&str>, wm: &mut WinManager) -> String {
+  let names: Vec<String> = wm
+    .screens
+    .iter()
+    .enumerate()
+    .map(|(i, s)| format!("{}:{}", i + 1, s.name))
+    .collect();
+  let arg = match command.first() {
+    Some(arg) => arg,
+    None => return format!("Screens: {}", names.join(" ")),
+  };
+
+  // number is 1-origin, same as the key to switch.
+  let screen = match arg.parse::<usize>() {
+    Ok(n) if n >= 1 && n <= wm.screens.len() => Some(n - 1),
+    _ => wm.screens.iter().position(|s| s.name == *arg),
+  };
+  match screen {
+    Some(screen) => {
+      wm.switch_screen(screen);
+      format!("Switched to screen {}", wm.screens[screen].name)
+    }
+    None => format!("no such screen: {} ({})", arg, names.join(" ")),
+  }
+}
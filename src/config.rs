@@ -0,0 +1,646 @@
+/********
+
+Implementation of general config file (xtop.json).
+Every section is optional and falls back to its default value.
+
+********/
+
+use crate::theme::{builtin_palette, Palette};
+use crate::util::NameSelection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+static CONFIG_PATH: &str = "xtop.json";
+
+// interval and timeout of a single background collector.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CollectorSetting {
+  pub interval_ms: u64, // how often the resource is read.
+  pub timeout_ms: u64,  // a read taking longer than this is reported as timed out.
+}
+
+impl CollectorSetting {
+  pub const fn new(interval_ms: u64, timeout_ms: u64) -> Self {
+    Self {
+      interval_ms,
+      timeout_ms,
+    }
+  }
+
+  // a read is reported as timed out only after it has missed its interval.
+  fn check(&self, name: &str) -> Result<(), String> {
+    if self.interval_ms == 0 {
+      return Err(format!(
+        "interval_ms of collector {} must be positive in {}",
+        name, CONFIG_PATH
+      ));
+    }
+    if self.timeout_ms < self.interval_ms {
+      return Err(format!(
+        "timeout_ms of collector {} must not be shorter than interval_ms in {}",
+        name, CONFIG_PATH
+      ));
+    }
+    Ok(())
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CollectorConfig {
+  pub net: CollectorSetting,
+  pub disk: CollectorSetting,
+  pub mem: CollectorSetting,
+  pub dmesg: CollectorSetting,
+  pub docker: CollectorSetting,
+  pub inputs: CollectorSetting,
+  pub fs: CollectorSetting,
+  pub psi: CollectorSetting,
+  pub sensors: CollectorSetting,
+  pub battery: CollectorSetting,
+  pub cpufreq: CollectorSetting,
+  pub numa: CollectorSetting,
+}
+
+impl Default for CollectorConfig {
+  fn default() -> Self {
+    Self {
+      net: CollectorSetting::new(2000, 2000),
+      disk: CollectorSetting::new(2000, 2000),
+      mem: CollectorSetting::new(2000, 2000),
+      dmesg: CollectorSetting::new(2000, 2000),
+      docker: CollectorSetting::new(4000, 10000), // `docker` command can be really slow.
+      inputs: CollectorSetting::new(4000, 4000),
+      fs: CollectorSetting::new(4000, 4000), // statvfs on a dead NFS server can hang.
+      psi: CollectorSetting::new(2000, 2000),
+      sensors: CollectorSetting::new(2000, 2000),
+      battery: CollectorSetting::new(4000, 4000),
+      cpufreq: CollectorSetting::new(2000, 2000),
+      numa: CollectorSetting::new(2000, 2000),
+    }
+  }
+}
+
+impl CollectorConfig {
+  fn check(&self) -> Result<(), String> {
+    [
+      ("net", &self.net),
+      ("disk", &self.disk),
+      ("mem", &self.mem),
+      ("dmesg", &self.dmesg),
+      ("docker", &self.docker),
+      ("inputs", &self.inputs),
+      ("fs", &self.fs),
+      ("psi", &self.psi),
+      ("sensors", &self.sensors),
+      ("battery", &self.battery),
+      ("cpufreq", &self.cpufreq),
+      ("numa", &self.numa),
+    ]
+    .iter()
+    .try_for_each(|(name, setting)| setting.check(name))
+  }
+}
+
+// where the log pane reads logs from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+  #[default]
+  Auto, // /dev/kmsg, or the journal if /dev/kmsg is not readable.
+  Kmsg,
+  Journal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct LogConfig {
+  pub source: LogSource,
+  pub unit: Option<String>, // systemd unit to read. only for the journal.
+  pub priority: Option<u8>, // read only this priority (0-7) and above. only for the journal.
+}
+
+// value drawn for a bucket of downsampled history.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregate {
+  Min,
+  #[default]
+  Avg,
+  Max,
+}
+
+// older values of graphs are merged into buckets instead of being dropped.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct DownsampleConfig {
+  pub aggregate: Aggregate,
+  pub bucket_sec: u64,    // span of values merged into a bucket.
+  pub retention_sec: u64, // how long buckets are kept.
+}
+
+impl Default for DownsampleConfig {
+  fn default() -> Self {
+    Self {
+      aggregate: Aggregate::Avg,
+      bucket_sec: 60,
+      retention_sec: 24 * 3600,
+    }
+  }
+}
+
+// how long graphs keep their values. enough values to fill the graph are always kept.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct HistoryConfig {
+  pub retention_sec: u64, // at full resolution.
+  pub downsample: Option<DownsampleConfig>,
+}
+
+impl Default for HistoryConfig {
+  fn default() -> Self {
+    Self {
+      retention_sec: 600,
+      downsample: None,
+    }
+  }
+}
+
+// how the upper limit of a graph is decided.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleMode {
+  #[default]
+  Auto, // follows the recent values.
+  Fixed, // always `max`.
+  Log,   // logarithmic, up to `max` or to the power of 10 above the recent values.
+}
+
+// vertical scale of a graph. `max` is in kB/s for net and disk, and in % for cpu and mem.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct GraphScale {
+  pub mode: ScaleMode,
+  pub max: Option<f64>,
+  pub shared_axis: bool, // rx and tx (or read and write) on one axis. only for net and disk.
+}
+
+impl GraphScale {
+  fn check(&self) -> Result<(), String> {
+    match (self.mode, self.max) {
+      (ScaleMode::Fixed, None) => Err(format!("scale \"fixed\" needs max in {}", CONFIG_PATH)),
+      (_, Some(max)) if max <= 0.0 => {
+        Err(format!("max of scale must be positive in {}", CONFIG_PATH))
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
+// interfaces shown in the graph of NetMeter.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct NetConfig {
+  pub interfaces: Vec<String>, // if not empty, only these interfaces are summed.
+  pub exclude: Option<String>, // regex of interfaces not to be summed.
+  pub include_virtual: bool,   // sum virtual interfaces such as lo, bridges and veths.
+  pub scale: GraphScale,
+}
+
+impl NetConfig {
+  pub fn selection(&self) -> Result<NameSelection, String> {
+    Ok(NameSelection {
+      names: self.interfaces.clone(),
+      exclude: compile_exclude(&self.exclude)?,
+      show_hidden: self.include_virtual,
+    })
+  }
+}
+
+// what CpuGraph plots.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CpuGraphView {
+  #[default]
+  Average, // average of all cores.
+  Overlay,  // a line per core in one graph.
+  Multiple, // a small graph per core.
+}
+
+// appearance of CpuMeter and CpuGraph.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct CpuConfig {
+  pub stacked: bool, // colour each class of time (user, system, iowait, ...) in the bar.
+  pub graph: CpuGraphView,
+  pub graph_cpus: Vec<u32>, // cores plotted per core. all cores if empty.
+  pub scale: GraphScale,
+}
+
+// devices shown in the graph of IoMeter.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct DiskConfig {
+  pub devices: Vec<String>,    // if not empty, only these devices are summed.
+  pub exclude: Option<String>, // regex of devices not to be summed.
+  pub include_all: bool,       // sum partitions and virtual devices (loop, dm, ...) too.
+  pub scale: GraphScale,
+}
+
+impl DiskConfig {
+  pub fn selection(&self) -> Result<NameSelection, String> {
+    Ok(NameSelection {
+      names: self.devices.clone(),
+      exclude: compile_exclude(&self.exclude)?,
+      show_hidden: self.include_all,
+    })
+  }
+}
+
+// graph of MemMeter.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct MemConfig {
+  pub scale: GraphScale,
+}
+
+// cgroups whose pressure is shown in PsiMeter.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct PsiConfig {
+  pub cgroups: Vec<String>, // relative to the root of cgroup v2. eg: "system.slice/docker.service"
+}
+
+// thresholds of temperatures in SensorMeter, used when the sensor has no max/crit of its own.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SensorConfig {
+  pub temp_high: f64, // [degree Celsius]
+  pub temp_crit: f64, // [degree Celsius]
+}
+
+impl Default for SensorConfig {
+  fn default() -> Self {
+    Self {
+      temp_high: 80.0,
+      temp_crit: 95.0,
+    }
+  }
+}
+
+// colours of a user theme by role, in "#RRGGBB". roles not given are taken from `base`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeColors {
+  pub base: Option<String>, // built-in or user theme. "dark" if not given.
+  pub background: Option<String>,
+  pub foreground: Option<String>,
+  pub dim: Option<String>,
+  pub accent: Option<String>,
+  pub header: Option<String>,
+  pub danger: Option<String>,
+  pub medium_danger: Option<String>,
+  pub highlight: Option<String>,
+  pub rx: Option<String>,
+  pub tx: Option<String>,
+  pub read: Option<String>,
+  pub write: Option<String>,
+}
+
+impl ThemeColors {
+  fn apply(&self, palette: &mut Palette) -> Result<(), String> {
+    macro_rules! apply {
+      ($($role:ident),*) => {
+        $(
+          if let Some(rgb) = &self.$role {
+            palette.$role = parse_rgb(rgb)?;
+          }
+        )*
+      };
+    }
+    apply!(
+      background,
+      foreground,
+      dim,
+      accent,
+      header,
+      danger,
+      medium_danger,
+      highlight,
+      rx,
+      tx,
+      read,
+      write
+    );
+    Ok(())
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ThemeConfig {
+  pub name: String, // "dark", "light", "high-contrast" or one of `themes`.
+  pub themes: BTreeMap<String, ThemeColors>,
+}
+
+impl Default for ThemeConfig {
+  fn default() -> Self {
+    Self {
+      name: "dark".into(),
+      themes: BTreeMap::new(),
+    }
+  }
+}
+
+impl ThemeConfig {
+  pub fn palette(&self) -> Result<Palette, String> {
+    self.resolve(&self.name, 0)
+  }
+
+  fn resolve(&self, name: &str, depth: usize) -> Result<Palette, String> {
+    if depth > self.themes.len() {
+      return Err(format!(
+        "theme {} derives from itself in {}",
+        name, CONFIG_PATH
+      ));
+    }
+    match self.themes.get(name) {
+      Some(colors) => {
+        let base = colors.base.as_deref().unwrap_or("dark");
+        // user theme can refine the built-in one of the same name.
+        let mut palette = match builtin_palette(base) {
+          Some(palette) if base == name => palette,
+          _ => self.resolve(base, depth + 1)?,
+        };
+        colors.apply(&mut palette)?;
+        Ok(palette)
+      }
+      None => {
+        builtin_palette(name).ok_or_else(|| format!("unknown theme in {}: {}", CONFIG_PATH, name))
+      }
+    }
+  }
+}
+
+// characters of graphs, bars and boxes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Symbols {
+  #[default]
+  Auto, // unicode if the locale is UTF-8, ASCII otherwise.
+  Unicode,
+  Ascii,
+}
+
+impl Symbols {
+  pub fn is_ascii(&self) -> bool {
+    match self {
+      Symbols::Auto => !crate::symbol::is_utf8_locale(),
+      Symbols::Unicode => false,
+      Symbols::Ascii => true,
+    }
+  }
+}
+
+fn parse_rgb(rgb: &str) -> Result<u32, String> {
+  let err = || format!("invalid colour in {}: {}", CONFIG_PATH, rgb);
+  match rgb.strip_prefix('#') {
+    Some(hex) if hex.len() == 6 => u32::from_str_radix(hex, 16).map_err(|_| err()),
+    _ => Err(err()),
+  }
+}
+
+fn compile_exclude(exclude: &Option<String>) -> Result<Option<regex::Regex>, String> {
+  match exclude {
+    Some(pattern) => match regex::Regex::new(pattern) {
+      Ok(re) => Ok(Some(re)),
+      Err(_) => Err(format!("invalid regex in {}: {}", CONFIG_PATH, pattern)),
+    },
+    None => Ok(None),
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct Config {
+  pub collectors: CollectorConfig,
+  pub log: LogConfig,
+  pub cpu: CpuConfig,
+  pub mem: MemConfig,
+  pub net: NetConfig,
+  pub disk: DiskConfig,
+  pub psi: PsiConfig,
+  pub sensors: SensorConfig,
+  pub history: HistoryConfig,
+  pub theme: ThemeConfig,
+  pub symbols: Symbols,
+}
+
+pub fn read_config() -> Result<Config, String> {
+  match std::fs::read_to_string(CONFIG_PATH) {
+    Ok(config_str) => parse_config(&config_str),
+    Err(_) => Ok(Config::default()),
+  }
+}
+
+pub fn parse_config(config_str: &str) -> Result<Config, String> {
+  let config: Config = match serde_json::from_str(config_str) {
+    Ok(config) => config,
+    Err(e) => return Err(format!("failed to parse {}: {}", CONFIG_PATH, e)),
+  };
+  config.collectors.check()?;
+  config.net.selection()?;
+  config.disk.selection()?;
+  config.check_scales()?;
+  config.theme.palette()?;
+
+  Ok(config)
+}
+
+impl Config {
+  // config of a component, whose `config` in the layout overrides this one.
+  pub fn with_overrides(&self, overrides: &Value) -> Result<Config, String> {
+    let mut value = serde_json::to_value(self).map_err(|e| e.to_string())?;
+    merge_json(&mut value, overrides);
+    let config: Config = match serde_json::from_value(value) {
+      Ok(config) => config,
+      Err(e) => return Err(format!("invalid config in the layout: {}", e)),
+    };
+    config.collectors.check()?;
+    config.net.selection()?;
+    config.disk.selection()?;
+    config.check_scales()?;
+
+    Ok(config)
+  }
+
+  fn check_scales(&self) -> Result<(), String> {
+    self.cpu.scale.check()?;
+    self.mem.scale.check()?;
+    self.net.scale.check()?;
+    self.disk.scale.check()
+  }
+}
+
+// objects are merged key by key, and any other value is replaced.
+fn merge_json(base: &mut Value, overrides: &Value) {
+  match (base, overrides) {
+    (Value::Object(base), Value::Object(overrides)) => {
+      for (key, value) in overrides {
+        merge_json(base.entry(key.clone()).or_insert(Value::Null), value);
+      }
+    }
+    (base, overrides) => *base = overrides.clone(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_partial_config() {
+    let config =
+      parse_config(r#"{"collectors": {"docker": {"interval_ms": 10000, "timeout_ms": 20000}}}"#)
+        .unwrap();
+    let mut answer = Config::default();
+    answer.collectors.docker = CollectorSetting::new(10000, 20000);
+    assert_eq!(config, answer);
+  }
+
+  #[test]
+  fn test_collector_config() {
+    assert!(Config::default().collectors.check().is_ok());
+    assert!(
+      parse_config(r#"{"collectors": {"net": {"interval_ms": 0, "timeout_ms": 0}}}"#).is_err()
+    );
+    assert!(
+      parse_config(r#"{"collectors": {"fs": {"interval_ms": 4000, "timeout_ms": 100}}}"#).is_err()
+    );
+    assert!(
+      parse_config(r#"{"collectors": {"fs": {"interval_ms": 500, "timeout_ms": 500}}}"#).is_ok()
+    );
+  }
+
+  #[test]
+  fn test_log_config() {
+    let config = parse_config(r#"{"log": {"source": "journal", "unit": "ssh.service"}}"#).unwrap();
+    assert_eq!(config.log.source, LogSource::Journal);
+    assert_eq!(config.log.unit, Some("ssh.service".into()));
+    assert_eq!(config.log.priority, None);
+    assert!(parse_config(r#"{"log": {"source": "syslog"}}"#).is_err());
+  }
+
+  #[test]
+  fn test_net_config() {
+    let config = parse_config(r#"{"net": {"exclude": "^(docker|veth)"}}"#).unwrap();
+    let selection = config.net.selection().unwrap();
+    assert!(selection.is_selected("eth0", false));
+    assert!(!selection.is_selected("veth1234", false));
+    assert!(!selection.is_selected("lo", true));
+    assert!(parse_config(r#"{"net": {"exclude": "("}}"#).is_err());
+  }
+
+  #[test]
+  fn test_cpu_config() {
+    let config = parse_config(r#"{"cpu": {"graph": "multiple", "graph_cpus": [0, 3]}}"#).unwrap();
+    assert_eq!(config.cpu.graph, CpuGraphView::Multiple);
+    assert_eq!(config.cpu.graph_cpus, vec![0, 3]);
+    assert!(!config.cpu.stacked);
+    assert!(parse_config(r#"{"cpu": {"graph": "bars"}}"#).is_err());
+  }
+
+  #[test]
+  fn test_theme_config() {
+    let config = parse_config(r#"{"theme": {"name": "light"}}"#).unwrap();
+    assert_eq!(
+      config.theme.palette(),
+      Ok(builtin_palette("light").unwrap())
+    );
+
+    let config = parse_config(
+      r##"{"theme": {"name": "mine", "themes": {
+        "base": {"base": "high-contrast", "rx": "#00ff00"},
+        "mine": {"base": "base", "danger": "#FF0000"}
+      }}}"##,
+    )
+    .unwrap();
+    let palette = config.theme.palette().unwrap();
+    assert_eq!(palette.danger, 0xFF0000);
+    assert_eq!(palette.rx, 0x00FF00);
+    assert_eq!(palette.tx, builtin_palette("high-contrast").unwrap().tx);
+
+    // user theme refining the built-in one of the same name
+    let config =
+      parse_config(r##"{"theme": {"name": "dark", "themes": {"dark": {"tx": "#123456"}}}}"##);
+    assert_eq!(config.unwrap().theme.palette().unwrap().tx, 0x123456);
+
+    assert!(parse_config(r#"{"theme": {"name": "solarized"}}"#).is_err());
+    assert!(parse_config(r#"{"theme": {"name": "a", "themes": {"a": {"rx": "green"}}}}"#).is_err());
+    assert!(
+      parse_config(r##"{"theme": {"name": "a", "themes": {"a": {"red": "#ff0000"}}}}"##).is_err()
+    );
+    let cycle = r#"{"theme": {"name": "a", "themes": {"a": {"base": "b"}, "b": {"base": "a"}}}}"#;
+    assert!(parse_config(cycle).is_err());
+  }
+
+  #[test]
+  fn test_overrides() {
+    let config =
+      parse_config(r#"{"net": {"exclude": "^veth"}, "cpu": {"stacked": true}}"#).unwrap();
+    let overridden = config
+      .with_overrides(&serde_json::json!({"net": {"interfaces": ["eth0"]}}))
+      .unwrap();
+    assert_eq!(overridden.net.interfaces, vec!["eth0".to_string()]);
+    assert_eq!(overridden.net.exclude, Some("^veth".into()));
+    assert!(overridden.cpu.stacked);
+    assert!(config
+      .with_overrides(&serde_json::json!({"log": {"source": "syslog"}}))
+      .is_err());
+    assert!(config
+      .with_overrides(&serde_json::json!({"disk": {"exclude": "("}}))
+      .is_err());
+  }
+
+  #[test]
+  fn test_empty_config() {
+    assert_eq!(parse_config("{}").unwrap(), Config::default());
+  }
+
+  #[test]
+  fn test_symbols_config() {
+    assert_eq!(parse_config("{}").unwrap().symbols, Symbols::Auto);
+    let config = parse_config(r#"{"symbols": "ascii"}"#).unwrap();
+    assert!(config.symbols.is_ascii());
+    assert!(!Symbols::Unicode.is_ascii());
+    assert!(parse_config(r#"{"symbols": "braille"}"#).is_err());
+  }
+
+  #[test]
+  fn test_scale_config() {
+    let config = parse_config(
+      r#"{"net": {"scale": {"mode": "log", "shared_axis": true}}, "mem": {"scale": {"mode": "fixed", "max": 80}}}"#,
+    )
+    .unwrap();
+    assert_eq!(config.net.scale.mode, ScaleMode::Log);
+    assert!(config.net.scale.shared_axis);
+    assert_eq!(config.mem.scale.max, Some(80.0));
+    assert_eq!(config.disk.scale, GraphScale::default());
+    assert!(parse_config(r#"{"cpu": {"scale": {"mode": "fixed"}}}"#).is_err());
+    assert!(parse_config(r#"{"disk": {"scale": {"mode": "log", "max": 0}}}"#).is_err());
+  }
+
+  #[test]
+  fn test_history_config() {
+    assert_eq!(parse_config("{}").unwrap().history.downsample, None);
+    let config =
+      parse_config(r#"{"history": {"retention_sec": 60, "downsample": {"aggregate": "max"}}}"#)
+        .unwrap();
+    assert_eq!(config.history.retention_sec, 60);
+    let downsample = config.history.downsample.unwrap();
+    assert_eq!(downsample.aggregate, Aggregate::Max);
+    assert_eq!(downsample.bucket_sec, 60);
+    assert!(parse_config(r#"{"history": {"downsample": {"aggregate": "median"}}}"#).is_err());
+  }
+}
@@ -0,0 +1,286 @@
+/*****
+
+History of values drawn in graphs.
+Values are kept for the retention period of the config, and at least as many as
+the graph is wide. Older ones are dropped, or merged into buckets when downsampled.
+
+*******/
+
+use crate::config::{Aggregate, HistoryConfig};
+use std::collections::VecDeque;
+
+// a value recorded at once, such as f64 or (rx, tx).
+pub trait Sample: Copy + Default {
+  // applies `f` to each pair of components.
+  fn zip(self, other: Self, f: fn(f64, f64) -> f64) -> Self;
+  fn scale(self, k: f64) -> Self;
+}
+
+impl Sample for f64 {
+  fn zip(self, other: Self, f: fn(f64, f64) -> f64) -> Self {
+    f(self, other)
+  }
+
+  fn scale(self, k: f64) -> Self {
+    self * k
+  }
+}
+
+impl<A: Sample, B: Sample> Sample for (A, B) {
+  fn zip(self, other: Self, f: fn(f64, f64) -> f64) -> Self {
+    (self.0.zip(other.0, f), self.1.zip(other.1, f))
+  }
+
+  fn scale(self, k: f64) -> Self {
+    (self.0.scale(k), self.1.scale(k))
+  }
+}
+
+impl<T: Sample, const N: usize> Sample for [T; N]
+where
+  [T; N]: Default,
+{
+  fn zip(self, other: Self, f: fn(f64, f64) -> f64) -> Self {
+    std::array::from_fn(|i| self[i].zip(other[i], f))
+  }
+
+  fn scale(self, k: f64) -> Self {
+    self.map(|v| v.scale(k))
+  }
+}
+
+// values merged by downsampling.
+#[derive(Clone, Copy, Debug)]
+struct Bucket<T> {
+  min: T,
+  max: T,
+  sum: T,
+  count: usize,
+  secs: f64, // span of the merged values.
+}
+
+impl<T: Sample> Bucket<T> {
+  fn new(value: T, secs: f64) -> Self {
+    Self {
+      min: value,
+      max: value,
+      sum: value,
+      count: 1,
+      secs,
+    }
+  }
+
+  fn add(&mut self, value: T, secs: f64) {
+    self.min = self.min.zip(value, f64::min);
+    self.max = self.max.zip(value, f64::max);
+    self.sum = self.sum.zip(value, |a, b| a + b);
+    self.count += 1;
+    self.secs += secs;
+  }
+
+  fn value(&self, aggregate: Aggregate) -> T {
+    match aggregate {
+      Aggregate::Min => self.min,
+      Aggregate::Avg => self.sum.scale(1.0 / self.count as f64),
+      Aggregate::Max => self.max,
+    }
+  }
+}
+
+pub struct History<T> {
+  config: HistoryConfig,
+  min_len: usize,             // kept beyond the retention to fill the graph.
+  interval: f64,              // [sec] of the latest value, assumed for columns not recorded yet.
+  recent: VecDeque<(T, f64)>, // (value, [sec] it stands for). newest at back.
+  recent_secs: f64,
+  pending: Option<Bucket<T>>, // bucket being filled by values older than the retention.
+  buckets: VecDeque<Bucket<T>>, // newest at back.
+  buckets_secs: f64,
+}
+
+impl<T: Sample> History<T> {
+  pub fn new(config: &HistoryConfig, interval: f64) -> Self {
+    Self {
+      config: *config,
+      min_len: 0,
+      interval,
+      recent: VecDeque::new(),
+      recent_secs: 0.0,
+      pending: None,
+      buckets: VecDeque::new(),
+      buckets_secs: 0.0,
+    }
+  }
+
+  // keep at least `columns` values, so that the graph is filled after the window grows.
+  // values are never dropped by shrinking the window.
+  pub fn fit(&mut self, columns: usize) {
+    self.min_len = self.min_len.max(columns);
+  }
+
+  // record `value` standing for `secs` since the previous one.
+  pub fn push(&mut self, value: T, secs: f64) {
+    self.interval = secs;
+    self.recent.push_back((value, secs));
+    self.recent_secs += secs;
+
+    let retention = self.config.retention_sec as f64;
+    while self.recent.len() > self.min_len {
+      let (oldest, oldest_secs) = self.recent[0];
+      if self.recent_secs - oldest_secs < retention {
+        break;
+      }
+      self.recent.pop_front();
+      self.recent_secs -= oldest_secs;
+      self.downsample(oldest, oldest_secs);
+    }
+  }
+
+  fn downsample(&mut self, value: T, secs: f64) {
+    let config = match self.config.downsample {
+      Some(config) => config,
+      None => return,
+    };
+    let bucket = match self.pending.as_mut() {
+      Some(bucket) => {
+        bucket.add(value, secs);
+        bucket
+      }
+      None => self.pending.insert(Bucket::new(value, secs)),
+    };
+    if bucket.secs < config.bucket_sec as f64 {
+      return;
+    }
+
+    let bucket = self.pending.take().unwrap();
+    self.buckets_secs += bucket.secs;
+    self.buckets.push_back(bucket);
+    while let Some(oldest) = self.buckets.front() {
+      if self.buckets_secs - oldest.secs < config.retention_sec as f64 {
+        break;
+      }
+      self.buckets_secs -= oldest.secs;
+      self.buckets.pop_front();
+    }
+  }
+
+  pub fn last(&self) -> T {
+    self.recent.back().map(|(v, _)| *v).unwrap_or_default()
+  }
+
+  // (value, [sec]) of `size` columns of the graph, newest first.
+  // older columns are buckets when downsampled, and defaults before the first value.
+  fn columns(&self, size: usize) -> impl Iterator<Item = (T, f64)> + '_ {
+    let aggregate = self
+      .config
+      .downsample
+      .map(|d| d.aggregate)
+      .unwrap_or_default();
+    let buckets = self
+      .pending
+      .iter()
+      .chain(self.buckets.iter().rev())
+      .map(move |b| (b.value(aggregate), b.secs));
+    self
+      .recent
+      .iter()
+      .rev()
+      .copied()
+      .chain(buckets)
+      .chain(std::iter::repeat((T::default(), self.interval)))
+      .take(size)
+  }
+
+  // latest `size` values. oldest entry is at index 0.
+  pub fn recent(&self, size: usize) -> Vec<T> {
+    let mut res: Vec<T> = self.columns(size).map(|(v, _)| v).collect();
+    res.reverse();
+    res
+  }
+
+  // [sec] spanned by `recent(size)`.
+  pub fn span(&self, size: usize) -> f64 {
+    self.columns(size).map(|(_, secs)| secs).sum()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::DownsampleConfig;
+
+  fn config(retention_sec: u64, downsample: Option<DownsampleConfig>) -> HistoryConfig {
+    HistoryConfig {
+      retention_sec,
+      downsample,
+    }
+  }
+
+  #[test]
+  fn test_retention() {
+    let mut history = History::new(&config(10, None), 2.0);
+    assert_eq!(history.recent(3), vec![0.0, 0.0, 0.0]);
+    assert_eq!(history.span(3), 6.0);
+
+    for i in 0..10 {
+      history.push(i as f64, 2.0);
+    }
+    // 10 sec at 2 sec interval
+    assert_eq!(history.recent(7), vec![0.0, 0.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+    assert_eq!(history.last(), 9.0);
+
+    // a wider graph keeps more values.
+    history.fit(6);
+    for i in 10..20 {
+      history.push(i as f64, 2.0);
+    }
+    assert_eq!(history.recent(6), vec![14.0, 15.0, 16.0, 17.0, 18.0, 19.0]);
+    history.fit(2);
+    history.push(20.0, 2.0);
+    assert_eq!(history.recent(6), vec![15.0, 16.0, 17.0, 18.0, 19.0, 20.0]);
+
+    // the retention is in seconds, whatever the interval is.
+    let mut history = History::new(&config(10, None), 2.0);
+    for i in 0..12 {
+      history.push(i as f64, 1.0);
+    }
+    let mut answer = vec![0.0];
+    answer.extend((2..12).map(|i| i as f64));
+    assert_eq!(history.recent(11), answer);
+  }
+
+  #[test]
+  fn test_downsample() {
+    let downsample = DownsampleConfig {
+      aggregate: Aggregate::Max,
+      bucket_sec: 4,
+      retention_sec: 8,
+    };
+    let mut history = History::new(&config(4, Some(downsample)), 1.0);
+    for i in 0..15 {
+      history.push(i as f64, 1.0);
+    }
+    // 11..14 as is, 8..10 pending, and buckets of 4..7 and 0..3.
+    assert_eq!(history.recent(5), vec![10.0, 11.0, 12.0, 13.0, 14.0]);
+    assert_eq!(
+      history.recent(8),
+      vec![0.0, 3.0, 7.0, 10.0, 11.0, 12.0, 13.0, 14.0]
+    );
+    assert_eq!(history.span(7), 4.0 + 3.0 + 4.0 + 4.0);
+
+    let mut history: History<(f64, f64)> = History::new(&config(1, Some(downsample)), 1.0);
+    for i in 0..5 {
+      history.push((i as f64, -i as f64), 1.0);
+    }
+    let avg = DownsampleConfig {
+      aggregate: Aggregate::Avg,
+      ..downsample
+    };
+    let mut averaged: History<(f64, f64)> = History::new(&config(1, Some(avg)), 1.0);
+    for i in 0..5 {
+      averaged.push((i as f64, -i as f64), 1.0);
+    }
+    assert_eq!(history.recent(2), vec![(3.0, 0.0), (4.0, -4.0)]);
+    assert_eq!(averaged.recent(2), vec![(1.5, -1.5), (4.0, -4.0)]);
+  }
+}
@@ -5,9 +5,12 @@ Calculate layout related things.
 ********/
 
 use super::config::*;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 
 #[derive(Debug)]
 pub struct LayoutFixed {
+  pub slot: usize, // index in the layout config.
   pub name: MeterName,
   pub y: i32,
   pub x: i32,
@@ -15,25 +18,312 @@ pub struct LayoutFixed {
   pub width: i32,
 }
 
-pub fn check_layout_validity(layouts: &[Layout]) -> Result<(), String> {
-  match get_fixed_layouts_may_error(layouts, 100, 100) {
+pub fn check_layout_validity(layout: &ScreenLayout) -> Result<(), String> {
+  match get_fixed_layouts_may_error(layout, 100, 100, &[]) {
     Ok(_) => Ok(()),
     Err(s) => Err(s),
   }
 }
 
-pub fn get_fixed_layouts(layouts: &[Layout], sheight: i32, swidth: i32) -> Vec<LayoutFixed> {
-  match get_fixed_layouts_may_error(layouts, sheight, swidth) {
-    Ok(fixed_layouts) => fixed_layouts,
-    Err(_) => vec![],
+// components placed in the screen.
+// while some component is smaller than the minimum size of the meter or runs off the screen,
+// the component of the lowest priority is hidden, and space of hidden ones is given to the others.
+// in the list form, a whole `Line` is hidden at once, unless a component doesn't fit by itself.
+pub fn get_fixed_layouts(layout: &ScreenLayout, sheight: i32, swidth: i32) -> Vec<LayoutFixed> {
+  let slots = layout.slots();
+  let lines = match layout {
+    ScreenLayout::Flat(layouts) => Some(line_numbers(layouts)),
+    ScreenLayout::Tree(_) => None,
+  };
+  let place = |hidden: &[bool]| get_fixed_layouts_may_error(layout, sheight, swidth, hidden).ok();
+  let doesnt_fit = |l: &LayoutFixed| {
+    let (min_height, min_width) = slots[l.slot].name.min_size();
+    l.height < min_height
+      || l.width < min_width
+      || l.y + l.height > sheight
+      || l.x + l.width > swidth
+  };
+
+  let mut hidden = vec![false; slots.len()];
+  loop {
+    let fixed_layouts = match place(&hidden) {
+      Some(fixed_layouts) => fixed_layouts,
+      None => return vec![],
+    };
+    if !fixed_layouts.iter().any(doesnt_fit) {
+      break;
+    }
+    // candidates are groups of slots hidden together.
+    let mut candidates: Vec<Vec<&LayoutFixed>> = vec![];
+    match &lines {
+      Some(lines) => {
+        let mut by_line: BTreeMap<usize, Vec<&LayoutFixed>> = BTreeMap::new();
+        for l in &fixed_layouts {
+          by_line.entry(lines[l.slot]).or_default().push(l);
+          if doesnt_fit(l) {
+            candidates.push(vec![l]);
+          }
+        }
+        candidates.extend(by_line.into_values());
+      }
+      None => candidates.extend(fixed_layouts.iter().map(|l| vec![l])),
+    }
+    let victim = candidates
+      .iter()
+      .min_by_key(|group| {
+        (
+          group.iter().map(|l| slots[l.slot].priority).max(),
+          !group.iter().any(|l| doesnt_fit(l)),
+          Reverse(group.iter().map(|l| l.slot).max()),
+        )
+      })
+      .unwrap();
+    for l in victim {
+      hidden[l.slot] = true;
+    }
   }
+
+  // hidden components come back if they fit now, eg: ones hidden with their Line.
+  let mut hidden_slots: Vec<usize> = (0..slots.len()).filter(|&i| hidden[i]).collect();
+  hidden_slots.sort_by_key(|&i| (Reverse(slots[i].priority), i));
+  for i in hidden_slots {
+    hidden[i] = false;
+    match place(&hidden) {
+      Some(fixed_layouts) if !fixed_layouts.iter().any(doesnt_fit) => {}
+      _ => hidden[i] = true,
+    }
+  }
+  place(&hidden).unwrap_or_default()
+}
+
+// index of the `Line` each component of the list form starts in.
+fn line_numbers(layouts: &[Layout]) -> Vec<usize> {
+  let mut line = 0;
+  layouts
+    .iter()
+    .map(|layout| {
+      let cur = line;
+      if layout.ratio == Size::Rest {
+        line += 1;
+      }
+      cur
+    })
+    .collect()
 }
 
 fn get_fixed_layouts_may_error(
+  layout: &ScreenLayout,
+  sheight: i32,
+  swidth: i32,
+  hidden: &[bool],
+) -> Result<Vec<LayoutFixed>, String> {
+  match layout {
+    ScreenLayout::Flat(layouts) => get_line_layouts(layouts, sheight, swidth, hidden),
+    ScreenLayout::Tree(root) => {
+      let mut fixed_layouts = vec![];
+      let mut slot = 0;
+      let area = (1, 0, sheight - 1, swidth);
+      if !count_hidden(root, 0, hidden).1 {
+        place_node(root, area, &mut slot, hidden, &mut fixed_layouts)?;
+      }
+      Ok(fixed_layouts)
+    }
+  }
+}
+
+// number of components in `node` whose first slot is `slot`, and whether all of them are hidden.
+fn count_hidden(node: &Node, slot: usize, hidden: &[bool]) -> (usize, bool) {
+  match node {
+    Node::Component { .. } => (1, hidden.get(slot).copied().unwrap_or(false)),
+    Node::Split { children, .. } => {
+      let (count, all) = children.iter().fold((0, true), |(count, all), child| {
+        let (n, h) = count_hidden(child, slot + count, hidden);
+        (count + n, all && h)
+      });
+      // empty split is placed to be reported as an error.
+      (count, all && !children.is_empty())
+    }
+  }
+}
+
+// place `node` in the area of (y, x, height, width).
+// hidden components and components without space are omitted, but still consume their slot.
+fn place_node(
+  node: &Node,
+  (y, x, height, width): (i32, i32, i32, i32),
+  slot: &mut usize,
+  hidden: &[bool],
+  fixed_layouts: &mut Vec<LayoutFixed>,
+) -> Result<(), String> {
+  match node {
+    Node::Component { name, .. } => {
+      if height > 0 && width > 0 && !hidden.get(*slot).copied().unwrap_or(false) {
+        fixed_layouts.push(LayoutFixed {
+          slot: *slot,
+          name: name.clone(),
+          y,
+          x,
+          height,
+          width,
+        });
+      }
+      *slot += 1;
+    }
+    Node::Split {
+      split, children, ..
+    } => {
+      if children.is_empty() {
+        return Err("Split should have at least one child.".into());
+      }
+      let total = match split {
+        Direction::Horizontal => width,
+        Direction::Vertical => height,
+      };
+      // children whose components are all hidden don't take space.
+      let mut shown = vec![];
+      for child in children {
+        let (count, all_hidden) = count_hidden(child, *slot, hidden);
+        if !all_hidden {
+          shown.push((child, *slot));
+        }
+        *slot += count;
+      }
+      let constraints: Vec<&Constraint> = shown.iter().map(|(c, _)| c.constraint()).collect();
+      let sizes = split_sizes(total, &constraints)?;
+      let mut offset = 0;
+      for ((child, mut first), size) in shown.into_iter().zip(sizes) {
+        let area = match split {
+          Direction::Horizontal => (y, x + offset, height, size),
+          Direction::Vertical => (y + offset, x, size, width),
+        };
+        place_node(child, area, &mut first, hidden, fixed_layouts)?;
+        offset += size;
+      }
+    }
+  }
+  Ok(())
+}
+
+// share `total` lines/columns among children by their constraints.
+// if children don't fit, the later ones are shrunk first.
+fn split_sizes(total: i32, constraints: &[&Constraint]) -> Result<Vec<i32>, String> {
+  let total = std::cmp::max(total, 0) as i64;
+  let bounds = |c: &Constraint| {
+    let max = c
+      .max
+      .map_or(i64::MAX, |m| std::cmp::min(m, i32::MAX as u64) as i64);
+    (c.min.unwrap_or(0) as i64, max)
+  };
+  let weight = |c: &Constraint| match c.size {
+    Sizing::Weight(w) => w,
+    Sizing::Fixed(_) => 0.0,
+  };
+
+  let mut sizes = vec![0; constraints.len()];
+  let mut settled = vec![false; constraints.len()];
+  for (i, c) in constraints.iter().enumerate() {
+    let (min, max) = bounds(c);
+    if min > max {
+      return Err("min should not be larger than max.".into());
+    }
+    match c.size {
+      Sizing::Fixed(n) => {
+        sizes[i] = std::cmp::min(n, i32::MAX as u64).clamp(min as u64, max as u64) as i64;
+        settled[i] = true;
+      }
+      Sizing::Weight(w) if !(w.is_finite() && w >= 0.0) => {
+        return Err("Weight should be a non-negative number.".into());
+      }
+      Sizing::Weight(_) => {}
+    }
+  }
+
+  // weighted children share the rest. ones exceeding their min/max are settled to it,
+  // and the others share the rest again.
+  loop {
+    let settled_sum: i64 = (0..sizes.len())
+      .filter(|&i| settled[i])
+      .map(|i| sizes[i])
+      .sum();
+    let rest = std::cmp::max(total - settled_sum, 0);
+    let unsettled: Vec<usize> = (0..sizes.len()).filter(|&i| !settled[i]).collect();
+    let weight_sum: f64 = unsettled.iter().map(|&i| weight(constraints[i])).sum();
+
+    let mut changed = false;
+    for &i in &unsettled {
+      let (min, max) = bounds(constraints[i]);
+      let share = match weight_sum > 0.0 {
+        true => rest as f64 * weight(constraints[i]) / weight_sum,
+        false => 0.0,
+      };
+      if share < min as f64 {
+        sizes[i] = min;
+      } else if share > max as f64 {
+        sizes[i] = max;
+      } else {
+        sizes[i] = share as i64;
+        continue;
+      }
+      settled[i] = true;
+      changed = true;
+    }
+
+    if !changed {
+      // columns left by rounding go to the first children.
+      let mut remainder = rest - unsettled.iter().map(|&i| sizes[i]).sum::<i64>();
+      for &i in &unsettled {
+        if remainder <= 0 {
+          break;
+        }
+        if weight(constraints[i]) > 0.0 && sizes[i] < bounds(constraints[i]).1 {
+          sizes[i] += 1;
+          remainder -= 1;
+        }
+      }
+      break;
+    }
+  }
+
+  let mut offset = 0;
+  Ok(
+    sizes
+      .into_iter()
+      .map(|size| {
+        let size = std::cmp::min(size, total - offset);
+        offset += size;
+        size as i32
+      })
+      .collect(),
+  )
+}
+
+// layout of `Line`s stacked vertically.
+// width of hidden components goes to the `Rest` one of the Line, or to the other ones in ratio
+// when it's hidden as well. a `Line` of only hidden ones takes no lines.
+fn get_line_layouts(
   layouts: &[Layout],
   sheight: i32,
   swidth: i32,
+  hidden: &[bool],
 ) -> Result<Vec<LayoutFixed>, String> {
+  let is_hidden = |slot: usize| hidden.get(slot).copied().unwrap_or(false);
+  let lines = line_numbers(layouts);
+  // for Lines whose `Rest` component is hidden: sum of ratios shown and the last one shown.
+  let mut stretched: BTreeMap<usize, (f64, usize)> = BTreeMap::new();
+  for (slot, layout) in layouts.iter().enumerate() {
+    if layout.ratio == Size::Rest && is_hidden(slot) {
+      stretched.insert(lines[slot], (0.0, slot));
+    }
+  }
+  for (slot, layout) in layouts.iter().enumerate() {
+    if let (Some((sum, last)), Size::Ratio(r)) = (stretched.get_mut(&lines[slot]), &layout.ratio) {
+      if !is_hidden(slot) && !matches!(layout.height, Height::Multiple(_)) {
+        *sum += r;
+        *last = slot;
+      }
+    }
+  }
+
   let mut fixed_layouts = vec![];
   let mut max_height_in_line = 0;
   let mut line_width = swidth;
@@ -41,16 +331,23 @@ fn get_fixed_layouts_may_error(
   let mut x_start = 0;
   let mut cur_x = x_start;
   let mut cur_y = 1;
+  let mut stretch_width = None; // width shared by the stretched components of the Line.
 
-  for layout in layouts {
+  for (slot, layout) in layouts.iter().enumerate() {
     let mut go_newline = false;
 
-    let width = match layout.ratio {
-      Size::Ratio(r) => (line_width as f64 * r) as i32,
-      Size::Rest => {
+    let is_multiline = matches!(layout.height, Height::Multiple(_));
+    let width = match (layout.ratio.clone(), stretched.get(&lines[slot])) {
+      (Size::Rest, _) => {
         go_newline = true;
         line_width - cur_x
       }
+      (Size::Ratio(_), Some(&(_, last))) if slot == last => line_width - cur_x,
+      (Size::Ratio(r), Some(&(sum, _))) if !is_multiline && sum > 0.0 => {
+        let width = *stretch_width.get_or_insert(line_width - cur_x);
+        (width as f64 * r / sum) as i32
+      }
+      (Size::Ratio(r), _) => (line_width as f64 * r) as i32,
     };
 
     let height = match layout.height {
@@ -58,6 +355,7 @@ fn get_fixed_layouts_may_error(
       Height::Rest => sheight - cur_y,
       Height::Minus(l) => (sheight - cur_y) - l as i32,
       // if this component uses multiple Line, wait until Line height is fixed
+      Height::Multiple(_) if is_hidden(slot) => 0,
       Height::Multiple(l) => {
         if l <= 1 {
           return Err("Multline component should have more than two Lines.".into());
@@ -75,6 +373,7 @@ fn get_fixed_layouts_may_error(
         } else {
           multiline_waiting_queue = Some((
             LayoutFixed {
+              slot,
               name: layout.name.clone(),
               y: cur_y,
               x: cur_x,
@@ -89,19 +388,23 @@ fn get_fixed_layouts_may_error(
       }
     };
 
-    if height != -1 {
-      max_height_in_line = std::cmp::max(max_height_in_line, height);
-      fixed_layouts.push(LayoutFixed {
-        name: layout.name.clone(),
-        y: cur_y,
-        x: cur_x,
-        height,
-        width,
-      });
+    if !is_hidden(slot) {
+      if height != -1 {
+        max_height_in_line = std::cmp::max(max_height_in_line, height);
+        fixed_layouts.push(LayoutFixed {
+          slot,
+          name: layout.name.clone(),
+          y: cur_y,
+          x: cur_x,
+          height,
+          width,
+        });
+      }
+      cur_x += width;
     }
 
-    cur_x += width;
     if go_newline {
+      stretch_width = None;
       if let Some((lay, remained_line)) = multiline_waiting_queue {
         let remained_line = remained_line - 1;
         if remained_line == 0 {
@@ -111,6 +414,7 @@ fn get_fixed_layouts_may_error(
 
           let height = (cur_y + max_height_in_line) - lay.y;
           fixed_layouts.push(LayoutFixed {
+            slot: lay.slot,
             name: lay.name,
             y: lay.y,
             x: lay.x,
@@ -131,15 +435,15 @@ fn get_fixed_layouts_may_error(
   Ok(fixed_layouts)
 }
 
-// receives (y,x) position and retturns clicked layout component and clicked offset inside the component.
+// receives (y,x) position and retturns the slot of clicked component and clicked offset inside the component.
 pub fn get_layout_from_click(
-  layouts: &[Layout],
+  layout: &ScreenLayout,
   sheight: i32,
   swidth: i32,
   y: i32,
   x: i32,
-) -> Option<(MeterName, (i32, i32))> {
-  let fixed_layouts = get_fixed_layouts(layouts, sheight, swidth);
+) -> Option<(usize, (i32, i32))> {
+  let fixed_layouts = get_fixed_layouts(layout, sheight, swidth);
 
   for layout in fixed_layouts {
     if layout.y <= y
@@ -147,7 +451,7 @@ pub fn get_layout_from_click(
       && layout.x <= x
       && x < (layout.x + layout.width)
     {
-      return Some((layout.name, (y - layout.y, x - layout.x)));
+      return Some((layout.slot, (y - layout.y, x - layout.x)));
     }
   }
 
@@ -162,8 +466,177 @@ mod test {
   //#[test]
   #[allow(dead_code)]
   fn test_calc_layout() {
-    let layout = config::read_layout_config();
-    let fixed_layout = get_fixed_layouts(&layout, 400, 1000);
+    let layout = &config::read_layout_config()[0].layout;
+    let fixed_layout = get_fixed_layouts(layout, 400, 1000);
     println!("{:?}", fixed_layout);
   }
+
+  fn constraint(size: Sizing, min: Option<u64>, max: Option<u64>) -> Constraint {
+    Constraint { size, min, max }
+  }
+
+  #[test]
+  fn test_split_sizes() {
+    use Sizing::*;
+    let fixed = constraint(Fixed(10), None, None);
+    let one = constraint(Weight(1.0), None, None);
+    let two = constraint(Weight(2.0), None, None);
+    assert_eq!(
+      split_sizes(100, &[&fixed, &one, &two]).unwrap(),
+      vec![10, 30, 60]
+    );
+    // remainder of rounding
+    assert_eq!(split_sizes(10, &[&one, &one, &one]).unwrap(), vec![4, 3, 3]);
+
+    // min and max are kept, and the rest is shared by the others.
+    let narrow = constraint(Weight(1.0), None, Some(10));
+    let wide = constraint(Weight(1.0), Some(70), None);
+    assert_eq!(split_sizes(100, &[&narrow, &one]).unwrap(), vec![10, 90]);
+    assert_eq!(split_sizes(100, &[&wide, &one]).unwrap(), vec![70, 30]);
+
+    // later children are shrunk when they don't fit.
+    let big = constraint(Fixed(80), None, None);
+    assert_eq!(split_sizes(100, &[&big, &wide]).unwrap(), vec![80, 20]);
+    assert_eq!(split_sizes(50, &[&big, &one]).unwrap(), vec![50, 0]);
+
+    let invalid = constraint(Weight(1.0), Some(10), Some(5));
+    assert!(split_sizes(100, &[&invalid]).is_err());
+    let negative = constraint(Weight(-1.0), None, None);
+    assert!(split_sizes(100, &[&negative]).is_err());
+  }
+
+  #[test]
+  fn test_tree_layout() {
+    let screens = config::parse_layout_config(
+      r#"{"split": "Vertical", "children": [
+        {"split": "Horizontal", "size": {"Fixed": 12}, "children": [
+          {"name": "CpuMeter"},
+          {"split": "Vertical", "children": [
+            {"name": "NetMeter"},
+            {"name": "IoMeter"}
+          ]}
+        ]},
+        {"name": "ProcMeter"},
+        {"name": "CommandBox", "size": {"Fixed": 1}}
+      ]}"#,
+    )
+    .unwrap();
+    let fixed: Vec<_> = get_fixed_layouts(&screens[0].layout, 43, 100)
+      .iter()
+      .map(|l| (l.slot, l.y, l.x, l.height, l.width))
+      .collect();
+    assert_eq!(
+      fixed,
+      vec![
+        (0, 1, 0, 12, 50),
+        (1, 1, 50, 6, 50),
+        (2, 7, 50, 6, 50),
+        (3, 13, 0, 29, 100),
+        (4, 42, 0, 1, 100),
+      ]
+    );
+    assert_eq!(
+      get_layout_from_click(&screens[0].layout, 43, 100, 8, 60),
+      Some((2, (1, 10)))
+    );
+
+    let empty = config::parse_layout_config(r#"{"split": "Vertical", "children": []}"#).unwrap();
+    assert!(check_layout_validity(&empty[0].layout).is_err());
+  }
+
+  #[test]
+  fn test_hiding() {
+    let screens = config::parse_layout_config(
+      r#"{"split": "Horizontal", "children": [
+        {"name": "Inputs"},
+        {"name": "CpuGraph"},
+        {"name": "FsMeter", "priority": 9}
+      ]}"#,
+    )
+    .unwrap();
+    let slots = |sheight, swidth| -> Vec<_> {
+      get_fixed_layouts(&screens[0].layout, sheight, swidth)
+        .iter()
+        .map(|l| (l.slot, l.x, l.width))
+        .collect()
+    };
+    assert_eq!(slots(20, 60), vec![(0, 0, 20), (1, 20, 20), (2, 40, 20)]);
+    // Inputs has the lowest priority, and its space is given to the others.
+    assert_eq!(slots(20, 50), vec![(1, 0, 25), (2, 25, 25)]);
+    assert_eq!(slots(20, 30), vec![(2, 0, 30)]);
+    assert_eq!(slots(20, 10), vec![]);
+    // too low for all
+    assert_eq!(slots(3, 60), vec![]);
+
+    // in line layout, only the too small ones are hidden, and the others take their width.
+    let screens = config::parse_layout_config(
+      r#"[
+        {"name": "CpuGraph", "height": {"Line": 10}, "ratio": {"Ratio": 0.8}},
+        {"name": "Inputs", "height": {"Line": 10}, "ratio": "Rest"}
+      ]"#,
+    )
+    .unwrap();
+    let fixed = get_fixed_layouts(&screens[0].layout, 20, 50);
+    assert_eq!(fixed.len(), 1);
+    assert_eq!((fixed[0].slot, fixed[0].width), (0, 50));
+    let screens = config::parse_layout_config(
+      r#"[
+        {"name": "CpuGraph", "height": {"Line": 10}, "ratio": {"Ratio": 0.5}},
+        {"name": "Inputs", "height": {"Line": 10}, "ratio": {"Ratio": 0.2}},
+        {"name": "FsMeter", "height": {"Line": 10}, "ratio": "Rest"}
+      ]"#,
+    )
+    .unwrap();
+    let fixed: Vec<_> = get_fixed_layouts(&screens[0].layout, 20, 60)
+      .iter()
+      .map(|l| (l.slot, l.x, l.width))
+      .collect();
+    assert_eq!(fixed, vec![(0, 0, 30), (2, 30, 30)]);
+    let screens = config::parse_layout_config(
+      r#"[
+        {"name": "CpuGraph", "height": {"Line": 10}, "ratio": {"Ratio": 0.4}},
+        {"name": "FsMeter", "height": {"Line": 10}, "ratio": {"Ratio": 0.4}},
+        {"name": "Inputs", "height": {"Line": 10}, "ratio": "Rest"}
+      ]"#,
+    )
+    .unwrap();
+    let fixed: Vec<_> = get_fixed_layouts(&screens[0].layout, 20, 61)
+      .iter()
+      .map(|l| (l.slot, l.x, l.width))
+      .collect();
+    assert_eq!(fixed, vec![(0, 0, 30), (1, 30, 31)]);
+
+    // in the default layout, the first Line is hidden for the others running off the screen,
+    // and its components that still fit come back.
+    let layout = &config::default_screen().layout;
+    let fixed = get_fixed_layouts(layout, 24, 160);
+    assert!(fixed
+      .iter()
+      .all(|l| l.y + l.height <= 24 && l.x + l.width <= 160));
+    let slots: Vec<usize> = fixed.iter().map(|l| l.slot).collect();
+    assert_eq!(slots, vec![3, 4, 5, 6, 7, 8, 9]);
+    assert_eq!((fixed[0].y, fixed[0].height), (1, 15));
+    assert_eq!((fixed[6].y, fixed[6].height), (23, 1));
+  }
+
+  #[test]
+  fn test_flat_layout() {
+    let layout = &config::parse_layout_config(
+      r#"[
+        {"name": "CpuMeter", "height": {"Line": 10}, "ratio": {"Ratio": 0.5}},
+        {"name": "NetMeter", "height": {"Line": 10}, "ratio": "Rest"},
+        {"name": "CommandBox", "height": "Rest", "ratio": "Rest"}
+      ]"#,
+    )
+    .unwrap()[0]
+      .layout;
+    let fixed: Vec<_> = get_fixed_layouts(layout, 41, 100)
+      .iter()
+      .map(|l| (l.slot, l.y, l.x, l.height, l.width))
+      .collect();
+    assert_eq!(
+      fixed,
+      vec![(0, 1, 0, 10, 50), (1, 1, 50, 10, 50), (2, 11, 0, 30, 100)]
+    );
+  }
 }
@@ -19,9 +19,42 @@ pub enum MeterName {
   CommandBox,
   DockerMeter,
   IoMeter,
+  FsMeter,
+  PsiMeter,
+  SensorMeter,
+  BatteryMeter,
   Empty,
 }
 
+impl MeterName {
+  // smallest (height, width) in which the meter can be drawn.
+  pub fn min_size(&self) -> (i32, i32) {
+    use MeterName::*;
+    match self {
+      CommandBox => (1, 10),
+      TaskMeter | Inputs | DmesgList | DockerMeter | FsMeter => (4, 20),
+      CpuMeter | CpuGraph | PsiMeter | SensorMeter | BatteryMeter => (5, 20),
+      NetMeter | IoMeter => (6, 20),
+      MemMeter => (8, 24),
+      ProcMeter => (5, 40),
+      Empty => (0, 0),
+    }
+  }
+
+  // meters of lower priority are hidden first when the terminal is too small.
+  pub fn default_priority(&self) -> u8 {
+    use MeterName::*;
+    match self {
+      CommandBox => 5,
+      ProcMeter => 4,
+      CpuMeter | CpuGraph | MemMeter => 3,
+      TaskMeter | NetMeter | IoMeter | DmesgList => 2,
+      Inputs | DockerMeter | FsMeter | PsiMeter | SensorMeter | BatteryMeter => 1,
+      Empty => 0,
+    }
+  }
+}
+
 // If Multiple is specified, the height is determined by the largest component except for Multiple attributed component in the line.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum Height {
@@ -43,6 +76,141 @@ pub struct Layout {
   pub name: MeterName, // name of component.
   pub height: Height,  // height of component.
   pub ratio: Size,     // ratio of width.
+  // options of `xtop.json` overridden only for this component. eg: {"net": {"interfaces": ["eth0"]}}
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub config: Option<serde_json::Value>,
+  // overrides `MeterName::default_priority`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub priority: Option<u8>,
+}
+
+// how children of a split are placed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+  Horizontal, // side by side, from left to right.
+  Vertical,   // stacked, from top to bottom.
+}
+
+// size of a node along the direction of its parent split.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Sizing {
+  Fixed(u64),  // absolute number of lines or columns.
+  Weight(f64), // share of the space left by `Fixed` siblings.
+}
+
+impl Default for Sizing {
+  fn default() -> Self {
+    Sizing::Weight(1.0)
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Constraint {
+  #[serde(default)]
+  pub size: Sizing,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub min: Option<u64>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max: Option<u64>,
+}
+
+// node of tree layout. components are the leaves, and split the area of their parent.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Node {
+  Split {
+    split: Direction,
+    children: Vec<Node>,
+    #[serde(flatten)]
+    constraint: Constraint,
+  },
+  Component {
+    name: MeterName,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    config: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<u8>,
+    #[serde(flatten)]
+    constraint: Constraint,
+  },
+}
+
+impl Node {
+  pub fn constraint(&self) -> &Constraint {
+    match self {
+      Node::Split { constraint, .. } | Node::Component { constraint, .. } => constraint,
+    }
+  }
+}
+
+// component of a slot, taken from either form of layout.
+#[derive(Debug, PartialEq)]
+pub struct SlotSpec<'a> {
+  pub name: &'a MeterName,
+  pub config: Option<&'a serde_json::Value>,
+  pub priority: u8,
+}
+
+impl<'a> SlotSpec<'a> {
+  fn new(name: &'a MeterName, config: &'a Option<serde_json::Value>, priority: Option<u8>) -> Self {
+    Self {
+      name,
+      config: config.as_ref(),
+      priority: priority.unwrap_or_else(|| name.default_priority()),
+    }
+  }
+}
+
+// layout of a screen: a list of components in `Line`s, or a tree of splits.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ScreenLayout {
+  Flat(Vec<Layout>),
+  Tree(Node),
+}
+
+impl ScreenLayout {
+  // components in the order of slots.
+  // slots of tree layout are numbered depth-first.
+  pub fn slots(&self) -> Vec<SlotSpec<'_>> {
+    fn walk<'a>(node: &'a Node, slots: &mut Vec<SlotSpec<'a>>) {
+      match node {
+        Node::Split { children, .. } => children.iter().for_each(|c| walk(c, slots)),
+        Node::Component {
+          name,
+          config,
+          priority,
+          ..
+        } => slots.push(SlotSpec::new(name, config, *priority)),
+      }
+    }
+    match self {
+      ScreenLayout::Flat(layouts) => layouts
+        .iter()
+        .map(|l| SlotSpec::new(&l.name, &l.config, l.priority))
+        .collect(),
+      ScreenLayout::Tree(node) => {
+        let mut slots = vec![];
+        walk(node, &mut slots);
+        slots
+      }
+    }
+  }
+}
+
+// a set of components shown at once. switched by number keys.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Screen {
+  pub name: String,
+  pub layout: ScreenLayout,
+}
+
+// layout file holds either a single layout or named screens.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+enum LayoutFile {
+  Screens { screens: Vec<Screen> },
+  Single(ScreenLayout),
 }
 
 use Height::*;
@@ -53,61 +221,98 @@ static DEFAULT_LAYOUT: [Layout; 10] = [
     name: MeterName::TaskMeter,
     height: Line(11),
     ratio: Ratio(0.25),
+    config: None,
+    priority: None,
   },
   Layout {
     name: MeterName::DockerMeter,
     height: Line(11),
     ratio: Ratio(0.5),
+    config: None,
+    priority: None,
   },
   Layout {
     name: MeterName::IoMeter,
     height: Line(11),
     ratio: Size::Rest,
+    config: None,
+    priority: None,
   },
   Layout {
     name: MeterName::CpuGraph,
     height: Line(15),
     ratio: Ratio(0.28),
+    config: None,
+    priority: None,
   },
   Layout {
     name: MeterName::MemMeter,
     height: Line(15),
     ratio: Ratio(0.16667),
+    config: None,
+    priority: None,
   },
   Layout {
     name: MeterName::NetMeter,
     height: Line(15),
     ratio: Ratio(0.22),
+    config: None,
+    priority: None,
   },
   Layout {
     name: MeterName::Inputs,
     height: Line(15),
     ratio: Size::Rest,
+    config: None,
+    priority: None,
   },
   Layout {
     name: MeterName::ProcMeter,
     height: Height::Minus(1),
     ratio: Ratio(0.75),
+    config: None,
+    priority: None,
   },
   Layout {
     name: MeterName::DmesgList,
     height: Height::Minus(1),
     ratio: Size::Rest,
+    config: None,
+    priority: None,
   },
   Layout {
     name: MeterName::CommandBox,
     height: Height::Rest,
     ratio: Size::Rest,
+    config: None,
+    priority: None,
   },
 ];
 
-pub fn read_layout_config() -> Vec<Layout> {
+pub fn read_layout_config() -> Vec<Screen> {
   match std::fs::read_to_string("layout.json") {
-    Ok(config_str) => serde_json::from_str(&config_str).unwrap(),
-    Err(_) => DEFAULT_LAYOUT.to_vec(),
+    Ok(config_str) => parse_layout_config(&config_str).unwrap(),
+    Err(_) => vec![default_screen()],
   }
 }
 
+pub fn default_screen() -> Screen {
+  Screen {
+    name: "main".into(),
+    layout: ScreenLayout::Flat(DEFAULT_LAYOUT.to_vec()),
+  }
+}
+
+pub fn parse_layout_config(config_str: &str) -> Result<Vec<Screen>, serde_json::Error> {
+  Ok(match serde_json::from_str(config_str)? {
+    LayoutFile::Screens { screens } => screens,
+    LayoutFile::Single(layout) => vec![Screen {
+      layout,
+      ..default_screen()
+    }],
+  })
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -115,7 +320,80 @@ mod tests {
   #[test]
   fn test_reading_json() {
     let layout_from_file = read_layout_config();
-    let layout_default = DEFAULT_LAYOUT.to_vec();
+    let layout_default = vec![default_screen()];
     assert_eq!(layout_from_file, layout_default);
   }
+
+  #[test]
+  fn test_screens() {
+    let screens = parse_layout_config(
+      r#"{"screens": [
+        {"name": "overview", "layout": [{"name": "CpuGraph", "height": "Rest", "ratio": "Rest"}]},
+        {"name": "procs", "layout": [{"name": "ProcMeter", "height": "Rest", "ratio": "Rest"}]}
+      ]}"#,
+    )
+    .unwrap();
+    assert_eq!(screens.len(), 2);
+    assert_eq!(screens[1].name, "procs");
+    assert!(
+      matches!(&screens[1].layout, ScreenLayout::Flat(l) if l[0].name == MeterName::ProcMeter)
+    );
+    assert!(parse_layout_config(r#"{"screens": [{"name": "a"}]}"#).is_err());
+  }
+
+  #[test]
+  fn test_tree() {
+    let screens = parse_layout_config(
+      r#"{"split": "Vertical", "children": [
+        {"split": "Horizontal", "size": {"Fixed": 10}, "children": [
+          {"name": "CpuGraph", "size": {"Weight": 2.0}, "min": 40},
+          {"name": "NetMeter", "config": {"net": {"interfaces": ["eth0"]}}}
+        ]},
+        {"name": "ProcMeter", "max": 30}
+      ]}"#,
+    )
+    .unwrap();
+    let node = match &screens[0].layout {
+      ScreenLayout::Tree(node) => node,
+      _ => panic!("tree layout is read as flat"),
+    };
+    match node {
+      Node::Split {
+        split, children, ..
+      } => {
+        assert_eq!(*split, Direction::Vertical);
+        assert_eq!(children.len(), 2);
+        match &children[1] {
+          Node::Component { constraint, .. } => {
+            assert_eq!(constraint.size, Sizing::Weight(1.0));
+            assert_eq!(constraint.max, Some(30));
+          }
+          _ => panic!("component is read as split"),
+        }
+      }
+      _ => panic!("split is read as component"),
+    }
+    let slots = screens[0].layout.slots();
+    assert_eq!(slots.len(), 3);
+    assert_eq!(slots[1].config.unwrap()["net"]["interfaces"][0], "eth0");
+    assert_eq!(slots[1].priority, MeterName::NetMeter.default_priority());
+  }
+
+  #[test]
+  fn test_slot_config() {
+    let layout: Vec<Layout> = serde_json::from_str(
+      r#"[
+        {"name": "NetMeter", "height": {"Line": 10}, "ratio": {"Ratio": 0.5},
+         "config": {"net": {"interfaces": ["eth0"]}}},
+        {"name": "NetMeter", "height": {"Line": 10}, "ratio": "Rest", "priority": 9}
+      ]"#,
+    )
+    .unwrap();
+    assert_eq!(
+      layout[0].config.as_ref().unwrap()["net"]["interfaces"][0],
+      "eth0"
+    );
+    assert_eq!(layout[1].config, None);
+    assert_eq!(layout[1].priority, Some(9));
+  }
 }
@@ -1,8 +1,12 @@
+pub mod collector;
 pub mod command;
+pub mod config;
 pub mod consts;
+pub mod history;
 pub mod layout;
 pub mod proclist;
 pub mod render;
 pub mod resource;
 pub mod symbol;
+pub mod theme;
 pub mod util;
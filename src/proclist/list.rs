@@ -7,7 +7,9 @@ ProcessList stores information about CPU, processes, tty drivers...
 
 use crate::resource::pstat::pid_t;
 use crate::resource::tty::init_tty_drivers;
-use crate::resource::{cmdline, cpu, loadavg, process, procmem, pstat, stat, tty, uptime as up};
+use crate::resource::{
+  cmdline, cpu, loadavg, process, procmem, pstat, stat, topology, tty, uptime as up,
+};
 use crate::util::clamp;
 use std::collections::HashMap;
 use std::fs;
@@ -18,6 +20,9 @@ pub struct ProcList {
   pub tty_drivers: Vec<tty::TtyDriver>,
   pub cpus: Vec<cpu::Cpu>,
   pub aggregated_cpu: cpu::Cpu,
+  pub topology: topology::Topology,
+  pub online_cpus: Vec<u32>,
+  pub cpus_generation: u32, // incremented when cpus are added, removed, onlined or offlined.
   pub loadaverage: loadavg::LoadAvg,
   pub kernel_threads: u32,
   pub userland_threads: u32,
@@ -38,9 +43,12 @@ impl ProcList {
     let plist = HashMap::new();
     let mut tty_drivers = vec![];
     let cpus = cpu::init_cpus();
+    let online_cpus = cpu::get_online_cpus();
     let aggregated_cpu = cpu::Cpu {
       ..Default::default()
     };
+    let cpu_ids: Vec<u32> = cpus.iter().map(|c| c.id).collect();
+    let topology = topology::Topology::read(&cpu_ids);
     init_tty_drivers(&mut tty_drivers);
     let btime = stat::get_btime();
     let jiffy = sysconf::sysconf(sysconf::SysconfVariable::ScClkTck).unwrap() as i64;
@@ -51,6 +59,9 @@ impl ProcList {
       plist,
       cpus,
       aggregated_cpu,
+      topology,
+      online_cpus,
+      cpus_generation: 0,
       loadaverage,
       tty_drivers,
       kernel_threads: 0,
@@ -64,9 +75,30 @@ impl ProcList {
 
   // update CPUs
   pub fn update_cpus(&mut self) {
+    self.check_cpu_hotplug();
     cpu::update_time_and_period(&mut self.cpus, &mut self.aggregated_cpu);
   }
 
+  // offline cpus are not counted in `aggregated_cpu`.
+  pub fn num_online_cpus(&self) -> usize {
+    std::cmp::max(self.cpus.iter().filter(|c| c.is_online()).count(), 1)
+  }
+
+  // rebuild CPUs when some of them are added, removed, onlined or offlined.
+  fn check_cpu_hotplug(&mut self) {
+    let online_cpus = cpu::get_online_cpus();
+    let present_cpus = cpu::list_present_cpus();
+    let cur_ids: Vec<u32> = self.cpus.iter().map(|c| c.id).collect();
+    if online_cpus == self.online_cpus && present_cpus == cur_ids {
+      return;
+    }
+
+    self.cpus = cpu::rebuild_cpus(&self.cpus, &present_cpus, &online_cpus);
+    self.topology.refresh(&present_cpus);
+    self.online_cpus = online_cpus;
+    self.cpus_generation += 1;
+  }
+
   pub fn recurse_proc_tree(&mut self, ppid: Option<pid_t>, _dname: &str, average_period: f64) {
     let dname = if _dname.chars().nth(_dname.len() - 1).unwrap() == '/' {
       &_dname[0..(_dname.len() - 1)]
@@ -120,6 +152,7 @@ impl ProcList {
       let taskdir = &format!("{}/{}/task", dname, pid);
       self.recurse_proc_tree(Some(pid), taskdir, average_period);
 
+      let num_online_cpus = self.num_online_cpus();
       let proc = self.plist.get_mut(&pid).unwrap();
 
       if pre_existing && proc.is_kernel_thread {
@@ -173,7 +206,7 @@ impl ProcList {
         clamp(
           (proc.utime as f64 + proc.stime as f64 - lasttimes as f64) / average_period * 100.0,
           0.0,
-          self.cpus.len() as f64 * 100.0,
+          num_online_cpus as f64 * 100.0,
         )
       };
 
@@ -1,4 +1,5 @@
 pub mod color;
 pub mod component;
 pub mod executer;
+pub mod graph;
 pub mod meter;
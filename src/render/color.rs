@@ -1,4 +1,17 @@
+/*****
+
+Colours of the terminal.
+Colours are taken from the palette of the theme, by their role.
+Terminals which cannot redefine colours get the nearest of their own colours,
+and monochrome terminals (or `NO_COLOR`) get attributes instead.
+
+*******/
+
+use crate::theme::Palette;
 use ncurses::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static MONOCHROME: AtomicBool = AtomicBool::new(false);
 
 mod colors {
   pub static BROWN_BLACK: i16 = 16;
@@ -9,6 +22,10 @@ mod colors {
   pub static ORANGE: i16 = 21;
   pub static DARK_WHITE: i16 = 22;
   pub static PINK: i16 = 23;
+  pub static RX: i16 = 24;
+  pub static TX: i16 = 25;
+  pub static READ: i16 = 26;
+  pub static WRITE: i16 = 27;
 }
 
 pub mod cpair {
@@ -20,51 +37,168 @@ pub mod cpair {
   pub static PAIR_DARK_ONLY: i16 = 6;
   pub static PAIR_CUTE: i16 = 7;
   pub static PAIR_DARK: i16 = 8;
+  // series of graphs
+  pub static PAIR_RX: i16 = 9;
+  pub static PAIR_TX: i16 = 10;
+  pub static PAIR_READ: i16 = 11;
+  pub static PAIR_WRITE: i16 = 12;
 }
 
-pub fn initialize_color() {
-  use cpair::*;
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColorMode {
+  Monochrome,
+  Indexed(i32), // # of fixed colours of the terminal.
+  Rgb,          // colours can be redefined.
+}
+
+// https://no-color.org/: set and not empty.
+fn no_color() -> bool {
+  std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
 
+// must be called after `initscr`.
+fn detect_color_mode() -> ColorMode {
+  if no_color() || !has_colors() {
+    return ColorMode::Monochrome;
+  }
   start_color();
-  define_colors();
+  // colours 16..=27 are redefined.
+  if can_change_color() && COLORS() > colors::WRITE as i32 {
+    ColorMode::Rgb
+  } else {
+    ColorMode::Indexed(COLORS())
+  }
+}
+
+pub fn initialize_color(palette: &Palette) {
+  use cpair::*;
+
+  let mode = detect_color_mode();
+  if mode == ColorMode::Monochrome {
+    MONOCHROME.store(true, Ordering::Relaxed);
+    return;
+  }
+  define_colors(palette, mode);
 
   bkgd(' ' as chtype | COLOR_PAIR(DEFAULT) as chtype);
 }
 
-pub fn define_colors() {
+fn define_colors(palette: &Palette, mode: ColorMode) {
   use colors::*;
   use cpair::*;
 
-  // init colors
-  init_color_rgb_m(BROWN_BLACK, 0x32302F, 4);
-  init_color_rgb_m(WHITE, 0xEBD8B2, 4);
-  init_color_rgb_m(LIGHT_BLUE, 0x84A87F, 4);
-  init_color_rgb_m(LIGHT_GREEN, 0x4E9A06, 4);
-  init_color_rgb_m(RED, 0xCC241D, 4);
-  init_color_rgb_m(ORANGE, 0xFE8019, 4);
-  init_color_rgb_m(DARK_WHITE, 0x504945, 4);
-  init_color_rgb_m(PINK, 0xd3869b, 4);
+  // init colors. without rgb, the nearest colour of the terminal other than the background is used.
+  let background = match mode {
+    ColorMode::Indexed(n) => nearest_color(palette.background, n, None),
+    _ => {
+      init_color_rgb(BROWN_BLACK, palette.background);
+      BROWN_BLACK
+    }
+  };
+  let color = |color: i16, rgb: u32| match mode {
+    ColorMode::Indexed(n) => nearest_color(rgb, n, Some(background)),
+    _ => {
+      init_color_rgb(color, rgb);
+      color
+    }
+  };
+  let white = color(WHITE, palette.foreground);
+  let light_blue = color(LIGHT_BLUE, palette.accent);
+  let light_green = color(LIGHT_GREEN, palette.header);
+  let red = color(RED, palette.danger);
+  let orange = color(ORANGE, palette.medium_danger);
+  let dark_white = color(DARK_WHITE, palette.dim);
+  let pink = color(PINK, palette.highlight);
+  let rx = color(RX, palette.rx);
+  let tx = color(TX, palette.tx);
+  let read = color(READ, palette.read);
+  let write = color(WRITE, palette.write);
 
   // init pairs
-  init_pair(DEFAULT, WHITE, BROWN_BLACK);
-  init_pair(PAIR_COMM, LIGHT_BLUE, BROWN_BLACK);
-  init_pair(PAIR_HEAD, LIGHT_GREEN, BROWN_BLACK);
-  init_pair(PAIR_DANGER, RED, BROWN_BLACK);
-  init_pair(PAIR_MED_DANGER, ORANGE, BROWN_BLACK);
-  init_pair(PAIR_DARK_ONLY, RED, DARK_WHITE);
-  init_pair(PAIR_CUTE, PINK, BROWN_BLACK);
-  init_pair(PAIR_DARK, DARK_WHITE, BROWN_BLACK);
+  init_pair(DEFAULT, white, background);
+  init_pair(PAIR_COMM, light_blue, background);
+  init_pair(PAIR_HEAD, light_green, background);
+  init_pair(PAIR_DANGER, red, background);
+  init_pair(PAIR_MED_DANGER, orange, background);
+  init_pair(PAIR_DARK_ONLY, red, dark_white);
+  init_pair(PAIR_CUTE, pink, background);
+  init_pair(PAIR_DARK, dark_white, background);
+  init_pair(PAIR_RX, rx, background);
+  init_pair(PAIR_TX, tx, background);
+  init_pair(PAIR_READ, read, background);
+  init_pair(PAIR_WRITE, write, background);
+}
+
+// attributes of the colour pair. monochrome terminals get attributes standing in for colours.
+pub fn color_attr(cpair: i16) -> attr_t {
+  use cpair::*;
+
+  if !MONOCHROME.load(Ordering::Relaxed) {
+    return COLOR_PAIR(cpair);
+  }
+  match cpair {
+    _ if cpair == PAIR_DANGER => A_BOLD() | A_REVERSE(),
+    _ if cpair == PAIR_DARK_ONLY => A_REVERSE(),
+    _ if cpair == PAIR_DARK => A_DIM(),
+    _ if [PAIR_HEAD, PAIR_MED_DANGER, PAIR_CUTE, PAIR_TX, PAIR_WRITE].contains(&cpair) => A_BOLD(),
+    _ => A_NORMAL(),
+  }
+}
+
+// rgb of colour `index` of xterm.
+fn xterm_rgb(index: i16) -> u32 {
+  static BASIC: [u32; 16] = [
+    0x000000, 0xCD0000, 0x00CD00, 0xCDCD00, 0x0000EE, 0xCD00CD, 0x00CDCD, 0xE5E5E5, 0x7F7F7F,
+    0xFF0000, 0x00FF00, 0xFFFF00, 0x5C5CFF, 0xFF00FF, 0x00FFFF, 0xFFFFFF,
+  ];
+  static LEVELS: [u32; 6] = [0, 95, 135, 175, 215, 255];
+  match index {
+    0..=15 => BASIC[index as usize],
+    // 6x6x6 colour cube
+    16..=231 => {
+      let i = (index - 16) as usize;
+      (LEVELS[i / 36] << 16) | (LEVELS[i / 6 % 6] << 8) | LEVELS[i % 6]
+    }
+    // grayscale
+    _ => {
+      let level = 8 + 10 * (index - 232) as u32;
+      (level << 16) | (level << 8) | level
+    }
+  }
+}
+
+// nearest of the first `num_colors` (8, 16 or 256) colours of the terminal, except `exclude`.
+fn nearest_color(rgb: u32, num_colors: i32, exclude: Option<i16>) -> i16 {
+  let num_colors = match num_colors {
+    n if n >= 256 => 256,
+    n if n >= 16 => 16,
+    _ => 8,
+  };
+  let channels = |rgb: u32| [(rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF].map(|c| c as i64);
+  let distance = |index: i16| {
+    let (a, b) = (channels(rgb), channels(xterm_rgb(index)));
+    // weighted by the sensitivity of eyes.
+    [2, 4, 3]
+      .iter()
+      .zip(a.iter().zip(b.iter()))
+      .map(|(w, (a, b))| w * (a - b) * (a - b))
+      .sum::<i64>()
+  };
+  (0..num_colors as i16)
+    .filter(|&index| Some(index) != exclude)
+    .min_by_key(|&index| distance(index))
+    .unwrap()
 }
 
 pub fn mvwaddstr_color(win: WINDOW, y: i32, x: i32, s: &str, cpair: i16) {
-  wattron(win, COLOR_PAIR(cpair));
+  wattron(win, color_attr(cpair));
   mvwaddstr(win, y, x, s);
-  wattroff(win, COLOR_PAIR(cpair));
+  wattroff(win, color_attr(cpair));
 }
 
-fn init_color_rgb_m(color: i16, rgb: u32, m: i16) {
-  let b = (rgb & 0xFF) as i16;
-  let g = ((rgb >> 8) & 0xFF) as i16;
-  let r = ((rgb >> 16) & 0xFF) as i16;
-  init_color(color, r * m, g * m, b * m);
+// ncurses takes each of rgb in [0, 1000].
+fn init_color_rgb(color: i16, rgb: u32) {
+  // rounded up, so that the terminal gets the same value back.
+  let scale = |shift: u32| ((((rgb >> shift) & 0xFF) * 1000).div_ceil(0xFF)) as i16;
+  init_color(color, scale(16), scale(8), scale(0));
 }
@@ -1,13 +1,17 @@
+pub mod batterymeter;
 pub mod commandbox;
 pub mod cpugraph;
 pub mod cpumanager;
 pub mod cpumeter;
 pub mod dmesglist;
 pub mod dockermeter;
+pub mod fsmeter;
 pub mod inputmeter;
 pub mod iometer;
 pub mod memmeter;
 pub mod netmeter;
 pub mod processmeter;
 pub mod processmeter_manager;
+pub mod psimeter;
+pub mod sensormeter;
 pub mod taskmeter;
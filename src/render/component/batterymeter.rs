@@ -0,0 +1,246 @@
+/*****
+
+Implementation of BatteryMeter.
+BatteryMeter shows the state of batteries and the AC adapter,
+and the transition of the capacity and the power.
+
+*******/
+
+use crate::config::GraphScale;
+use crate::history::History;
+use crate::render::graph::{draw_percent_axis, draw_time_axis, draw_unit_axis, Scale, WATT_STEP};
+use crate::render::{color::*, executer::manager::WinManager, meter::*};
+use crate::resource::battery::{Battery, PowerSupplies};
+use crate::symbol::brail::b32::*;
+use crate::util::firstn_chars;
+
+use ncurses::*;
+
+static DANGER_CAPACITY: f64 = 10.0; // [%] while discharging
+static MED_DANGER_CAPACITY: f64 = 20.0;
+static BAR_WIDTH: usize = 10;
+
+static CAPACITYCOLOR: i16 = cpair::DEFAULT;
+static POWERCOLOR: i16 = cpair::PAIR_COMM;
+
+pub struct BatteryMeter {
+  pub height: i32,
+  pub width: i32,
+  pub win: WINDOW,
+  supplies: PowerSupplies,
+  history: History<(f64, f64)>, // (capacity, power) summed over batteries
+}
+
+// "2:35"
+fn format_duration(secs: u64) -> String {
+  format!("{}:{:02}", secs / 3600, secs / 60 % 60)
+}
+
+impl BatteryMeter {
+  pub fn set_supplies(&mut self, supplies: PowerSupplies, update_interval: f64) {
+    self.supplies = supplies;
+    self
+      .history
+      .push((self.total_capacity(), self.total_power()), update_interval);
+  }
+
+  // capacity of all batteries as if they were one.
+  fn total_capacity(&self) -> f64 {
+    let batteries = &self.supplies.batteries;
+    let now: Option<f64> = batteries.iter().map(|b| b.energy_now).sum();
+    let full: Option<f64> = batteries.iter().map(|b| b.energy_full).sum();
+    match (now, full) {
+      (Some(now), Some(full)) if full > 0.0 => now / full * 100.0,
+      _ => {
+        let capacities: Vec<f64> = batteries.iter().filter_map(|b| b.capacity).collect();
+        capacities.iter().sum::<f64>() / std::cmp::max(capacities.len(), 1) as f64
+      }
+    }
+  }
+
+  fn total_power(&self) -> f64 {
+    self
+      .supplies
+      .batteries
+      .iter()
+      .filter_map(|b| b.power_now)
+      .sum()
+  }
+
+  fn capacity_color(battery: &Battery) -> i16 {
+    let capacity = battery.capacity.unwrap_or(100.0);
+    if !battery.is_discharging() {
+      cpair::DEFAULT
+    } else if capacity <= DANGER_CAPACITY {
+      cpair::PAIR_DANGER
+    } else if capacity <= MED_DANGER_CAPACITY {
+      cpair::PAIR_MED_DANGER
+    } else {
+      cpair::DEFAULT
+    }
+  }
+
+  // "BAT0 Discharging  87% [||||||||  ] 12.3W 2:35 left  45.1/57.0Wh  123 cycles"
+  fn draw_battery(&self, y: i32, battery: &Battery) {
+    let width = std::cmp::max(self.width - 2, 0) as usize;
+    let capacity = battery.capacity.unwrap_or(0.0);
+    let num_bars = ((capacity / 100.0 * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+    let mut parts = vec![
+      (format!("{:<6}", battery.name), cpair::PAIR_COMM),
+      (
+        format!(
+          "{:<12} {:>3.0}% [{}{}]",
+          battery.status,
+          capacity,
+          "|".repeat(num_bars),
+          " ".repeat(BAR_WIDTH - num_bars),
+        ),
+        Self::capacity_color(battery),
+      ),
+    ];
+    let mut rest = String::new();
+    if let Some(p) = battery.power_now {
+      rest += &format!(" {:>5.1}W", p);
+    }
+    if let Some(t) = battery.time_to_empty.filter(|_| battery.is_discharging()) {
+      rest += &format!(" {} left", format_duration(t));
+    }
+    if let Some(t) = battery.time_to_full.filter(|_| battery.is_charging()) {
+      rest += &format!(" {} to full", format_duration(t));
+    }
+    if let (Some(now), Some(full)) = (battery.energy_now, battery.energy_full) {
+      rest += &format!("  {:.1}/{:.1}Wh", now, full);
+    }
+    if let Some(c) = battery.cycle_count {
+      rest += &format!("  {} cycles", c);
+    }
+    parts.push((rest, cpair::DEFAULT));
+
+    let mut cx = 1;
+    for (s, color) in parts {
+      let s = firstn_chars(&s, width.saturating_sub(cx as usize - 1));
+      mvwaddstr_color(self.win, y, cx, &s, color);
+      cx += s.len() as i32;
+    }
+  }
+
+  fn draw_single_col(&self, bar: &[Cc], y_bottom: i32, x: i32) {
+    // draw from bottom.
+    for (i, cc) in bar.iter().enumerate() {
+      mvwaddstr_color(self.win, y_bottom - i as i32, x, &cc.ch.to_string(), cc.co);
+    }
+  }
+
+  fn draw_graph(&self, y_top: i32, y_bottom: i32) {
+    let x_start = 3;
+    let width = self.width - 2 - x_start - 3;
+    let height = y_bottom - y_top + 1;
+    if width <= 0 || height <= 1 {
+      return;
+    }
+    let hists = self.history.recent(width as usize);
+    let capacities: Vec<f64> = hists.iter().map(|(c, _)| *c).collect();
+    let watts: Vec<f64> = hists.iter().map(|(_, w)| *w).collect();
+    let config = GraphScale::default();
+    // capacity is always drawn up to 100 %.
+    let capacity_scale = Scale::fit(&config, 100.0, 100.0, Some(100.0));
+    let max_watts = watts.iter().fold(0.0_f64, |a, w| a.max(*w));
+    let watt_scale = Scale::fit(&config, max_watts, WATT_STEP, None);
+    let brails = get_brails_complement_2sep_axes_color(
+      height,
+      (0.0, capacity_scale.top()),
+      (0.0, watt_scale.top()),
+      (capacity_scale.map_all(&capacities), CAPACITYCOLOR),
+      (watt_scale.map_all(&watts), POWERCOLOR),
+    );
+    for (i, col) in brails.iter().enumerate() {
+      self.draw_single_col(col, y_bottom, x_start + i as i32 + 1);
+    }
+
+    draw_percent_axis(self.win, y_top, y_top + height / 2, &capacity_scale);
+    draw_unit_axis(
+      self.win,
+      (y_top, y_bottom),
+      Some(self.width),
+      &watt_scale,
+      "W",
+      POWERCOLOR,
+    );
+    draw_time_axis(
+      self.win,
+      self.height,
+      x_start,
+      self.history.span(width as usize),
+    );
+  }
+}
+
+impl Meter for BatteryMeter {
+  fn render(&mut self) {
+    let win = self.win;
+    werase(win);
+    draw_box(win);
+
+    // draw each battery, and the graph in the rest
+    let mut cy = 1;
+    for battery in &self.supplies.batteries {
+      if cy >= self.height - 1 {
+        break;
+      }
+      self.draw_battery(cy, battery);
+      cy += 1;
+    }
+    if !self.supplies.batteries.is_empty() {
+      self.draw_graph(cy, self.height - 2);
+    }
+
+    // draw header
+    let s = match (self.supplies.batteries.is_empty(), self.supplies.ac_online) {
+      (true, _) => " Battery (no battery) ".to_string(),
+      (false, Some(true)) => format!(" Battery {:.0}% (AC) ", self.total_capacity()),
+      (false, _) => format!(" Battery {:.0}% ", self.total_capacity()),
+    };
+    mvwaddstr_color(win, 0, 1, &s, cpair::PAIR_HEAD);
+
+    wrefresh(win);
+  }
+
+  fn init_meter(
+    _parent: WINDOW,
+    wm: &mut WinManager,
+    height: i32,
+    width: i32,
+    y: i32,
+    x: i32,
+  ) -> Self {
+    let win = newwin(height, width, y, x);
+    wattron(win, COLOR_PAIR(cpair::DEFAULT));
+    wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
+    draw_box(win);
+    wrefresh(win);
+
+    let interval = wm.config.collectors.battery.interval_ms as f64 / 1000.0;
+    let mut history = History::new(&wm.config.history, interval);
+    history.fit(width.max(0) as usize);
+
+    BatteryMeter {
+      width,
+      height,
+      win,
+      supplies: PowerSupplies::default(),
+      history,
+    }
+  }
+
+  fn resize(&mut self, _parent: WINDOW, height: i32, width: i32, y: i32, x: i32) {
+    self.height = height;
+    self.width = width;
+    self.history.fit(width.max(0) as usize);
+    wresize(self.win, height, width);
+    werase(self.win);
+    mvwin(self.win, y, x);
+
+    self.render();
+    wrefresh(self.win);
+  }
+}
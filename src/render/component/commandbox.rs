@@ -72,7 +72,7 @@ impl CommandBox {
     let win = newwin(needed_height, needed_width, y0, x0);
     wattron(win, COLOR_PAIR(cpair::DEFAULT));
     wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
-    box_(win, 0, 0);
+    draw_box(win);
     self.complete_win = Some(win);
 
     // render each completions
@@ -105,15 +105,19 @@ impl CommandBox {
     use crate::render::color::cpair::*;
     let mut total_len = 0;
 
-    let s = " ❦ ";
+    let s = if crate::symbol::is_ascii() {
+      " * "
+    } else {
+      " ❦ "
+    };
     if self.is_active {
-      wattron(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD() | A_BLINK());
+      wattron(self.win, color_attr(PAIR_CUTE) | A_BOLD() | A_BLINK());
       mvwaddstr(self.win, 0, 0, s);
-      wattroff(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD() | A_BLINK());
+      wattroff(self.win, color_attr(PAIR_CUTE) | A_BOLD() | A_BLINK());
     } else {
-      wattron(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD());
+      wattron(self.win, color_attr(PAIR_CUTE) | A_BOLD());
       mvwaddstr(self.win, 0, 0, s);
-      wattroff(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD());
+      wattroff(self.win, color_attr(PAIR_CUTE) | A_BOLD());
     }
     total_len += s.chars().count();
 
@@ -121,15 +125,19 @@ impl CommandBox {
     mvwaddstr(self.win, 0, total_len as i32, s);
     total_len += s.chars().count();
 
-    let s = " ❦  ";
+    let s = if crate::symbol::is_ascii() {
+      " *  "
+    } else {
+      " ❦  "
+    };
     if self.is_active {
-      wattron(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD() | A_BLINK());
+      wattron(self.win, color_attr(PAIR_CUTE) | A_BOLD() | A_BLINK());
       mvwaddstr(self.win, 0, total_len as i32, s);
-      wattroff(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD() | A_BLINK());
+      wattroff(self.win, color_attr(PAIR_CUTE) | A_BOLD() | A_BLINK());
     } else {
-      wattron(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD());
+      wattron(self.win, color_attr(PAIR_CUTE) | A_BOLD());
       mvwaddstr(self.win, 0, total_len as i32, s);
-      wattroff(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD());
+      wattroff(self.win, color_attr(PAIR_CUTE) | A_BOLD());
     }
     total_len += s.chars().count();
 
@@ -147,13 +155,13 @@ impl Meter for CommandBox {
     x += self.render_header() as i32;
 
     if self.result_buffer.is_empty() {
-      wattron(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD());
+      wattron(self.win, color_attr(PAIR_CUTE) | A_BOLD());
       mvwaddstr(self.win, 0, x, &self.command_buffer);
-      wattroff(self.win, COLOR_PAIR(PAIR_CUTE) | A_BOLD());
+      wattroff(self.win, color_attr(PAIR_CUTE) | A_BOLD());
     } else {
-      wattron(self.win, COLOR_PAIR(PAIR_DARK) | A_BOLD());
+      wattron(self.win, color_attr(PAIR_DARK) | A_BOLD());
       mvwaddstr(self.win, 0, x, &self.result_buffer);
-      wattroff(self.win, COLOR_PAIR(PAIR_DARK) | A_BOLD());
+      wattroff(self.win, color_attr(PAIR_DARK) | A_BOLD());
     }
 
     // render completion
@@ -1,44 +1,137 @@
 /*****
 
 Implementation of CPUGraph.
-CPUGraph shows the transition of CPU usage.
+CPUGraph shows the transition of CPU usage,
+either of the average or of each core as overlaid lines or small graphs.
 
 *******/
 
+use crate::config::{CpuGraphView, GraphScale, HistoryConfig};
 use crate::consts::*;
+use crate::history::History;
+use crate::render::graph::{draw_percent_axis, draw_time_axis, Scale};
 use crate::render::{color::*, executer::manager::*, meter::*};
 use crate::resource::cpu;
 use crate::symbol::block::lv;
+use crate::util::firstn_chars;
 use ncurses::*;
 
-static MAXBUFSZ: usize = 300; // XXX should decide dynamically.
 static GRAPH_STYLE: &str = "brail"; // XXX should be configurable.
+static PANEL_MIN_HEIGHT: i32 = 3; // label and 2 rows of graph
+static PANEL_MIN_WIDTH: i32 = 16;
+static STEP: f64 = 50.0; // [%] step of the upper limit in auto scale.
+
+// colours of lines in overlay view, used in turn.
+static LINE_COLORS: [i16; 6] = [
+  cpair::DEFAULT,
+  cpair::PAIR_COMM,
+  cpair::PAIR_CUTE,
+  cpair::PAIR_MED_DANGER,
+  cpair::PAIR_HEAD,
+  cpair::PAIR_DANGER,
+];
+
+// cores plotted in overlay and multiple views.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoreSelection {
+  All,
+  Cores(Vec<u32>),
+  Busiest(usize), // N cores with the highest usage in the visible range.
+}
 
 pub struct CpuGraph {
   pub height: i32,
   pub width: i32,
   pub win: WINDOW,
-  history: Vec<f64>,  // ring-buffer for history of CPU usage
-  cur_hist_ix: usize, // always points to newly recorded value of history
-  max_percent: f64,   // [0.0, 1.0]
+  history: History<f64>,                  // [%] of CPU usage
+  core_history: Vec<(u32, History<f64>)>, // [%] of each core
+  history_config: HistoryConfig,
+  config: GraphScale,
+  scale: Scale, // [%]
+  view: CpuGraphView,
+  selection: CoreSelection,
 }
 
 impl CpuGraph {
-  pub fn set_cpu(&mut self, acpu: &cpu::Cpu) {
-    self.cur_hist_ix += 1;
-    if self.cur_hist_ix >= MAXBUFSZ {
-      self.cur_hist_ix %= MAXBUFSZ;
+  pub fn set_cpu(&mut self, acpu: &cpu::Cpu, update_interval: f64) {
+    self.history.push(acpu.percent(), update_interval);
+  }
+
+  // record each core, over the same interval as `set_cpu()`.
+  // offline cores are recorded as idle, and removed cores are forgotten.
+  pub fn set_cores(&mut self, cpus: &[cpu::Cpu], update_interval: f64) {
+    self
+      .core_history
+      .retain(|(id, _)| cpus.iter().any(|c| c.id == *id));
+    for c in cpus {
+      let ix = match self.core_history.iter().position(|(id, _)| *id == c.id) {
+        Some(ix) => ix,
+        None => {
+          let mut history = History::new(&self.history_config, update_interval);
+          history.fit(self.width.max(0) as usize);
+          self.core_history.push((c.id, history));
+          self.core_history.len() - 1
+        }
+      };
+      let percent = if c.is_online() { c.percent() } else { 0.0 };
+      self.core_history[ix].1.push(percent, update_interval);
+    }
+    self.core_history.sort_by_key(|(id, _)| *id);
+  }
+
+  pub fn set_view(&mut self, view: CpuGraphView) {
+    self.view = view;
+    self.render();
+  }
+
+  // Average -> Overlay -> Multiple -> Average
+  pub fn toggle_view(&mut self) -> CpuGraphView {
+    use CpuGraphView::*;
+    let view = match self.view {
+      Average => Overlay,
+      Overlay => Multiple,
+      Multiple => Average,
+    };
+    self.set_view(view);
+    view
+  }
+
+  // selecting cores switches the average view to the overlay view.
+  pub fn select(&mut self, selection: CoreSelection) {
+    self.selection = selection;
+    if self.view == CpuGraphView::Average {
+      self.view = CpuGraphView::Overlay;
     }
+    self.render();
+  }
 
-    self.history[self.cur_hist_ix] = acpu.percent();
+  // ids of cores to plot with `size` entries of history, in the order of ids.
+  fn selected_cores(&self, size: usize) -> Vec<(u32, Vec<f64>)> {
+    let all = self
+      .core_history
+      .iter()
+      .map(|(id, history)| (*id, history.recent(size)));
+    match &self.selection {
+      CoreSelection::All => all.collect(),
+      CoreSelection::Cores(ids) => all.filter(|(id, _)| ids.contains(id)).collect(),
+      CoreSelection::Busiest(n) => {
+        let mut cores: Vec<(u32, Vec<f64>)> = all.collect();
+        let mean = |h: &[f64]| h.iter().sum::<f64>() / std::cmp::max(h.len(), 1) as f64;
+        cores.sort_by(|(_, a), (_, b)| mean(b).total_cmp(&mean(a)));
+        cores.truncate(*n);
+        cores.sort_by_key(|(id, _)| *id);
+        cores
+      }
+    }
   }
 
   fn draw_single_bar(&self, bar: &str, y_bottom: i32, x: i32) {
     let max_height = self.height - 2;
-    let threshold = (max_height as f64 * CPUUSAGE_MED_DANGER) as usize;
+    let danger = self.scale.ratio(CPUUSAGE_MED_DANGER * 100.0);
+    let threshold = (max_height as f64 * danger) as usize;
     // draw from bottom.
     for (i, c) in bar.chars().enumerate() {
-      if self.max_percent > CPUUSAGE_MED_DANGER && threshold <= i {
+      if danger < 1.0 && threshold <= i {
         mvwaddstr_color(
           self.win,
           y_bottom - i as i32,
@@ -61,48 +154,42 @@ impl CpuGraph {
   // returns latest history whose size is decided by self.width.
   // oldest entry is at index 0.
   fn get_recent_history(&self, size: usize) -> Vec<f64> {
-    let mut res = vec![0.0; size];
-    let start = self.cur_hist_ix;
-    for i in (0..size).rev() {
-      res[i] = self.history[(start + MAXBUFSZ - i) % MAXBUFSZ];
-    }
-
-    res.reverse();
-    res
+    self.history.recent(size)
   }
 
   fn get_bar(&self, maxheight: i32, percent: f64) -> String {
-    lv::get_bar(maxheight, percent / self.max_percent / 100.0)
+    lv::get_bar(maxheight, self.scale.ratio(percent))
   }
 
   fn update_upper_limit(&mut self, recent_hists: &[f64]) {
     let max_percent = recent_hists.iter().fold(0.0, |a, b| b.max(a));
-    self.max_percent = if max_percent >= 50.0 { 1.0 } else { 0.5 }
+    self.scale = Scale::fit(&self.config, max_percent, STEP, Some(100.0));
   }
-}
 
-impl Meter for CpuGraph {
-  fn render(&mut self) {
-    use crate::symbol::brail;
-    let win = self.win;
-    // erase and draw box
-    werase(win);
-    box_(win, 0, 0);
+  fn draw_axes(&self, size: usize) {
+    draw_percent_axis(self.win, 1, self.height / 2, &self.scale);
+    draw_time_axis(self.win, self.height, 3, self.history.span(size));
+  }
 
+  fn draw_average(&mut self) {
+    use crate::symbol::brail;
     // draw bars
     let x_start = 3;
     let width = self.width - 2 - x_start;
     let height = self.height - 2;
     let y_bottom = height;
 
-    let hists = self.get_recent_history(width as usize);
+    let hists = self.get_recent_history(std::cmp::max(width, 0) as usize);
     self.update_upper_limit(&hists);
-    let current_usage = hists.last().copied().unwrap();
     match GRAPH_STYLE {
       // line-chart
       "brail" => {
-        let brails =
-          brail::b32::get_brails_complement(height, 0.0, self.max_percent * 100.0, hists);
+        let brails = brail::b32::get_brails_complement(
+          height,
+          0.0,
+          self.scale.top(),
+          self.scale.map_all(&hists),
+        );
         for (i, brail) in brails.iter().enumerate() {
           self.draw_single_brail(brail, y_bottom, x_start + i as i32 + 1);
         }
@@ -116,55 +203,174 @@ impl Meter for CpuGraph {
       }
     }
 
-    // draw header
-    mvwaddstr_color(
-      win,
-      0,
-      1,
-      &format!(" CPU Usage ({:>3.2}) ", current_usage),
-      cpair::PAIR_HEAD,
-    );
-
-    // draw y-axes
-    mvwaddstr(win, 1, 1, &format!("{:>3}", self.max_percent * 100.0));
-    mvwaddstr(
-      win,
-      self.height / 2,
-      1,
-      &format!("{:>3}", self.max_percent * 0.5 * 100.0),
-    );
+    self.draw_axes(hists.len());
+  }
+
+  // returns legend of the header.
+  fn draw_overlay(&mut self) -> Vec<(String, i16)> {
+    use crate::symbol::brail::b32::get_brails_complement_multi_color;
+    let x_start = 3;
+    let width = self.width - 2 - x_start;
+    let height = self.height - 2;
+    let y_bottom = height;
+
+    let cores = self.selected_cores(std::cmp::max(width, 0) as usize);
+    let all: Vec<f64> = cores.iter().flat_map(|(_, h)| h.iter().copied()).collect();
+    self.update_upper_limit(&all);
+    let data: Vec<(Vec<f64>, i16)> = cores
+      .iter()
+      .enumerate()
+      .map(|(i, (_, h))| (self.scale.map_all(h), LINE_COLORS[i % LINE_COLORS.len()]))
+      .collect();
+    if !data.is_empty() && width > 0 {
+      let cols = get_brails_complement_multi_color(height, 0.0, self.scale.top(), data);
+      for (i, col) in cols.iter().enumerate() {
+        for (j, cc) in col.iter().enumerate() {
+          let (y, x) = (y_bottom - j as i32, x_start + i as i32 + 1);
+          mvwaddstr_color(self.win, y, x, &cc.ch.to_string(), cc.co);
+        }
+      }
+    }
+
+    self.draw_axes(cores.first().map_or(0, |(_, h)| h.len()));
+    cores
+      .iter()
+      .enumerate()
+      .map(|(i, (id, _))| (format!("cpu{}", id), LINE_COLORS[i % LINE_COLORS.len()]))
+      .collect()
+  }
+
+  // a graph of 0-100% per core, laid out in a grid.
+  // returns legend of the header, telling cores which do not fit.
+  fn draw_multiple(&mut self) -> Vec<(String, i16)> {
+    use crate::symbol::brail::b32::get_brails_complement;
+    let inner_h = self.height - 2;
+    let inner_w = self.width - 2;
+    if inner_h < PANEL_MIN_HEIGHT || inner_w < PANEL_MIN_WIDTH {
+      return vec![];
+    }
+    let max_rows = inner_h / PANEL_MIN_HEIGHT;
+    let max_cols = inner_w / PANEL_MIN_WIDTH;
+    let num_cores = self.selected_cores(0).len() as i32;
+    let cols = std::cmp::min((num_cores + max_rows - 1) / max_rows, max_cols).max(1);
+    let panel_w = inner_w / cols;
+
+    let cores = self.selected_cores(std::cmp::max(panel_w - 1, 0) as usize);
+    let num_shown = std::cmp::min(cores.len() as i32, max_rows * cols);
+    let rows = std::cmp::max((num_shown + cols - 1) / cols, 1);
+    let panel_h = inner_h / rows;
+    // every core on the same scale up to 100 %.
+    let scale = Scale::fit(&self.config, 100.0, STEP, Some(100.0));
+
+    for (k, (id, hists)) in cores.iter().take(num_shown as usize).enumerate() {
+      let (row, col) = (k as i32 / cols, k as i32 % cols);
+      let (y_top, x_left) = (1 + row * panel_h, 1 + col * panel_w);
+      let current = hists.last().copied().unwrap_or(0.0);
+      let color = if current >= CPUUSAGE_HIGH_DANGER * 100.0 {
+        cpair::PAIR_DANGER
+      } else if current >= CPUUSAGE_MED_DANGER * 100.0 {
+        cpair::PAIR_MED_DANGER
+      } else {
+        cpair::PAIR_COMM
+      };
+      let label = firstn_chars(
+        &format!("cpu{} {:>3.0}%", id, current),
+        std::cmp::max(panel_w - 1, 0) as usize,
+      );
+      mvwaddstr_color(self.win, y_top, x_left, &label, color);
+
+      let graph_h = panel_h - 1;
+      let brails = get_brails_complement(graph_h, 0.0, scale.top(), scale.map_all(hists));
+      for (i, brail) in brails.iter().enumerate() {
+        self.draw_single_brail(brail, y_top + graph_h, x_left + i as i32);
+      }
+    }
+
+    let hidden = cores.len() as i32 - num_shown;
+    if hidden > 0 {
+      vec![(format!("(+{} cores)", hidden), cpair::PAIR_DARK)]
+    } else {
+      vec![]
+    }
+  }
+}
+
+impl Meter for CpuGraph {
+  fn render(&mut self) {
+    let win = self.win;
+    // erase and draw box
+    werase(win);
+    draw_box(win);
+
+    let current_usage = self.history.last();
+    let legend = match self.view {
+      CpuGraphView::Average => {
+        self.draw_average();
+        vec![]
+      }
+      CpuGraphView::Overlay => self.draw_overlay(),
+      CpuGraphView::Multiple => self.draw_multiple(),
+    };
+
+    // draw header, followed by cores in the colour of their line.
+    let header = format!(" CPU Usage ({:>3.2}) ", current_usage);
+    mvwaddstr_color(win, 0, 1, &header, cpair::PAIR_HEAD);
+    let mut cx = 1 + header.len() as i32;
+    for (s, color) in legend {
+      let s = firstn_chars(
+        &format!("{} ", s),
+        std::cmp::max(self.width - 1 - cx, 0) as usize,
+      );
+      mvwaddstr_color(win, 0, cx, &s, color);
+      cx += s.len() as i32;
+    }
 
     wrefresh(win);
   }
 
   fn init_meter(
     _parent: ncurses::WINDOW,
-    _wm: &mut WinManager,
+    wm: &mut WinManager,
     height: i32,
     width: i32,
     y: i32,
     x: i32,
   ) -> Self {
-    let height = std::cmp::min(height, MAXBUFSZ as i32);
     let win = newwin(height, width, y, x);
     wattron(win, COLOR_PAIR(cpair::DEFAULT));
     wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
-    box_(win, 0, 0);
+    draw_box(win);
     wrefresh(win);
 
+    let mut history = History::new(&wm.config.history, wm.update_interval);
+    history.fit(width.max(0) as usize);
+
+    let config = &wm.config.cpu;
     CpuGraph {
       width,
       height,
       win,
-      history: vec![0.0; MAXBUFSZ],
-      cur_hist_ix: 0,
-      max_percent: 0.5,
+      history,
+      core_history: vec![],
+      history_config: wm.config.history,
+      config: config.scale.clone(),
+      scale: Scale::fit(&config.scale, 0.0, STEP, Some(100.0)),
+      view: config.graph,
+      selection: if config.graph_cpus.is_empty() {
+        CoreSelection::All
+      } else {
+        CoreSelection::Cores(config.graph_cpus.clone())
+      },
     }
   }
 
   fn resize(&mut self, _parent: WINDOW, height: i32, width: i32, y: i32, x: i32) {
     self.height = height;
     self.width = width;
+    self.history.fit(width.max(0) as usize);
+    for (_, history) in self.core_history.iter_mut() {
+      history.fit(width.max(0) as usize);
+    }
     wresize(self.win, height, width);
     werase(self.win);
     mvwin(self.win, y, x);
@@ -173,5 +379,8 @@ impl Meter for CpuGraph {
     wrefresh(self.win);
   }
 
-  fn handle_click(&mut self, _y: i32, _x: i32) {}
+  // clicking the graph switches the view.
+  fn handle_click(&mut self, _y: i32, _x: i32) {
+    self.toggle_view();
+  }
 }
@@ -2,24 +2,34 @@
 
 Implementation of CPUManager.
 CPUManager manages CPUMeters.
+CPUs are grouped by NUMA node and package, and SMT siblings are placed side by side.
 
 *******/
 
 use super::cpumeter;
-use crate::render::{color, executer::manager::*, meter::*};
-use crate::resource::cpu;
+use crate::render::{color, color::*, executer::manager::*, meter::*};
+use crate::resource::{cpu, topology};
+use crate::util::{firstn_chars, human_size};
 use ncurses::*;
 
+type Header = (i32, topology::CpuGroup); // (y, group)
+
 #[derive(Debug)]
 pub struct CpuManager {
   pub cpumeters: Vec<cpumeter::CpuMeter>,
   pub height: i32,
   pub width: i32,
   pub win: WINDOW,
+  pub cpus_generation: u32, // `ProcList::cpus_generation` which the layout is based on.
+  ids: Vec<u32>,            // id of each cpumeter
+  groups: Vec<topology::CpuGroup>,
+  positions: Vec<(i32, i32)>, // (y, x) of each cpumeter
+  headers: Vec<Header>,       // empty if there is only one group, or no room for them.
+  group_lines: Vec<String>,   // summary of each group shown in headers
 }
 
 impl CpuManager {
-  pub fn set_cpus(&mut self, cpus: &[cpu::Cpu]) {
+  pub fn set_cpus(&mut self, cpus: &[cpu::Cpu], topology: &topology::Topology) {
     for (i, cpu) in cpus
       .iter()
       .enumerate()
@@ -27,11 +37,86 @@ impl CpuManager {
     {
       self.cpumeters[i].set_cpu(*cpu);
     }
+
+    // "node0 pkg0  cpu  34.2%  mem 2.1G/5.3G"
+    self.group_lines = self
+      .headers
+      .iter()
+      .map(|(_, group)| {
+        let percents: Vec<f64> = cpus
+          .iter()
+          .filter(|c| group.cpus.contains(&c.id))
+          .map(|c| c.percent())
+          .collect();
+        let load = percents.iter().sum::<f64>() / std::cmp::max(percents.len(), 1) as f64;
+        let mut s = match group.node {
+          Some(node) => format!("node{} pkg{}", node, group.package),
+          None => format!("pkg{}", group.package),
+        };
+        s += &format!("  cpu {:>5.1}%", load);
+        if let Some(node) = group.node.and_then(|n| topology.node(n)) {
+          if node.mem_total > 0 {
+            s += &format!(
+              "  mem {}/{}",
+              human_size(node.mem_used() as f64 * 1024.0),
+              human_size(node.mem_total as f64 * 1024.0)
+            );
+          }
+        }
+        s
+      })
+      .collect();
+  }
+
+  // re-create cpumeters after cpus are added, removed, onlined or offlined.
+  pub fn relayout(&mut self, wm: &mut WinManager) {
+    let stacked = self.cpumeters.first().map(|m| m.stacked);
+    for meter in self.cpumeters.iter_mut() {
+      meter.destroy();
+    }
+    werase(self.win);
+
+    let ids: Vec<u32> = wm.plist.cpus.iter().map(|c| c.id).collect();
+    let groups = wm.plist.topology.groups();
+    let (positions, headers) = arrange(&ids, &groups, self.width / 2, self.height);
+    self.cpumeters = init_meters(self.win, wm, &positions, self.width);
+    if let Some(stacked) = stacked {
+      for meter in self.cpumeters.iter_mut() {
+        meter.stacked = stacked;
+      }
+    }
+    self.ids = ids;
+    self.groups = groups;
+    self.positions = positions;
+    self.headers = headers;
+    self.group_lines.clear();
+    self.cpus_generation = wm.plist.cpus_generation;
+    wrefresh(self.win);
+  }
+
+  // toggle stacked bars of each class of time.
+  pub fn toggle_stacked(&mut self) -> bool {
+    let stacked = !self.cpumeters.first().is_some_and(|m| m.stacked);
+    for meter in self.cpumeters.iter_mut() {
+      meter.stacked = stacked;
+    }
+    self.render();
+    stacked
   }
 }
 
 impl Meter for CpuManager {
   fn render(&mut self) {
+    for ((y, _), line) in self.headers.iter().zip(self.group_lines.iter()) {
+      wmove(self.win, *y, 0);
+      wclrtoeol(self.win);
+      let s = firstn_chars(line, std::cmp::max(self.width, 0) as usize);
+      mvwaddstr_color(self.win, *y, 0, &s, cpair::PAIR_HEAD);
+    }
+    if !self.headers.is_empty() {
+      wrefresh(self.win);
+    }
+
     for i in 0..self.cpumeters.len() {
       self.cpumeters[i].render();
     }
@@ -54,13 +139,22 @@ impl Meter for CpuManager {
     );
 
     // init each windows of cpumeter inside parent window.
-    let cpumeters = init_meters(win, wm, height, width);
+    let ids: Vec<u32> = wm.plist.cpus.iter().map(|c| c.id).collect();
+    let groups = wm.plist.topology.groups();
+    let (positions, headers) = arrange(&ids, &groups, width / 2, height);
+    let cpumeters = init_meters(win, wm, &positions, width);
 
     CpuManager {
       cpumeters,
       width,
       height,
       win,
+      cpus_generation: wm.plist.cpus_generation,
+      ids,
+      groups,
+      positions,
+      headers,
+      group_lines: vec![],
     }
   }
 
@@ -71,43 +165,80 @@ impl Meter for CpuManager {
     wresize(self.win, self.height, self.width);
     werase(self.win);
     mvwin(self.win, y, x);
+    // headers are shown or hidden by the new height.
+    let (positions, headers) = arrange(&self.ids, &self.groups, self.width / 2, self.height);
+    if headers.len() != self.headers.len() {
+      self.group_lines.clear();
+    }
+    self.positions = positions;
+    self.headers = headers;
+
     // moving subwindows is not recommended (though i don't know why).
     // hence, destroy and re-create subwins.
     for i in 0..self.cpumeters.len() {
-      let (y, x) = pos_win_start(i as u32, self.width / 2);
+      let (y, x) = self.positions[i];
       self.cpumeters[i].recreate(self.win, 1, self.width / 2, y, x);
     }
 
     wrefresh(self.win);
   }
 
-  fn handle_click(&mut self, _y: i32, _x: i32) {}
+  // clicking any CPU toggles stacked bars.
+  fn handle_click(&mut self, _y: i32, _x: i32) {
+    self.toggle_stacked();
+  }
 }
 
 fn init_meters(
   parent: WINDOW,
   wm: &mut WinManager,
-  _height: i32,
+  positions: &[(i32, i32)],
   width: i32,
 ) -> Vec<cpumeter::CpuMeter> {
-  let mut meters = vec![];
-  let num_cpu = wm.plist.cpus.len();
   let width = width / 2;
   let height = 1;
 
-  for i in 0..num_cpu {
-    let (y, x) = pos_win_start(wm.plist.cpus[i].id, width);
-    let meter = cpumeter::CpuMeter::init_meter(parent, wm, height, width, y, x);
-    meters.push(meter);
+  positions
+    .iter()
+    .map(|&(y, x)| cpumeter::CpuMeter::init_meter(parent, wm, height, width, y, x))
+    .collect()
+}
+
+// returns (y, x) of each CPU in `ids`, and headers of groups.
+// `width` is a width of each cpumeter, not a screen-width (manager width).
+// headers are dropped if they don't fit in `height` with all the CPUs.
+fn arrange(
+  ids: &[u32],
+  groups: &[topology::CpuGroup],
+  width: i32,
+  height: i32,
+) -> (Vec<(i32, i32)>, Vec<Header>) {
+  let mut positions = vec![(0, 0); ids.len()];
+  let mut headers = vec![];
+  let num_rows: i32 = groups.iter().map(|g| (g.cpus.len() as i32 + 1) / 2).sum();
+  let show_headers = groups.len() > 1 && num_rows + groups.len() as i32 <= height;
+
+  let mut y = 0;
+  for group in groups {
+    if show_headers {
+      headers.push((y, group.clone()));
+      y += 1;
+    }
+    for (k, id) in group.cpus.iter().enumerate() {
+      if let Some(i) = ids.iter().position(|i| i == id) {
+        positions[i] = pos_win_start(k as u32, width, y);
+      }
+    }
+    y += (group.cpus.len() as i32 + 1) / 2;
   }
 
-  meters
+  (positions, headers)
 }
 
-// `width` is a width of each cpumeter, not a screen-width (manager width).
-fn pos_win_start(id: u32, width: i32) -> (i32, i32) {
-  let x = if id % 2 == 0 { 0 } else { width };
-  let y = id as i32 / 2;
+// position of `k`th CPU in a group which starts at `y_start`.
+fn pos_win_start(k: u32, width: i32, y_start: i32) -> (i32, i32) {
+  let x = if k.is_multiple_of(2) { 0 } else { width };
+  let y = y_start + k as i32 / 2;
 
   (y, x)
 }
@@ -5,16 +5,33 @@ CPUMeter shows information about each CPUs.
 
 *******/
 
-use crate::render::{color, executer::manager::WinManager, meter::*};
-use crate::resource::cpu;
+use crate::render::{color, color::*, executer::manager::WinManager, meter::*};
+use crate::resource::cpu::{self, CpuFreq, CpuTimeClass};
 use ncurses::*;
 
+static STEAL_WARN: f64 = 10.0; // [%]
+static IOWAIT_WARN: f64 = 20.0; // [%]
+static MIN_BAR_WIDTH: i32 = 10; // frequency and governor are hidden if the bar gets narrower.
+
 #[derive(Debug)]
 pub struct CpuMeter {
   pub height: i32,
   pub width: i32,
   win: WINDOW,
   cpu: Option<cpu::Cpu>,
+  pub stacked: bool, // colour each class of time in the bar.
+}
+
+fn class_color(class: CpuTimeClass) -> i16 {
+  use CpuTimeClass::*;
+  match class {
+    Nice => cpair::PAIR_COMM,
+    User => cpair::PAIR_HEAD,
+    System => cpair::PAIR_DANGER,
+    Irq | SoftIrq => cpair::PAIR_MED_DANGER,
+    Steal | Guest => cpair::PAIR_CUTE,
+    IoWait => cpair::PAIR_DARK,
+  }
 }
 
 impl Meter for CpuMeter {
@@ -31,22 +48,72 @@ impl Meter for CpuMeter {
       }
     };
 
-    let max_width = self.width - "cpuxx []".len() as i32 - 1;
-    let percent = cpu.percent() * 0.01;
-    let divs = (0..((max_width as f64 * percent) as u32))
-      .map(|_| "|")
-      .collect::<String>();
-    let spaces = (0..(max_width - divs.len() as i32))
-      .map(|_| " ")
-      .collect::<String>();
-    mvwprintw(win, 0, 0, &format!("cpu{:>2} [{}{}]", cpu.id, divs, spaces));
+    // offline cpus are shown dark with an empty bar.
+    if !cpu.is_online() {
+      let max_width = std::cmp::max(self.width - "cpuxx []".len() as i32 - 1, 0) as usize;
+      let s = format!("cpu{:>2} [{:^mw$}]", cpu.id, "offline", mw = max_width);
+      mvwaddstr_color(win, 0, 0, &s, cpair::PAIR_DARK);
+      wrefresh(win);
+      return;
+    }
+
+    // " 2400MHz sched"
+    let mut suffix = match cpu.freq {
+      CpuFreq::Valid(freq) => format!(" {:>4}MHz", freq / 1000),
+      CpuFreq::Absent => String::new(),
+      CpuFreq::Offline => " offline".into(),
+    };
+    if let Some(governor) = cpu.governor {
+      suffix += &format!(" {:<5}", governor);
+    }
+    let mut max_width = self.width - "cpuxx []".len() as i32 - 1;
+    if max_width - suffix.len() as i32 >= MIN_BAR_WIDTH {
+      max_width -= suffix.len() as i32;
+    } else {
+      suffix.clear();
+    }
+    let max_width = std::cmp::max(max_width, 0);
+
+    // highlight cores starved by the hypervisor or waiting for IO.
+    let label_color = if cpu.percent_of(CpuTimeClass::Steal) >= STEAL_WARN {
+      cpair::PAIR_DANGER
+    } else if cpu.percent_of(CpuTimeClass::IoWait) >= IOWAIT_WARN {
+      cpair::PAIR_MED_DANGER
+    } else {
+      cpair::DEFAULT
+    };
+    mvwaddstr_color(win, 0, 0, &format!("cpu{:>2}", cpu.id), label_color);
+    mvwaddstr(win, 0, 5, " [");
+
+    if self.stacked {
+      // cumulative positions avoid rounding errors piling up.
+      let mut cum = 0.0;
+      let mut x = 7;
+      for (class, percent) in cpu.breakdown().iter() {
+        cum += percent;
+        let end = 7 + (max_width as f64 * (cum * 0.01).min(1.0)).round() as i32;
+        if end > x {
+          let divs = "|".repeat((end - x) as usize);
+          mvwaddstr_color(win, 0, x, &divs, class_color(*class));
+          x = end;
+        }
+      }
+      mvwaddstr(win, 0, x, &" ".repeat((7 + max_width - x) as usize));
+    } else {
+      let percent = cpu.percent() * 0.01;
+      let divs = "|".repeat((max_width as f64 * percent) as usize);
+      let spaces = " ".repeat(max_width as usize - divs.len());
+      mvwaddstr(win, 0, 7, &format!("{}{}", divs, spaces));
+    }
+    mvwaddstr(win, 0, 7 + max_width, "]");
+    mvwaddstr_color(win, 0, 8 + max_width, &suffix, cpair::PAIR_DARK);
 
     wrefresh(win);
   }
 
   fn init_meter(
     parent: WINDOW,
-    _wm: &mut WinManager,
+    wm: &mut WinManager,
     height: i32,
     width: i32,
     y: i32,
@@ -58,6 +125,7 @@ impl Meter for CpuMeter {
       width,
       win,
       cpu: None,
+      stacked: wm.config.cpu.stacked,
     }
   }
 
@@ -80,6 +148,11 @@ impl CpuMeter {
     self.cpu = Some(cpu);
   }
 
+  pub fn destroy(&mut self) {
+    werase(self.win);
+    delwin(self.win);
+  }
+
   pub fn recreate(&mut self, parent: WINDOW, height: i32, width: i32, y: i32, x: i32) {
     self.width = width;
     self.height = height;
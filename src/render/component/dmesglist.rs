@@ -1,50 +1,301 @@
 /*****
 
 Implementation of DmesgList.
-DmesgList just shows the output from /dev/kmsg
+DmesgList shows the output from /dev/kmsg (or the journal) colored by its priority.
+It can be scrolled back, and filtered by level or pattern.
+Notable kernel events (OOM kill, segfault, ...) are tagged and counted.
 
 *******/
 
 use crate::render::{color::*, executer::manager::WinManager, meter::*};
-use crate::resource::dmesg;
+use crate::resource::dmesg::{self, LogLevel};
+use crate::resource::kevent::{self, EventSummary};
+use crate::util::{firstn_chars, local_time_string};
 use ncurses::*;
+use regex::Regex;
+use std::time::Instant;
 
 static MAXBUFSZ: usize = 300; // XXX should decide dynamically.
+static SCROLL_STEP: i32 = 3; // # of lines scrolled by a mouse wheel.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeFormat {
+  Boot, // seconds since boot, as dmesg does.
+  Wall, // local wall-clock time.
+}
+
+#[derive(Clone, Debug)]
+pub enum Pattern {
+  Substring(String),
+  Regex(Regex),
+  Nothing,
+}
+
+impl Pattern {
+  // returns byte range of the first match in `s`.
+  pub fn find(&self, s: &str) -> Option<(usize, usize)> {
+    use Pattern::*;
+    match self {
+      Substring(sub) => s.find(sub.as_str()).map(|start| (start, start + sub.len())),
+      Regex(re) => re.find(s).map(|m| (m.start(), m.end())),
+      Nothing => None,
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct DmesgFilter {
+  pub max_level: Option<LogLevel>, // show only logs whose priority is equal or higher than this.
+  pub pattern: Pattern,
+}
+
+impl Default for DmesgFilter {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl DmesgFilter {
+  pub fn new() -> Self {
+    Self {
+      max_level: None,
+      pattern: Pattern::Nothing,
+    }
+  }
+
+  pub fn is_match(&self, line: &dmesg::KmsgLine) -> bool {
+    if let Some(max_level) = self.max_level {
+      if line.priority() > max_level {
+        return false;
+      }
+    }
+    match self.pattern {
+      Pattern::Nothing => true,
+      _ => self.pattern.find(&line.log).is_some(),
+    }
+  }
+}
 
 pub struct DmesgList {
   pub height: i32,
   pub width: i32,
   pub win: WINDOW,
-  history: Vec<Option<dmesg::KmsgLine>>, // ring-buffer for history. 0 is latest.
-  cur_hist_ix: usize,                    // always points to newly recorded value of history
+  lines: Vec<dmesg::KmsgLine>, // oldest entry is at index 0.
+  scroll: usize,               // # of filtered lines hidden below. 0 follows the latest.
+  filter: DmesgFilter,
+  time_format: TimeFormat,
+  btime: i64,
+  source: String, // name of the log source.
+  pub events: EventSummary,
+  is_first_read: bool, // events in the first read happened before xtop started.
+  clicked_pid: Option<i32>, // task of the event line clicked last.
+  error: Option<String>, // error of the last read from the source.
 }
 
 impl DmesgList {
-  pub fn set_dmesg(&mut self, dmesgs: Vec<dmesg::KmsgLine>) {
-    for i in 0..self.history.len() {
-      self.history[i] = None;
+  // append records newly read.
+  pub fn append_dmesg(&mut self, dmesgs: Vec<dmesg::KmsgLine>) {
+    // keep the view at the same place while scrolled back.
+    if self.scroll > 0 {
+      let num_new = dmesgs
+        .iter()
+        .filter(|l| !l.is_continuation() && self.filter.is_match(l))
+        .count();
+      self.scroll += num_new;
+    }
+
+    for dmesg in &dmesgs {
+      if let Some(event) = kevent::classify(dmesg) {
+        self.events.add(&event);
+        if !self.is_first_read {
+          self.events.last_arrival = Some(Instant::now());
+        }
+      }
+    }
+    self.is_first_read = false;
+
+    for dmesg in dmesgs {
+      dmesg::push_merging(&mut self.lines, dmesg);
+    }
+    if self.lines.len() > MAXBUFSZ {
+      let num_old = self.lines.len() - MAXBUFSZ;
+      self.lines.drain(0..num_old);
+    }
+    self.clamp_scroll();
+  }
+
+  // drop all the lines read from the previous source.
+  pub fn set_source(&mut self, source: &str) {
+    self.source = source.into();
+    self.lines.clear();
+    self.scroll = 0;
+    self.events = EventSummary::default();
+    self.is_first_read = true;
+    self.error = None;
+    self.render();
+  }
+
+  pub fn set_error(&mut self, error: Option<String>) {
+    self.error = error;
+  }
+
+  pub fn set_level(&mut self, level: Option<LogLevel>) {
+    self.filter.max_level = level;
+    self.scroll = 0;
+    self.render();
+  }
+
+  pub fn set_pattern(&mut self, pattern: Pattern) {
+    self.filter.pattern = pattern;
+    self.scroll = 0;
+    self.render();
+  }
+
+  pub fn set_time_format(&mut self, time_format: TimeFormat) {
+    self.time_format = time_format;
+    self.render();
+  }
+
+  pub fn toggle_time_format(&mut self) -> TimeFormat {
+    let next = match self.time_format {
+      TimeFormat::Boot => TimeFormat::Wall,
+      TimeFormat::Wall => TimeFormat::Boot,
+    };
+    self.set_time_format(next);
+    next
+  }
+
+  // returns the PID of the task in the event line clicked last, if any.
+  pub fn take_clicked_pid(&mut self) -> Option<i32> {
+    self.clicked_pid.take()
+  }
+
+  fn filtered_lines(&self) -> Vec<&dmesg::KmsgLine> {
+    self
+      .lines
+      .iter()
+      .filter(|l| self.filter.is_match(l))
+      .collect()
+  }
+
+  fn num_visible_lines(&self) -> usize {
+    std::cmp::max(self.height - 2, 0) as usize
+  }
+
+  // returns the line shown at row `y` of the window.
+  fn line_at(&self, y: i32) -> Option<&dmesg::KmsgLine> {
+    let height = self.height - 2;
+    if y < 1 || y > height {
+      return None;
+    }
+    let lines = self.filtered_lines();
+    let end = lines.len() - std::cmp::min(self.scroll, lines.len());
+    let index = end.checked_sub((height - y + 1) as usize)?;
+    Some(lines[index])
+  }
+
+  fn clamp_scroll(&mut self) {
+    let num_lines = self.filtered_lines().len();
+    let max_scroll = num_lines.saturating_sub(self.num_visible_lines());
+    self.scroll = std::cmp::min(self.scroll, max_scroll);
+  }
+
+  fn timestamp_string(&self, line: &dmesg::KmsgLine) -> String {
+    match self.time_format {
+      TimeFormat::Boot => format!(
+        "[{:>5}.{:03}]",
+        line.timestamp / 1_000_000,
+        (line.timestamp % 1_000_000) / 1000
+      ),
+      TimeFormat::Wall => format!(
+        "[{}]",
+        local_time_string(self.btime + (line.timestamp / 1_000_000) as i64)
+      ),
     }
-    for (i, dmesg) in dmesgs.iter().rev().enumerate() {
-      if i >= MAXBUFSZ {
+  }
+
+  fn draw_line(&self, line: &dmesg::KmsgLine, y: i32, x: i32, width: i32) {
+    let win = self.win;
+    let mut cx = x;
+    let max_x = x + width;
+
+    let ts = self.timestamp_string(line);
+    mvwaddstr_color(
+      win,
+      y,
+      cx,
+      &firstn_chars(&ts, (max_x - cx) as usize),
+      cpair::PAIR_DARK,
+    );
+    cx += ts.chars().count() as i32 + 1;
+    if cx >= max_x {
+      return;
+    }
+
+    if let Some(event) = kevent::classify(line) {
+      let tag = format!("[{}]", event.kind);
+      let tag = firstn_chars(&tag, (max_x - cx) as usize);
+      wattron(win, A_REVERSE());
+      mvwaddstr_color(win, y, cx, &tag, cpair::PAIR_DANGER);
+      wattroff(win, A_REVERSE());
+      cx += tag.chars().count() as i32 + 1;
+      if cx >= max_x {
+        return;
+      }
+    }
+
+    let color = level_color(line.priority());
+    let log = &line.log;
+    let (pre, matched, post) = match self.filter.pattern.find(log) {
+      Some((start, end)) => (&log[..start], &log[start..end], &log[end..]),
+      None => (log.as_str(), "", ""),
+    };
+    for (s, attr) in [(pre, 0), (matched, A_REVERSE()), (post, 0)] {
+      if cx >= max_x {
         break;
       }
-      self.history[i] = Some(dmesg.clone());
+      let s = firstn_chars(s, (max_x - cx) as usize);
+      wattron(win, attr);
+      mvwaddstr_color(win, y, cx, &s, color);
+      wattroff(win, attr);
+      cx += s.chars().count() as i32;
     }
   }
 
-  // 0 is latest.
-  // returned size can be smaller than `size`.
-  fn get_recent_history(&self, size: usize) -> Vec<&dmesg::KmsgLine> {
-    let mut res = vec![];
-    let start = self.cur_hist_ix;
-    for i in (0..size).rev() {
-      match &self.history[(start + MAXBUFSZ - i) % MAXBUFSZ] {
-        Some(h) => res.push(h),
-        None => continue,
-      };
+  fn draw_header(&self, num_lines: usize) {
+    let mut s = format!(" {} ", self.source);
+    if let Some(level) = self.filter.max_level {
+      s += &format!("[<={}] ", level);
     }
+    match &self.filter.pattern {
+      Pattern::Substring(sub) => s += &format!("\"{}\" ", sub),
+      Pattern::Regex(re) => s += &format!("/{}/ ", re),
+      Pattern::Nothing => {}
+    }
+    if self.scroll > 0 {
+      s += &format!("(-{}/{}) ", self.scroll, num_lines);
+    }
+    mvwaddstr_color(self.win, 0, 1, &s, cpair::PAIR_HEAD);
 
-    res
+    if let Some(error) = &self.error {
+      let x = 1 + s.chars().count() as i32;
+      let error = firstn_chars(
+        &format!(" {} ", error),
+        (self.width - x - 1).max(0) as usize,
+      );
+      mvwaddstr_color(self.win, 0, x, &error, cpair::PAIR_DANGER);
+    }
+  }
+}
+
+fn level_color(level: LogLevel) -> i16 {
+  use LogLevel::*;
+  match level {
+    Emerg | Alert | Crit | Err => cpair::PAIR_DANGER,
+    Warning => cpair::PAIR_MED_DANGER,
+    Notice => cpair::PAIR_COMM,
+    Info => cpair::DEFAULT,
+    Debug => cpair::PAIR_DARK,
   }
 }
 
@@ -53,7 +304,7 @@ impl Meter for DmesgList {
     let win = self.win;
     // erase and draw box
     werase(win);
-    box_(win, 0, 0);
+    draw_box(win);
 
     // write logs
     let x_start = 1;
@@ -63,27 +314,22 @@ impl Meter for DmesgList {
       return;
     }
     let y_bottom = height;
-    let hists = self.get_recent_history(height as usize);
-    for (i, hist) in hists.iter().enumerate() {
-      let mut cx = x_start;
-      for c in hist.log.chars() {
-        if cx >= width {
-          break;
-        }
-        mvwaddstr(self.win, y_bottom - i as i32, cx, &c.to_string());
-        cx += 1;
-      }
+    let lines = self.filtered_lines();
+    let end = lines.len() - std::cmp::min(self.scroll, lines.len());
+    let start = end.saturating_sub(height as usize);
+    for (i, line) in lines[start..end].iter().rev().enumerate() {
+      self.draw_line(line, y_bottom - i as i32, x_start, width);
     }
 
     // draw header
-    mvwaddstr_color(win, 0, 1, " dmesg ", cpair::PAIR_HEAD);
+    self.draw_header(lines.len());
 
     wrefresh(win);
   }
 
   fn init_meter(
     _parent: WINDOW,
-    _wm: &mut WinManager,
+    wm: &mut WinManager,
     height: i32,
     width: i32,
     y: i32,
@@ -93,15 +339,23 @@ impl Meter for DmesgList {
     let win = newwin(height, width, y, x);
     wattron(win, COLOR_PAIR(cpair::DEFAULT));
     wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
-    box_(win, 0, 0);
+    draw_box(win);
     wrefresh(win);
 
     DmesgList {
       width,
       height,
       win,
-      history: vec![None; MAXBUFSZ],
-      cur_hist_ix: 0,
+      lines: vec![],
+      scroll: 0,
+      filter: DmesgFilter::new(),
+      time_format: TimeFormat::Boot,
+      btime: wm.plist.btime,
+      source: "dmesg".into(),
+      events: EventSummary::default(),
+      is_first_read: true,
+      clicked_pid: None,
+      error: None,
     }
   }
 
@@ -111,8 +365,33 @@ impl Meter for DmesgList {
     wresize(self.win, height, width);
     werase(self.win);
     mvwin(self.win, y, x);
+    self.clamp_scroll();
 
     self.render();
     wrefresh(self.win);
   }
+
+  // clicking the header toggles the format of timestamps,
+  // and clicking an event line selects the task of the event.
+  fn handle_click(&mut self, y: i32, _x: i32) {
+    if y == 0 {
+      self.toggle_time_format();
+      return;
+    }
+    self.clicked_pid = self
+      .line_at(y)
+      .and_then(kevent::classify)
+      .and_then(|event| event.pid);
+  }
+
+  fn handle_scroll(&mut self, y_diff: i32) {
+    let diff = y_diff.saturating_mul(SCROLL_STEP);
+    self.scroll = if diff < 0 {
+      self.scroll.saturating_add(diff.unsigned_abs() as usize)
+    } else {
+      self.scroll.saturating_sub(diff as usize)
+    };
+    self.clamp_scroll();
+    self.render();
+  }
 }
@@ -115,7 +115,7 @@ impl Meter for DockerMeter {
     }
 
     // draw header
-    box_(win, 0, 0);
+    draw_box(win);
     let s = format!(" Container ({} Running) ", self.containers.len());
     mvwaddstr_color(win, 0, 1, &s, cpair::PAIR_HEAD);
 
@@ -136,7 +136,7 @@ impl Meter for DockerMeter {
     let win = newwin(height, width, y, x);
     wattron(win, COLOR_PAIR(cpair::DEFAULT));
     wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
-    box_(win, 0, 0);
+    draw_box(win);
     wrefresh(win);
 
     DockerMeter {
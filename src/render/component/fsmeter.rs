@@ -0,0 +1,155 @@
+/*****
+
+Implementation of FsMeter.
+FsMeter shows the usage of bytes and inodes of each mounted filesystem.
+
+*******/
+
+use crate::render::{color::*, executer::manager::WinManager, meter::*};
+use crate::resource::fs::FsUsage;
+use crate::util::{firstn_chars, human_size};
+
+use ncurses::*;
+
+static DANGER_RATIO: f64 = 0.90;
+static MED_DANGER_RATIO: f64 = 0.75;
+static BAR_WIDTH: usize = 10;
+static FSTYPE_MAXLEN: usize = 7;
+
+pub struct FsMeter {
+  pub height: i32,
+  pub width: i32,
+  pub win: WINDOW,
+  usages: Vec<FsUsage>,
+  scroll: usize,
+}
+
+impl FsMeter {
+  pub fn set_usages(&mut self, usages: Vec<FsUsage>) {
+    self.usages = usages;
+    self.scroll = std::cmp::min(self.scroll, self.usages.len().saturating_sub(1));
+  }
+
+  fn draw_usage(&self, usage: &FsUsage, y: i32, width: i32) {
+    let ratio = usage.used_ratio();
+    let inodes = match usage.inodes_ratio() {
+      Some(r) => format!("{:>3.0}%", r * 100.0),
+      None => "   -".into(),
+    };
+    let worst = ratio.max(usage.inodes_ratio().unwrap_or(0.0));
+    let color = ratio_color(worst);
+
+    // right part: "ext4    12.3G/ 100G [||||      ]  12%  i 3%"
+    let num_bars = ((ratio * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+    let right = format!(
+      "{:<fw$} {:>6}/{:>6} [{}{}] {:>3.0}% i{}",
+      firstn_chars(&usage.mount.fstype, FSTYPE_MAXLEN),
+      human_size(usage.used as f64),
+      human_size(usage.size as f64),
+      "|".repeat(num_bars),
+      " ".repeat(BAR_WIDTH - num_bars),
+      ratio * 100.0,
+      inodes,
+      fw = FSTYPE_MAXLEN,
+    );
+    let name_width = std::cmp::max(width - right.len() as i32 - 1, 0) as usize;
+    let name = format!(
+      "{:<nw$}",
+      firstn_chars(&usage.mount.mount_point, name_width),
+      nw = name_width
+    );
+
+    mvwaddstr_color(self.win, y, 1, &name, cpair::PAIR_COMM);
+    let s = firstn_chars(
+      &right,
+      std::cmp::max(width - name_width as i32 - 1, 0) as usize,
+    );
+    mvwaddstr_color(self.win, y, 1 + name_width as i32 + 1, &s, color);
+  }
+}
+
+fn ratio_color(ratio: f64) -> i16 {
+  if ratio >= DANGER_RATIO {
+    cpair::PAIR_DANGER
+  } else if ratio >= MED_DANGER_RATIO {
+    cpair::PAIR_MED_DANGER
+  } else {
+    cpair::DEFAULT
+  }
+}
+
+impl Meter for FsMeter {
+  fn render(&mut self) {
+    let win = self.win;
+    werase(win);
+    draw_box(win);
+
+    // draw each filesystem
+    let width = self.width - 2;
+    let num_rows = std::cmp::max(self.height - 2, 0) as usize;
+    for (i, usage) in self
+      .usages
+      .iter()
+      .skip(self.scroll)
+      .take(num_rows)
+      .enumerate()
+    {
+      self.draw_usage(usage, 1 + i as i32, width);
+    }
+
+    // draw header
+    let s = if self.scroll > 0 {
+      format!(" Filesystem ({}, -{}) ", self.usages.len(), self.scroll)
+    } else {
+      format!(" Filesystem ({}) ", self.usages.len())
+    };
+    mvwaddstr_color(win, 0, 1, &s, cpair::PAIR_HEAD);
+
+    wrefresh(win);
+  }
+
+  fn init_meter(
+    _parent: WINDOW,
+    _wm: &mut WinManager,
+    height: i32,
+    width: i32,
+    y: i32,
+    x: i32,
+  ) -> Self {
+    let win = newwin(height, width, y, x);
+    wattron(win, COLOR_PAIR(cpair::DEFAULT));
+    wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
+    draw_box(win);
+    wrefresh(win);
+
+    FsMeter {
+      width,
+      height,
+      win,
+      usages: vec![],
+      scroll: 0,
+    }
+  }
+
+  fn resize(&mut self, _parent: WINDOW, height: i32, width: i32, y: i32, x: i32) {
+    self.height = height;
+    self.width = width;
+    wresize(self.win, height, width);
+    werase(self.win);
+    mvwin(self.win, y, x);
+
+    self.render();
+    wrefresh(self.win);
+  }
+
+  fn handle_scroll(&mut self, y_diff: i32) {
+    let max_scroll = self.usages.len().saturating_sub(1);
+    self.scroll = if y_diff < 0 {
+      self.scroll.saturating_sub(y_diff.unsigned_abs() as usize)
+    } else {
+      self.scroll.saturating_add(y_diff as usize)
+    }
+    .min(max_scroll);
+    self.render();
+  }
+}
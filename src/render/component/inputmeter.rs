@@ -65,9 +65,8 @@ pub struct InputMeter {
 }
 
 impl InputMeter {
-  pub fn update_inputs(&mut self) {
-    let devices = input::get_devices();
-    self.devices = get_input_blocks(&devices);
+  pub fn set_devices(&mut self, devices: &[InputDevice]) {
+    self.devices = get_input_blocks(devices);
   }
 }
 
@@ -142,7 +141,7 @@ impl Meter for InputMeter {
     }
 
     // draw header
-    box_(win, 0, 0);
+    draw_box(win);
     mvwaddstr_color(win, 0, 1, " X Inputs ", cpair::PAIR_HEAD);
 
     wrefresh(win);
@@ -159,7 +158,7 @@ impl Meter for InputMeter {
     let win = newwin(height, width, y, x);
     wattron(win, COLOR_PAIR(cpair::DEFAULT));
     wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
-    box_(win, 0, 0);
+    draw_box(win);
     wrefresh(win);
 
     InputMeter {
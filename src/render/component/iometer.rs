@@ -1,119 +1,161 @@
 /*****
 
 Implementation of IoMeter.
-IoMeter shows the IO usages.
+IoMeter shows the IO usages summed over selected devices,
+or a table of each device.
 
 *******/
 
+use crate::config::GraphScale;
+use crate::history::History;
+use crate::render::graph::{draw_rate_axes, draw_time_axis, Scale};
 use crate::render::{color::*, executer::manager::WinManager, meter::*};
 use crate::resource::disk;
 use crate::symbol::brail::b32::*;
-use crate::util::{DataSize, DataUnit::*};
+use crate::util::{firstn_chars, NameSelection};
 
 use ncurses::*;
+use std::collections::HashMap;
 
-static MAXBUFSZ: usize = 300; // XXX should decide dynamically.
-static THRESHOLD: u64 = 500;
+static THRESHOLD: f64 = 500.0; // [kB/s] step of the upper limit in auto scale.
 
-static RDCOLOR: i16 = cpair::DEFAULT;
-static WRCOLOR: i16 = cpair::PAIR_COMM;
+static RDCOLOR: i16 = cpair::PAIR_READ;
+static WRCOLOR: i16 = cpair::PAIR_WRITE;
+static BUSY_UTIL: f64 = 80.0; // [%] devices busier than this are highlighted.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IoView {
+  Graph,
+  Table,
+}
 
 pub struct IoMeter {
   pub height: i32,
   pub width: i32,
   pub win: WINDOW,
-  cur_hist_ix: usize,
-  history: Vec<(DataSize<f64>, DataSize<f64>)>, // ring-buffer for history of (R[kB/s], W[kB/s])
-  tps: f64, // current TPS (# of transfer requests toward any of device per sec.)
-  current_stat: Option<disk::DiskStat>,
-  max_r_kb: DataSize<u64>,
-  max_w_kb: DataSize<u64>,
+  history: History<(f64, f64)>, // (R[kB/s], W[kB/s])
+  tps: f64, // current TPS (# of transfer requests toward any of selected device per sec.)
+  scale: GraphScale,
+  r_scale: Scale, // [kB/s]
+  w_scale: Scale, // [kB/s]
+  devices: Vec<disk::DiskDevice>,
+  rates: HashMap<String, disk::DiskRate>,
+  selection: NameSelection, // devices summed into the graph.
+  default_selection: NameSelection,
+  view: IoView,
+  table_scroll: usize,
 }
 
 impl IoMeter {
-  pub fn set_statistics(&mut self, statistics: Vec<disk::DiskStat>, update_interval: f64) {
-    self.cur_hist_ix += 1;
-    if self.cur_hist_ix >= MAXBUFSZ {
-      self.cur_hist_ix %= MAXBUFSZ;
+  pub fn set_statistics(&mut self, devices: Vec<disk::DiskDevice>, update_interval: f64) {
+    // calculate rates of each device, and sum up selected ones.
+    let mut tps = 0.0;
+    let mut r_kb = 0.0;
+    let mut w_kb = 0.0;
+    let mut rates = HashMap::new();
+    for device in &devices {
+      let rate = match self
+        .devices
+        .iter()
+        .find(|d| d.stat.name == device.stat.name)
+      {
+        Some(prev) => device.stat.rate(&prev.stat, update_interval),
+        None => disk::DiskRate::default(),
+      };
+      if self.is_selected(device) {
+        tps += rate.rd_io + rate.wr_io;
+        r_kb += rate.rd_kb;
+        w_kb += rate.wr_kb;
+      }
+      rates.insert(device.stat.name.clone(), rate);
     }
+    self.tps = tps;
+    self.history.push((r_kb, w_kb), update_interval);
 
-    // add values of all interfaces
-    let mut sum = disk::DiskStat {
-      ..Default::default()
-    };
-    for statistic in statistics.into_iter() {
-      sum += statistic;
+    // save current statistic for later calculation
+    self.devices = devices;
+    self.rates = rates;
+  }
+
+  fn is_selected(&self, device: &disk::DiskDevice) -> bool {
+    self
+      .selection
+      .is_selected(&device.stat.name, device.is_hidden())
+  }
+
+  fn selected_names(&self) -> Vec<String> {
+    self
+      .devices
+      .iter()
+      .filter(|d| self.is_selected(d))
+      .map(|d| d.stat.name.clone())
+      .collect()
+  }
+
+  // devices shown in the table. hidden devices are listed only when selected.
+  fn table_devices(&self) -> Vec<&disk::DiskDevice> {
+    self
+      .devices
+      .iter()
+      .filter(|d| !d.is_hidden() || self.selection.show_hidden || self.is_selected(d))
+      .collect()
+  }
+
+  // select devices summed into the graph. empty `names` falls back to the configured ones.
+  pub fn select(&mut self, names: Vec<String>) {
+    if names.is_empty() {
+      self.selection = self.default_selection.clone();
+    } else {
+      self.selection.names = names;
     }
+    self.render();
+  }
 
-    // calculate and update values
-    let (r_kb, w_kb) = match self.current_stat.as_ref() {
-      Some(cur) => {
-        self.tps = sum.tps(cur, update_interval);
-        (
-          sum.kb_read_persec(cur, update_interval),
-          sum.kb_write_persec(cur, update_interval),
-        )
-      }
-      None => {
-        self.tps = 0.0;
-        (0.0, 0.0)
-      }
-    };
-    self.history[self.cur_hist_ix] = (DataSize::new(r_kb, Kb), DataSize::new(w_kb, Kb));
+  pub fn set_exclude(&mut self, exclude: Option<regex::Regex>) {
+    self.selection.names.clear();
+    self.selection.exclude = exclude;
+    self.render();
+  }
 
-    // save current statistic for later calculation
-    self.current_stat = Some(sum);
+  pub fn toggle_all(&mut self) -> bool {
+    self.selection.names.clear();
+    self.selection.show_hidden = !self.selection.show_hidden;
+    self.render();
+    self.selection.show_hidden
+  }
+
+  pub fn toggle_view(&mut self) -> IoView {
+    self.view = match self.view {
+      IoView::Graph => IoView::Table,
+      IoView::Table => IoView::Graph,
+    };
+    self.render();
+    self.view
   }
 
   fn draw_header(&self, y: i32, x: i32) {
-    let (r_kb, w_kb) = self.history[self.cur_hist_ix];
+    let (r_kb, w_kb) = self.history.last();
     let s = &format!(
-      " IO ({:>2.2} tps : {:>2.2} / {:>2.2} kB/s) ",
+      " IO ({:>2.2} tps : {:>2.2} / {:>2.2} kB/s) [{}] ",
       self.tps,
-      r_kb.convert(Kb),
-      w_kb.convert(Kb)
+      r_kb,
+      w_kb,
+      firstn_chars(&self.selected_names().join(","), 30)
     );
     mvwaddstr_color(self.win, y, x, s, cpair::PAIR_HEAD);
   }
 
-  // returns latest history whose size is decided by `size`.
-  // oldest entry is at index 0.
-  fn get_recent_history(&self, size: usize) -> Vec<(DataSize<f64>, DataSize<f64>)> {
-    let mut res = vec![(DataSize::new(0.0, Kb), DataSize::new(0.0, Kb)); size];
-    let start = self.cur_hist_ix;
-    for i in (0..size).rev() {
-      res[i] = self.history[(start + MAXBUFSZ - i) % MAXBUFSZ];
-    }
-
-    res.reverse();
-    res
-  }
-
-  fn update_upper_limit(&mut self, recent_hists: &[(DataSize<f64>, DataSize<f64>)]) {
-    // use u64 instead of f64
-    let recent_rd: Vec<u64> = recent_hists
-      .iter()
-      .map(|(r, _w)| r.convert(Kb) as u64)
-      .collect();
-    let recent_wr: Vec<u64> = recent_hists
-      .iter()
-      .map(|(_r, w)| w.convert(Kb) as u64)
-      .collect();
-    let max_rd = recent_rd.into_iter().fold(0_u64, |a, b| b.max(a));
-    let max_wr = recent_wr.into_iter().fold(0_u64, |a, b| b.max(a));
-    let max_kb_rd = if max_rd > THRESHOLD {
-      (max_rd + THRESHOLD) / THRESHOLD * THRESHOLD
+  fn update_upper_limit(&mut self, rd_hists: &[f64], wr_hists: &[f64]) {
+    let max_rd = rd_hists.iter().fold(0.0, |a, b| b.max(a));
+    let max_wr = wr_hists.iter().fold(0.0, |a, b| b.max(a));
+    let (max_rd, max_wr) = if self.scale.shared_axis {
+      (max_rd.max(max_wr), max_rd.max(max_wr))
     } else {
-      THRESHOLD
-    };
-    let max_kb_wr = if max_wr > THRESHOLD {
-      (max_wr + THRESHOLD) / THRESHOLD * THRESHOLD
-    } else {
-      THRESHOLD
+      (max_rd, max_wr)
     };
 
-    self.max_r_kb = DataSize::new(max_kb_rd, Kb);
-    self.max_w_kb = DataSize::new(max_kb_wr, Kb);
+    self.r_scale = Scale::fit(&self.scale, max_rd, THRESHOLD, None);
+    self.w_scale = Scale::fit(&self.scale, max_wr, THRESHOLD, None);
   }
 
   fn draw_single_col(&self, bar: &[Cc], y_bottom: i32, x: i32) {
@@ -122,86 +164,97 @@ impl IoMeter {
       mvwaddstr_color(self.win, y_bottom - i as i32, x, &cc.ch.to_string(), cc.co);
     }
   }
-
-  fn draw_yaxes(&self) {
-    let win = self.win;
-    let r_unit = if self.max_r_kb.convert(Mb) >= 2 {
-      Mb
-    } else {
-      Kb
-    };
-    let w_unit = if self.max_w_kb.convert(Mb) >= 2 {
-      Mb
-    } else {
-      Kb
-    };
-
-    // left y-axe (rd)
-    let s = &format!("{:>3.0}", self.max_r_kb.convert(r_unit));
-    mvwaddstr(win, 1, 1, s);
-    let s = &format!("{:>3.0}", self.max_r_kb.convert(r_unit) as f64 * 0.5);
-    mvwaddstr(win, self.height / 2, 1, s);
-    let s = &format!("[{}]", r_unit);
-    mvwaddstr(win, self.height - 2, 1, s);
-
-    // right y-axe (wr)
-    let s = &format!("{:>3.0}", self.max_w_kb.convert(w_unit));
-    mvwaddstr_color(win, 1, self.width - 1 - s.len() as i32, s, WRCOLOR);
-    let s = &format!("{:>3.0}", self.max_w_kb.convert(w_unit) as f64 * 0.5);
-    mvwaddstr_color(
-      win,
-      self.height / 2,
-      self.width - 1 - s.len() as i32,
-      s,
-      WRCOLOR,
-    );
-    let s = &format!("[{}]", w_unit);
-    mvwaddstr_color(
-      win,
-      self.height - 2,
-      self.width - 1 - s.len() as i32,
-      s,
-      WRCOLOR,
-    );
-  }
 }
 
-impl Meter for IoMeter {
-  fn render(&mut self) {
-    let win = self.win;
-    // erase and draw box
-    werase(win);
-    box_(win, 0, 0);
-
+impl IoMeter {
+  fn render_graph(&mut self) {
     // draw bars
     let x_start = 3;
     let width = self.width - 2 - x_start;
     let height = self.height - 2;
     let y_bottom = height;
-    let hists = self.get_recent_history(width as usize);
-    self.update_upper_limit(&hists);
-    let rd_hists: Vec<f64> = hists
-      .iter()
-      .map(|(rd, _wr)| rd.convert(Kb) as f64)
-      .collect();
-    let wr_hists: Vec<f64> = hists
-      .iter()
-      .map(|(_rd, wr)| wr.convert(Kb) as f64)
-      .collect();
+    let size = width.max(0) as usize;
+    let hists = self.history.recent(size);
+    let rd_hists: Vec<f64> = hists.iter().map(|(rd, _wr)| *rd).collect();
+    let wr_hists: Vec<f64> = hists.iter().map(|(_rd, wr)| *wr).collect();
+    self.update_upper_limit(&rd_hists, &wr_hists);
     let brails = get_brails_complement_2sep_axes_color(
       height - 1,
-      (0.0, self.max_r_kb.convert(Kb) as f64),
-      (0.0, self.max_w_kb.convert(Kb) as f64),
-      (rd_hists, RDCOLOR),
-      (wr_hists, WRCOLOR),
+      (0.0, self.r_scale.top()),
+      (0.0, self.w_scale.top()),
+      (self.r_scale.map_all(&rd_hists), RDCOLOR),
+      (self.w_scale.map_all(&wr_hists), WRCOLOR),
     );
 
     for (i, col) in brails.iter().enumerate() {
       self.draw_single_col(col, y_bottom, x_start + i as i32 + 1);
     }
 
-    // draw y-axes
-    self.draw_yaxes();
+    // draw axes
+    let right = (!self.scale.shared_axis).then_some((&self.w_scale, WRCOLOR));
+    draw_rate_axes(self.win, self.height, self.width, &self.r_scale, right);
+    draw_time_axis(self.win, self.height, x_start, self.history.span(size));
+  }
+
+  fn render_table(&self) {
+    let win = self.win;
+    let width = (self.width - 2).max(0) as usize;
+    let title = format!(
+      "  {:<10} {:>7} {:>7} {:>9} {:>9} {:>7} {:>6} {:>6} {:>6}",
+      "DEVICE", "r/s", "w/s", "rkB/s", "wkB/s", "await", "%util", "aqu", "inflt"
+    );
+    mvwaddstr_color(win, 1, 1, &firstn_chars(&title, width), cpair::PAIR_HEAD);
+
+    let num_rows = (self.height - 3).max(0) as usize;
+    for (i, device) in self
+      .table_devices()
+      .iter()
+      .skip(self.table_scroll)
+      .take(num_rows)
+      .enumerate()
+    {
+      let rate = self
+        .rates
+        .get(&device.stat.name)
+        .copied()
+        .unwrap_or_default();
+      let is_selected = self.is_selected(device);
+      let row = format!(
+        "{} {:<10} {:>7.1} {:>7.1} {:>9.1} {:>9.1} {:>7.2} {:>6.1} {:>6.2} {:>6}",
+        if is_selected { '*' } else { ' ' },
+        firstn_chars(&device.stat.name, 10),
+        rate.rd_io,
+        rate.wr_io,
+        rate.rd_kb,
+        rate.wr_kb,
+        rate.await_ms,
+        rate.util,
+        rate.queue,
+        device.stat.io_pgr,
+      );
+      let color = if rate.util >= BUSY_UTIL {
+        cpair::PAIR_DANGER
+      } else if is_selected {
+        cpair::DEFAULT
+      } else {
+        cpair::PAIR_DARK
+      };
+      mvwaddstr_color(win, 2 + i as i32, 1, &firstn_chars(&row, width), color);
+    }
+  }
+}
+
+impl Meter for IoMeter {
+  fn render(&mut self) {
+    let win = self.win;
+    // erase and draw box
+    werase(win);
+    draw_box(win);
+
+    match self.view {
+      IoView::Graph => self.render_graph(),
+      IoView::Table => self.render_table(),
+    }
 
     // draw header
     self.draw_header(0, 1);
@@ -211,35 +264,46 @@ impl Meter for IoMeter {
 
   fn init_meter(
     _parent: WINDOW,
-    _wm: &mut WinManager,
+    wm: &mut WinManager,
     height: i32,
     width: i32,
     y: i32,
     x: i32,
   ) -> Self {
-    let height = std::cmp::min(height, MAXBUFSZ as i32);
+    // config is validated at startup.
+    let selection = wm.config.disk.selection().unwrap_or_default();
     let win = newwin(height, width, y, x);
     wattron(win, COLOR_PAIR(cpair::DEFAULT));
     wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
-    box_(win, 0, 0);
+    draw_box(win);
     wrefresh(win);
 
+    let interval = wm.config.collectors.disk.interval_ms as f64 / 1000.0;
+    let mut history = History::new(&wm.config.history, interval);
+    history.fit(width.max(0) as usize);
+
     IoMeter {
       width,
       height,
       win,
-      current_stat: None,
       tps: 0.0,
-      cur_hist_ix: 0,
-      history: vec![(DataSize::new(0.0, Kb), DataSize::new(0.0, Kb)); MAXBUFSZ],
-      max_r_kb: DataSize::new(THRESHOLD, Kb),
-      max_w_kb: DataSize::new(THRESHOLD, Kb),
+      history,
+      scale: wm.config.disk.scale.clone(),
+      r_scale: Scale::fit(&wm.config.disk.scale, 0.0, THRESHOLD, None),
+      w_scale: Scale::fit(&wm.config.disk.scale, 0.0, THRESHOLD, None),
+      devices: vec![],
+      rates: HashMap::new(),
+      selection: selection.clone(),
+      default_selection: selection,
+      view: IoView::Graph,
+      table_scroll: 0,
     }
   }
 
   fn resize(&mut self, _parent: WINDOW, height: i32, width: i32, y: i32, x: i32) {
     self.height = height;
     self.width = width;
+    self.history.fit(width.max(0) as usize);
     wresize(self.win, height, width);
     werase(self.win);
     mvwin(self.win, y, x);
@@ -247,4 +311,36 @@ impl Meter for IoMeter {
     self.render();
     wrefresh(self.win);
   }
+
+  // clicking the header toggles the view,
+  // and clicking a row of the table toggles the device in the graph.
+  fn handle_click(&mut self, y: i32, _x: i32) {
+    if y == 0 {
+      self.toggle_view();
+      return;
+    }
+    if self.view != IoView::Table || y < 2 {
+      return;
+    }
+    let index = self.table_scroll + (y - 2) as usize;
+    if let Some(device) = self.table_devices().get(index) {
+      let name = device.stat.name.clone();
+      let selected = self.selected_names();
+      self.selection.toggle(&name, selected);
+      self.render();
+    }
+  }
+
+  fn handle_scroll(&mut self, y_diff: i32) {
+    let max_scroll = self.table_devices().len().saturating_sub(1);
+    self.table_scroll = if y_diff < 0 {
+      self
+        .table_scroll
+        .saturating_sub(y_diff.unsigned_abs() as usize)
+    } else {
+      self.table_scroll.saturating_add(y_diff as usize)
+    }
+    .min(max_scroll);
+    self.render();
+  }
 }
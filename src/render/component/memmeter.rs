@@ -5,57 +5,39 @@ MemMeter shows the statistics of memory usage.
 
 *******/
 
+use crate::config::GraphScale;
+use crate::history::History;
 use crate::render::color::*;
+use crate::render::graph::{draw_percent_axis, draw_time_axis, Scale};
 use crate::resource::mem;
 use crate::symbol::brail::b32;
 use ncurses::*;
 
-use crate::render::{executer::manager::WinManager, meter::Meter};
+use crate::render::{executer::manager::WinManager, meter::*};
 
 static UNIT_MB: u64 = 1024;
 #[allow(dead_code)]
 static UNIT_GB: u64 = UNIT_MB * 1024;
 
-static MAXBUFSZ: usize = 1000; // XXX should decide dynamically.
+static STEP: f64 = 50.0; // [%] step of the upper limit in auto scale.
 
 pub struct MemMeter {
   pub height: i32,
   pub width: i32,
   pub win: WINDOW,
   usage: Option<mem::MemInfo>,
-  history: Vec<f64>,  // ring-buffer for history of memory usage.
-  cur_hist_ix: usize, // always points to newly recorded value of history
-  max_percent: f64,   // [0.0, 1.0]
+  history: History<f64>, // [%] of memory used.
+  config: GraphScale,
+  scale: Scale, // [%]
 }
 
 impl MemMeter {
-  pub fn set_usage(&mut self, usage: &mem::MemInfo) {
-    {
-      let percent = usage.used as f64 / usage.total as f64;
-
-      self.cur_hist_ix += 1;
-      if self.cur_hist_ix >= MAXBUFSZ {
-        self.cur_hist_ix %= MAXBUFSZ; // XXX buggy???
-      }
-      self.history[self.cur_hist_ix] = percent;
-    }
+  pub fn set_usage(&mut self, usage: &mem::MemInfo, update_interval: f64) {
+    let percent = usage.used as f64 / usage.total as f64 * 100.0;
+    self.history.push(percent, update_interval);
     self.usage = Some(usage.clone());
   }
 
-  // returns latest history whose size is decided by self.width.
-  // oldest entry is at index 0.
-  fn get_recent_history(&self, size: usize) -> Vec<f64> {
-    let size = if size > MAXBUFSZ { MAXBUFSZ } else { size };
-    let mut res = vec![0.0; size];
-    let start = self.cur_hist_ix;
-    for i in (0..size).rev() {
-      res[i] = self.history[(start + MAXBUFSZ - i) % MAXBUFSZ];
-    }
-
-    res.reverse();
-    res
-  }
-
   fn draw_single_col(&self, bar: &str, y_bottom: i32, x: i32) {
     // draw from bottom.
     for (i, c) in bar.chars().enumerate() {
@@ -65,7 +47,7 @@ impl MemMeter {
 
   fn update_upper_limit(&mut self, recent_hists: &[f64]) {
     let max_percent = recent_hists.iter().fold(0.0, |a, b| b.max(a));
-    self.max_percent = if max_percent >= 0.5 { 1.0 } else { 0.5 };
+    self.scale = Scale::fit(&self.config, max_percent, STEP, Some(100.0));
   }
 }
 
@@ -74,11 +56,18 @@ impl Meter for MemMeter {
     let win = self.win;
     // erase and draw box
     werase(win);
-    box_(win, 0, 0);
+    draw_box(win);
 
     let mut cy = 1;
     let used_percent = {
-      let usage = self.usage.as_ref().unwrap();
+      let usage = match self.usage.as_ref() {
+        Some(_usage) => _usage,
+        None => {
+          mvwaddstr_color(win, 0, 1, " Memory ", cpair::PAIR_HEAD);
+          wrefresh(win);
+          return;
+        }
+      };
 
       // draw data
       let s = &format!("total: {:>7} MB", usage.total / UNIT_MB);
@@ -101,10 +90,12 @@ impl Meter for MemMeter {
     let x0 = 4;
     let width = self.width - 1 - x0;
     let height = self.height - 1 - cy;
-    let hists = self.get_recent_history(width as usize);
+    let size = width.max(0) as usize;
+    let hists = self.history.recent(size);
     self.update_upper_limit(&hists);
 
-    let brails = b32::get_brails_complement(height, 0.0, self.max_percent, hists);
+    let brails =
+      b32::get_brails_complement(height, 0.0, self.scale.top(), self.scale.map_all(&hists));
     for (i, brail) in brails.iter().enumerate() {
       self.draw_single_col(brail, cy + height - 1, x0 + i as i32);
     }
@@ -118,21 +109,16 @@ impl Meter for MemMeter {
       cpair::PAIR_HEAD,
     );
 
-    // draw y-axes
-    mvwaddstr(win, cy, 1, &format!("{:>3}", self.max_percent * 100.0));
-    mvwaddstr(
-      win,
-      cy + height / 2,
-      1,
-      &format!("{:>3}", self.max_percent * 0.5 * 100.0),
-    );
+    // draw axes
+    draw_percent_axis(win, cy, cy + height / 2, &self.scale);
+    draw_time_axis(win, self.height, x0 - 1, self.history.span(size));
 
     wrefresh(win);
   }
 
   fn init_meter(
     _parent: WINDOW,
-    _wm: &mut WinManager,
+    wm: &mut WinManager,
     height: i32,
     width: i32,
     y: i32,
@@ -141,23 +127,28 @@ impl Meter for MemMeter {
     let win = newwin(height, width, y, x);
     wattron(win, COLOR_PAIR(cpair::DEFAULT));
     wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
-    box_(win, 0, 0);
+    draw_box(win);
     wrefresh(win);
 
+    let interval = wm.config.collectors.mem.interval_ms as f64 / 1000.0;
+    let mut history = History::new(&wm.config.history, interval);
+    history.fit(width.max(0) as usize);
+
     MemMeter {
       width,
       height,
       win,
       usage: None,
-      history: vec![0.0; MAXBUFSZ],
-      cur_hist_ix: 0,
-      max_percent: 1.0,
+      history,
+      config: wm.config.mem.scale.clone(),
+      scale: Scale::fit(&wm.config.mem.scale, 100.0, STEP, Some(100.0)),
     }
   }
 
   fn resize(&mut self, _parent: WINDOW, height: i32, width: i32, y: i32, x: i32) {
     self.height = height;
     self.width = width;
+    self.history.fit(width.max(0) as usize);
     wresize(self.win, height, width);
     werase(self.win);
     mvwin(self.win, y, x);
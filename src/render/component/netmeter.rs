@@ -1,233 +1,321 @@
 /*****
 
 Implementation of NetMeter.
-NetGraph shows the transition of net usage.
+NetGraph shows the transition of net usage summed over selected interfaces,
+or a table of each interface.
 
 *******/
 
+use crate::config::GraphScale;
+use crate::history::History;
+use crate::render::graph::{draw_rate_axes, draw_time_axis, Scale};
 use crate::render::{color::*, executer::manager::WinManager, meter::*};
 use crate::resource::net;
 use crate::symbol::brail::b32::*;
-use crate::util::{DataSize, DataUnit::*};
+use crate::util::{firstn_chars, human_size, DataSize, DataUnit::*, NameSelection};
 
 use ncurses::*;
+use std::collections::HashMap;
 
-static MAXBUFSZ: usize = 300; // XXX should decide dynamically.
-static THRESHOLD: u64 = 500;
+static THRESHOLD: f64 = 500.0; // [kB/s] step of the upper limit in auto scale.
 
-static RXCOLOR: i16 = cpair::DEFAULT;
-static TXCOLOR: i16 = cpair::PAIR_COMM;
+static RXCOLOR: i16 = cpair::PAIR_RX;
+static TXCOLOR: i16 = cpair::PAIR_TX;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NetView {
+  Graph,
+  Table,
+}
 
 pub struct NetMeter {
   pub height: i32,
   pub width: i32,
   pub win: WINDOW,
-  history: Vec<(DataSize<u64>, DataSize<u64>)>, // ring-buffer for history of (rx, tx) [KBytes]
-  cur_hist_ix: usize,                           // always points to newly recorded value of history
-  max_rx_kb: DataSize<u64>,
-  max_tx_kb: DataSize<u64>,
+  history: History<(f64, f64)>, // (rx, tx) [kB/s]
+  scale: GraphScale,
+  rx_scale: Scale,         // [kB/s]
+  tx_scale: Scale,         // [kB/s]
   total_rx: DataSize<u64>, // Bytes
   total_tx: DataSize<u64>, // Bytes
-  diff_rx: DataSize<u64>,  // Bytes
-  diff_tx: DataSize<u64>,  // Bytes
+  diff_rx: DataSize<u64>,  // Bytes/sec
+  diff_tx: DataSize<u64>,  // Bytes/sec
+  interfaces: Vec<net::NetInterface>,
+  rates: HashMap<String, net::NetRate>,
+  selection: NameSelection, // interfaces summed into the graph.
+  default_selection: NameSelection,
+  view: NetView,
+  table_scroll: usize,
 }
 
 impl NetMeter {
-  fn update_upper_limit(&mut self, recent_hists: &[(DataSize<u64>, DataSize<u64>)]) {
-    let recent_rx: Vec<u64> = recent_hists.iter().map(|(r, _t)| r.convert(Kb)).collect();
-    let recent_tx: Vec<u64> = recent_hists.iter().map(|(_r, t)| t.convert(Kb)).collect();
-    let max_rx = recent_rx.into_iter().fold(0_u64, |a, b| b.max(a));
-    let max_tx = recent_tx.into_iter().fold(0_u64, |a, b| b.max(a));
-    let max_kb_rx = if max_rx > THRESHOLD {
-      (max_rx + THRESHOLD) / THRESHOLD * THRESHOLD
-    } else {
-      THRESHOLD
-    };
-    let max_kb_tx = if max_tx > THRESHOLD {
-      (max_tx + THRESHOLD) / THRESHOLD * THRESHOLD
+  fn update_upper_limit(&mut self, rx_hists: &[f64], tx_hists: &[f64]) {
+    let max_rx = rx_hists.iter().fold(0.0, |a, b| b.max(a));
+    let max_tx = tx_hists.iter().fold(0.0, |a, b| b.max(a));
+    let (max_rx, max_tx) = if self.scale.shared_axis {
+      (max_rx.max(max_tx), max_rx.max(max_tx))
     } else {
-      THRESHOLD
+      (max_rx, max_tx)
     };
 
-    self.max_rx_kb = DataSize::new(max_kb_rx, Kb);
-    self.max_tx_kb = DataSize::new(max_kb_tx, Kb);
+    self.rx_scale = Scale::fit(&self.scale, max_rx, THRESHOLD, None);
+    self.tx_scale = Scale::fit(&self.scale, max_tx, THRESHOLD, None);
   }
 
-  // returns latest history whose size is decided by self.width.
-  // oldest entry is at index 0.
-  fn get_recent_history(&self, size: usize) -> Vec<(DataSize<u64>, DataSize<u64>)> {
-    let mut res = vec![(DataSize::new(0, Kb), DataSize::new(0, Kb)); size];
-    let start = self.cur_hist_ix;
-    for i in (0..size).rev() {
-      res[i] = self.history[(start + MAXBUFSZ - i) % MAXBUFSZ];
-    }
-
-    res.reverse();
-    res
-  }
-
-  pub fn set_statistics(&mut self, statistics: &[net::NetStatistics]) {
-    self.cur_hist_ix += 1;
-    if self.cur_hist_ix >= MAXBUFSZ {
-      self.cur_hist_ix %= MAXBUFSZ;
-    }
-
+  pub fn set_statistics(&mut self, interfaces: Vec<net::NetInterface>, update_interval: f64) {
     let mut total_rx = 0;
     let mut total_tx = 0;
-    for statistic in statistics {
-      total_rx += statistic.rx_bytes;
-      total_tx += statistic.tx_bytes;
+    let mut rx_rate = 0.0;
+    let mut tx_rate = 0.0;
+    let mut rates = HashMap::new();
+    for interface in &interfaces {
+      let rate = match self.interfaces.iter().find(|i| i.name == interface.name) {
+        Some(prev) => interface.statistics.rate(&prev.statistics, update_interval),
+        None => net::NetRate::default(),
+      };
+      if self.is_selected(interface) {
+        total_rx += interface.statistics.rx_bytes;
+        total_tx += interface.statistics.tx_bytes;
+        rx_rate += rate.rx_bytes;
+        tx_rate += rate.tx_bytes;
+      }
+      rates.insert(interface.name.clone(), rate);
     }
 
-    if self.total_rx.val == 0 || self.total_tx.val == 0 {
-      self.total_rx = DataSize::new(total_rx, B);
-      self.total_tx = DataSize::new(total_tx, B);
-    }
-    self.diff_rx = DataSize::new(total_rx - self.total_rx.convert(B), B);
-    self.diff_tx = DataSize::new(total_tx - self.total_tx.convert(B), B);
     self.total_rx = DataSize::new(total_rx, B);
     self.total_tx = DataSize::new(total_tx, B);
+    self.diff_rx = DataSize::new(rx_rate as u64, B);
+    self.diff_tx = DataSize::new(tx_rate as u64, B);
+    self.interfaces = interfaces;
+    self.rates = rates;
 
-    self.history[self.cur_hist_ix] = (
-      DataSize::new(self.diff_rx.convert(Kb), Kb),
-      DataSize::new(self.diff_tx.convert(Kb), Kb),
+    self.history.push(
+      (
+        self.diff_rx.convert(Kb) as f64,
+        self.diff_tx.convert(Kb) as f64,
+      ),
+      update_interval,
     );
   }
 
-  fn draw_single_col(&self, bar: &[Cc], y_bottom: i32, x: i32) {
-    // draw from bottom.
-    for (i, cc) in bar.iter().enumerate() {
-      mvwaddstr_color(self.win, y_bottom - i as i32, x, &cc.ch.to_string(), cc.co);
-    }
+  fn is_selected(&self, interface: &net::NetInterface) -> bool {
+    self
+      .selection
+      .is_selected(&interface.name, interface.is_virtual)
   }
 
-  fn draw_yaxes(&self) {
-    let win = self.win;
-    let rx_unit = if self.max_rx_kb.convert(Mb) >= 2 {
-      Mb
-    } else {
-      Kb
-    };
-    let tx_unit = if self.max_tx_kb.convert(Mb) >= 2 {
-      Mb
+  fn selected_names(&self) -> Vec<String> {
+    self
+      .interfaces
+      .iter()
+      .filter(|i| self.is_selected(i))
+      .map(|i| i.name.clone())
+      .collect()
+  }
+
+  // select interfaces summed into the graph. empty `names` falls back to the configured ones.
+  pub fn select(&mut self, names: Vec<String>) {
+    if names.is_empty() {
+      self.selection = self.default_selection.clone();
     } else {
-      Kb
-    };
+      self.selection.names = names;
+    }
+    self.render();
+  }
 
-    // left y-axe (rx)
-    let s = &format!("{:>3.0}", self.max_rx_kb.convert(rx_unit));
-    mvwaddstr(win, 1, 1, s);
-    let s = &format!("{:>3.0}", self.max_rx_kb.convert(rx_unit) as f64 * 0.5);
-    mvwaddstr(win, self.height / 2, 1, s);
-    let s = &format!("[{}]", rx_unit);
-    mvwaddstr(win, self.height - 2, 1, s);
-
-    // right y-axe (tx)
-    let s = &format!("{:>3.0}", self.max_tx_kb.convert(tx_unit));
-    mvwaddstr_color(win, 1, self.width - 1 - s.len() as i32, s, TXCOLOR);
-    let s = &format!("{:>3.0}", self.max_tx_kb.convert(tx_unit) as f64 * 0.5);
-    mvwaddstr_color(
-      win,
-      self.height / 2,
-      self.width - 1 - s.len() as i32,
-      s,
-      TXCOLOR,
-    );
-    let s = &format!("[{}]", tx_unit);
-    mvwaddstr_color(
-      win,
-      self.height - 2,
-      self.width - 1 - s.len() as i32,
-      s,
-      TXCOLOR,
-    );
+  pub fn set_exclude(&mut self, exclude: Option<regex::Regex>) {
+    self.selection.names.clear();
+    self.selection.exclude = exclude;
+    self.render();
   }
-}
 
-impl Meter for NetMeter {
-  fn render(&mut self) {
-    let win = self.win;
-    // erase and draw box
-    werase(win);
-    box_(win, 0, 0);
+  pub fn toggle_virtual(&mut self) -> bool {
+    self.selection.names.clear();
+    self.selection.show_hidden = !self.selection.show_hidden;
+    self.render();
+    self.selection.show_hidden
+  }
+
+  pub fn toggle_view(&mut self) -> NetView {
+    self.view = match self.view {
+      NetView::Graph => NetView::Table,
+      NetView::Table => NetView::Graph,
+    };
+    self.render();
+    self.view
+  }
 
+  fn render_graph(&mut self) {
     // draw bars
     let x_start = 3;
     let width = self.width - 2 - x_start;
     let height = self.height - 2;
     let y_bottom = height;
-    let hists = self.get_recent_history(width as usize);
-    self.update_upper_limit(&hists);
-    let rx_hists: Vec<f64> = hists
-      .iter()
-      .map(|(rx, _tx)| rx.convert(Kb) as f64)
-      .collect();
-    let tx_hists: Vec<f64> = hists
-      .iter()
-      .map(|(_rx, tx)| tx.convert(Kb) as f64)
-      .collect();
+    let size = width.max(0) as usize;
+    let hists = self.history.recent(size);
+    let rx_hists: Vec<f64> = hists.iter().map(|(rx, _tx)| *rx).collect();
+    let tx_hists: Vec<f64> = hists.iter().map(|(_rx, tx)| *tx).collect();
+    self.update_upper_limit(&rx_hists, &tx_hists);
     let brails = get_brails_complement_2sep_axes_color(
       height - 1,
-      (0.0, self.max_rx_kb.convert(Kb) as f64),
-      (0.0, self.max_tx_kb.convert(Kb) as f64),
-      (rx_hists, RXCOLOR),
-      (tx_hists, TXCOLOR),
+      (0.0, self.rx_scale.top()),
+      (0.0, self.tx_scale.top()),
+      (self.rx_scale.map_all(&rx_hists), RXCOLOR),
+      (self.tx_scale.map_all(&tx_hists), TXCOLOR),
     );
 
     for (i, col) in brails.iter().enumerate() {
       self.draw_single_col(col, y_bottom, x_start + i as i32 + 1);
     }
 
+    // draw axes
+    let right = (!self.scale.shared_axis).then_some((&self.tx_scale, TXCOLOR));
+    draw_rate_axes(self.win, self.height, self.width, &self.rx_scale, right);
+    draw_time_axis(self.win, self.height, x_start, self.history.span(size));
+  }
+
+  fn render_table(&self) {
+    let win = self.win;
+    let width = (self.width - 2).max(0) as usize;
+    let title = format!(
+      "  {:<10} {:<7} {:>6} {:>5} {:>7} {:>7} {:>7} {:>7} {:>6} {:>6} {:>5} ADDRESS",
+      "IFACE", "STATE", "SPEED", "MTU", "RX/s", "TX/s", "RXpk/s", "TXpk/s", "DROP", "ERR", "OVR"
+    );
+    mvwaddstr_color(win, 1, 1, &firstn_chars(&title, width), cpair::PAIR_HEAD);
+
+    let num_rows = (self.height - 3).max(0) as usize;
+    for (i, interface) in self
+      .interfaces
+      .iter()
+      .skip(self.table_scroll)
+      .take(num_rows)
+      .enumerate()
+    {
+      let st = &interface.statistics;
+      let rate = self.rates.get(&interface.name).copied().unwrap_or_default();
+      let is_selected = self.is_selected(interface);
+      let speed = match interface.speed {
+        Some(speed) if speed >= 1000 => format!("{}G", speed / 1000),
+        Some(speed) => format!("{}M", speed),
+        None => "-".into(),
+      };
+      let row = format!(
+        "{} {:<10} {:<7} {:>6} {:>5} {:>7} {:>7} {:>7.0} {:>7.0} {:>6} {:>6} {:>5} {}",
+        if is_selected { '*' } else { ' ' },
+        firstn_chars(&interface.name, 10),
+        firstn_chars(&interface.operstate, 7),
+        speed,
+        interface.mtu,
+        human_size(rate.rx_bytes),
+        human_size(rate.tx_bytes),
+        rate.rx_packets,
+        rate.tx_packets,
+        st.rx_dropped + st.tx_dropped,
+        st.rx_errors + st.tx_errors,
+        st.rx_over_errors,
+        interface.addresses.join(" "),
+      );
+      let color = if rate.errors > 0.0 {
+        cpair::PAIR_DANGER // errors increased in the last interval.
+      } else if is_selected {
+        cpair::DEFAULT
+      } else {
+        cpair::PAIR_DARK
+      };
+      mvwaddstr_color(win, 2 + i as i32, 1, &firstn_chars(&row, width), color);
+    }
+  }
+
+  fn draw_single_col(&self, bar: &[Cc], y_bottom: i32, x: i32) {
+    // draw from bottom.
+    for (i, cc) in bar.iter().enumerate() {
+      mvwaddstr_color(self.win, y_bottom - i as i32, x, &cc.ch.to_string(), cc.co);
+    }
+  }
+}
+
+impl Meter for NetMeter {
+  fn render(&mut self) {
+    let win = self.win;
+    // erase and draw box
+    werase(win);
+    draw_box(win);
+
+    match self.view {
+      NetView::Graph => self.render_graph(),
+      NetView::Table => self.render_table(),
+    }
+
     // draw header
-    let rx_kb = self.diff_rx.convert(Kb) as f64 / 1024.0;
-    let tx_kb = self.diff_tx.convert(Kb) as f64 / 1024.0;
+    let rx_kb = self.diff_rx.convert(B) as f64 / 1024.0;
+    let tx_kb = self.diff_tx.convert(B) as f64 / 1024.0;
+    let names = self.selected_names();
+    let target = if names.len() == self.interfaces.len() {
+      "all".into()
+    } else {
+      names.join(",")
+    };
     mvwaddstr_color(
       win,
       0,
       1,
-      &format!(" Net ({:>5.02} / {:>5.02} kB/s) ", rx_kb, tx_kb),
+      &format!(
+        " Net ({:>5.02} / {:>5.02} kB/s) [{}] ",
+        rx_kb,
+        tx_kb,
+        firstn_chars(&target, 30)
+      ),
       cpair::PAIR_HEAD,
     );
 
-    // draw y-axes
-    self.draw_yaxes();
-
     wrefresh(win);
   }
 
   fn init_meter(
     _parent: WINDOW,
-    _wm: &mut WinManager,
+    wm: &mut WinManager,
     height: i32,
     width: i32,
     y: i32,
     x: i32,
   ) -> Self {
-    let height = std::cmp::min(height, MAXBUFSZ as i32);
+    // config is validated at startup.
+    let selection = wm.config.net.selection().unwrap_or_default();
     let win = newwin(height, width, y, x);
     wattron(win, COLOR_PAIR(cpair::DEFAULT));
     wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
-    box_(win, 0, 0);
+    draw_box(win);
     wrefresh(win);
 
+    let interval = wm.config.collectors.net.interval_ms as f64 / 1000.0;
+    let mut history = History::new(&wm.config.history, interval);
+    history.fit(width.max(0) as usize);
+
     NetMeter {
       width,
       height,
       win,
-      history: vec![(DataSize::new(0, Kb), DataSize::new(0, Kb)); MAXBUFSZ],
-      cur_hist_ix: 0,
-      max_rx_kb: DataSize::new(THRESHOLD, Kb),
-      max_tx_kb: DataSize::new(THRESHOLD, Kb),
+      history,
+      scale: wm.config.net.scale.clone(),
+      rx_scale: Scale::fit(&wm.config.net.scale, 0.0, THRESHOLD, None),
+      tx_scale: Scale::fit(&wm.config.net.scale, 0.0, THRESHOLD, None),
       total_rx: DataSize::new(0, Kb),
       total_tx: DataSize::new(0, Kb),
       diff_rx: DataSize::new(0, Kb),
       diff_tx: DataSize::new(0, Kb),
+      interfaces: vec![],
+      rates: HashMap::new(),
+      selection: selection.clone(),
+      default_selection: selection,
+      view: NetView::Graph,
+      table_scroll: 0,
     }
   }
 
   fn resize(&mut self, _parent: WINDOW, height: i32, width: i32, y: i32, x: i32) {
     self.height = height;
     self.width = width;
+    self.history.fit(width.max(0) as usize);
     wresize(self.win, height, width);
     werase(self.win);
     mvwin(self.win, y, x);
@@ -235,4 +323,36 @@ impl Meter for NetMeter {
     self.render();
     wrefresh(self.win);
   }
+
+  // clicking the header toggles the view,
+  // and clicking a row of the table toggles the interface in the graph.
+  fn handle_click(&mut self, y: i32, _x: i32) {
+    if y == 0 {
+      self.toggle_view();
+      return;
+    }
+    if self.view != NetView::Table || y < 2 {
+      return;
+    }
+    let index = self.table_scroll + (y - 2) as usize;
+    if let Some(interface) = self.interfaces.get(index) {
+      let name = interface.name.clone();
+      let selected = self.selected_names();
+      self.selection.toggle(&name, selected);
+      self.render();
+    }
+  }
+
+  fn handle_scroll(&mut self, y_diff: i32) {
+    let max_scroll = self.interfaces.len().saturating_sub(1);
+    self.table_scroll = if y_diff < 0 {
+      self
+        .table_scroll
+        .saturating_sub(y_diff.unsigned_abs() as usize)
+    } else {
+      self.table_scroll.saturating_add(y_diff as usize)
+    }
+    .min(max_scroll);
+    self.render();
+  }
 }
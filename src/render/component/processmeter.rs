@@ -182,6 +182,12 @@ impl SubWins {
     self.refresh();
   }
 
+  pub fn del(&mut self) {
+    delwin(self.pid_win);
+    delwin(self.cpu_win);
+    delwin(self.comm_win);
+  }
+
   pub fn refresh(&mut self) {
     wrefresh(self.pid_win);
     wrefresh(self.cpu_win);
@@ -135,36 +135,23 @@ impl ProcessMeterManager {
     let y0 = ((self.cursor as f64 / num_procs as f64) * actual_height as f64) as i32 + 1;
     // erase bar
     for y in 1..self.height {
-      wattron(self.win, COLOR_PAIR(PAIR_DARK_ONLY));
+      wattron(self.win, color_attr(PAIR_DARK_ONLY));
       mvwaddstr(self.win, y as i32, x0, " ");
-      wattroff(self.win, COLOR_PAIR(PAIR_DARK_ONLY));
+      wattroff(self.win, color_attr(PAIR_DARK_ONLY));
     }
     // draw bar
+    let bar = if crate::symbol::is_ascii() {
+      "#"
+    } else {
+      "▇"
+    };
     for y in y0..(y0 + bar_height) {
-      mvwaddstr(self.win, y, x0, "▇");
+      mvwaddstr(self.win, y, x0, bar);
     }
 
     wrefresh(self.win);
   }
 
-  // XXX impl as trait method for Meter
-  pub fn handle_scroll(&mut self, y_diff: i32) {
-    use crate::util::clamp;
-    let tmp_cursor = self.cursor as i32 + y_diff;
-    let sorted_procs = self.apply_filter();
-    self.cursor = if sorted_procs.len() - self.num_active_meters() > 0 {
-      clamp(
-        tmp_cursor as f64,
-        0.0,
-        (self.apply_filter().len() - self.processmeters.len()) as f64,
-      ) as usize
-    } else {
-      0
-    };
-    self.set_procs_meter();
-    self.render();
-  }
-
   fn set_highlighted_pid(&mut self) {
     for i in 0..self.processmeters.len() {
       self.processmeters[i].highlighted_pid = self.highlighted_pid;
@@ -275,10 +262,21 @@ impl Meter for ProcessMeterManager {
 
     werase(self.win);
     werase(self.processmeters_win);
-    mvwin(self.win, y, x); // XXX subwindows also moves?
+    let (mut old_y, mut old_x) = (0, 0);
+    getbegyx(self.win, &mut old_y, &mut old_x);
+    mvwin(self.win, y, x);
+
+    // sub windows don't move with the parent. create them again if moved.
+    let moved = (old_y, old_x) != (y, x);
+    if moved {
+      self.header_subwins.del();
+      delwin(self.header_win);
+      self.header_win = derwin(self.win, 1, self.width, 0, 0);
+      self.header_subwins = create_header_win(self.header_win, self.width, 0, 0);
+    }
 
-    // if height becomes larger, delete current wins.
-    if self.height > old_height {
+    // if height becomes larger or moved, delete current wins.
+    if self.height > old_height || moved {
       // delete all processmeters windows. create new ones.
       for i in 0..self.processmeters.len() {
         self.processmeters[i as usize].del();
@@ -320,6 +318,23 @@ impl Meter for ProcessMeterManager {
     self.processmeters[meter_ix].handle_click(0, x);
     self.render();
   }
+
+  fn handle_scroll(&mut self, y_diff: i32) {
+    use crate::util::clamp;
+    let tmp_cursor = (self.cursor as i32).saturating_add(y_diff);
+    let sorted_procs = self.apply_filter();
+    self.cursor = if sorted_procs.len() - self.num_active_meters() > 0 {
+      clamp(
+        tmp_cursor as f64,
+        0.0,
+        (self.apply_filter().len() - self.processmeters.len()) as f64,
+      ) as usize
+    } else {
+      0
+    };
+    self.set_procs_meter();
+    self.render();
+  }
 }
 
 fn init_meters(parent: WINDOW, wm: &mut WinManager, height: i32, width: i32) -> Vec<ProcessMeter> {
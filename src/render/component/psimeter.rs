@@ -0,0 +1,267 @@
+/*****
+
+Implementation of PsiMeter.
+PsiMeter shows Pressure Stall Information of cpu, memory and io,
+and the transition of the pressure of one of them.
+
+*******/
+
+use crate::config::GraphScale;
+use crate::history::History;
+use crate::render::graph::{draw_percent_axis, draw_time_axis, Scale};
+use crate::render::{color::*, executer::manager::WinManager, meter::*};
+use crate::resource::psi::{Pressure, PsiResource, PsiSnapshot, PsiStat};
+use crate::symbol::brail::b32::*;
+use crate::util::firstn_chars;
+
+use ncurses::*;
+
+static STEP: f64 = 10.0; // [%] step of the upper limit of the graph.
+static DANGER_AVG: f64 = 40.0; // [%] of time stalled
+static MED_DANGER_AVG: f64 = 10.0;
+
+static SOMECOLOR: i16 = cpair::DEFAULT;
+static FULLCOLOR: i16 = cpair::PAIR_DANGER;
+
+pub struct PsiMeter {
+  pub height: i32,
+  pub width: i32,
+  pub win: WINDOW,
+  snapshot: PsiSnapshot,
+  history: History<[(f64, f64); 3]>, // (some, full) avg10 of cpu/memory/io
+  graph_resource: PsiResource,       // resource shown in the graph
+}
+
+fn avg_color(avg: f64) -> i16 {
+  if avg >= DANGER_AVG {
+    cpair::PAIR_DANGER
+  } else if avg >= MED_DANGER_AVG {
+    cpair::PAIR_MED_DANGER
+  } else {
+    cpair::DEFAULT
+  }
+}
+
+impl PsiMeter {
+  pub fn set_snapshot(&mut self, snapshot: PsiSnapshot, update_interval: f64) {
+    let mut entry = [(0.0, 0.0); 3];
+    for (i, resource) in PsiResource::all().iter().enumerate() {
+      if let Some(p) = snapshot.system.get(*resource) {
+        entry[i] = (p.some.avg10, p.full.avg10);
+      }
+    }
+    self.history.push(entry, update_interval);
+    self.snapshot = snapshot;
+  }
+
+  // cycle the resource shown in the graph.
+  pub fn next_graph_resource(&mut self) -> PsiResource {
+    use PsiResource::*;
+    self.graph_resource = match self.graph_resource {
+      Cpu => Memory,
+      Memory => Io,
+      Io => Cpu,
+    };
+    self.render();
+    self.graph_resource
+  }
+
+  // returns latest history of `graph_resource` whose size is decided by `size`.
+  // oldest entry is at index 0.
+  fn get_recent_history(&self, size: usize) -> Vec<(f64, f64)> {
+    let ix = PsiResource::all()
+      .iter()
+      .position(|r| *r == self.graph_resource)
+      .unwrap();
+    self.history.recent(size).iter().map(|e| e[ix]).collect()
+  }
+
+  // "cpu  some  2.77  3.39  2.39 full  0.00  0.00  0.00"
+  fn draw_pressure(&self, y: i32, resource: PsiResource, pressure: Option<&Pressure>) {
+    let width = std::cmp::max(self.width - 2, 0) as usize;
+    let p = match pressure {
+      Some(p) => p,
+      None => {
+        let s = format!("{:<4} (not available)", resource.to_string());
+        mvwaddstr_color(self.win, y, 1, &firstn_chars(&s, width), cpair::PAIR_DARK);
+        return;
+      }
+    };
+    let mut cx = 1;
+    for (s, color) in [
+      (format!("{:<4} ", resource.to_string()), cpair::PAIR_COMM),
+      (
+        format!(
+          "some {:>5.2} {:>5.2} {:>5.2} ",
+          p.some.avg10, p.some.avg60, p.some.avg300
+        ),
+        avg_color(p.some.avg10),
+      ),
+      (
+        format!(
+          "full {:>5.2} {:>5.2} {:>5.2}",
+          p.full.avg10, p.full.avg60, p.full.avg300
+        ),
+        avg_color(p.full.avg10),
+      ),
+    ] {
+      let s = firstn_chars(&s, width.saturating_sub(cx as usize - 1));
+      mvwaddstr_color(self.win, y, cx, &s, color);
+      cx += s.len() as i32;
+    }
+  }
+
+  // "<cgroup> cpu 1.20 mem 0.00 io 3.40" with some avg10.
+  fn draw_cgroup(&self, y: i32, name: &str, stat: Option<&PsiStat>) {
+    let width = std::cmp::max(self.width - 2, 0) as usize;
+    let name_width = 16;
+    let s = format!("{:<nw$} ", firstn_chars(name, name_width), nw = name_width);
+    mvwaddstr_color(self.win, y, 1, &firstn_chars(&s, width), cpair::PAIR_COMM);
+    let mut cx = 1 + s.len() as i32;
+
+    let stat = match stat {
+      Some(stat) => stat,
+      None => {
+        let s = firstn_chars("(not found)", width.saturating_sub(cx as usize - 1));
+        mvwaddstr_color(self.win, y, cx, &s, cpair::PAIR_DARK);
+        return;
+      }
+    };
+    for resource in PsiResource::all().iter() {
+      let (s, color) = match stat.get(*resource) {
+        Some(p) => (
+          format!("{} {:>5.2} ", resource, p.some.avg10),
+          avg_color(p.some.avg10),
+        ),
+        None => (format!("{}   -   ", resource), cpair::PAIR_DARK),
+      };
+      let s = firstn_chars(&s, width.saturating_sub(cx as usize - 1));
+      mvwaddstr_color(self.win, y, cx, &s, color);
+      cx += s.len() as i32;
+    }
+  }
+
+  fn draw_single_col(&self, bar: &[Cc], y_bottom: i32, x: i32) {
+    // draw from bottom.
+    for (i, cc) in bar.iter().enumerate() {
+      mvwaddstr_color(self.win, y_bottom - i as i32, x, &cc.ch.to_string(), cc.co);
+    }
+  }
+
+  fn draw_graph(&self, y_top: i32, y_bottom: i32) {
+    let x_start = 4;
+    let width = self.width - 2 - x_start;
+    let height = y_bottom - y_top + 1;
+    if width <= 0 || height <= 1 {
+      return;
+    }
+    let hists = self.get_recent_history(width as usize);
+    let max = hists.iter().fold(0.0_f64, |a, (s, f)| a.max(*s).max(*f));
+    let scale = Scale::fit(&GraphScale::default(), max, STEP, Some(100.0));
+    let some: Vec<f64> = hists.iter().map(|(s, _)| *s).collect();
+    let full: Vec<f64> = hists.iter().map(|(_, f)| *f).collect();
+    let brails = get_brails_complement_2axes_color(
+      height,
+      0.0,
+      scale.top(),
+      (scale.map_all(&some), SOMECOLOR),
+      (scale.map_all(&full), FULLCOLOR),
+    );
+
+    for (i, col) in brails.iter().enumerate() {
+      self.draw_single_col(col, y_bottom, x_start + i as i32 + 1);
+    }
+
+    draw_percent_axis(self.win, y_top, y_top + height / 2, &scale);
+    draw_time_axis(
+      self.win,
+      self.height,
+      x_start,
+      self.history.span(width as usize),
+    );
+  }
+}
+
+impl Meter for PsiMeter {
+  fn render(&mut self) {
+    let win = self.win;
+    werase(win);
+    draw_box(win);
+
+    // draw pressure of the system and cgroups
+    let mut cy = 1;
+    for resource in PsiResource::all().iter() {
+      if cy >= self.height - 1 {
+        break;
+      }
+      self.draw_pressure(cy, *resource, self.snapshot.system.get(*resource));
+      cy += 1;
+    }
+    for (name, stat) in &self.snapshot.cgroups {
+      if cy >= self.height - 1 {
+        break;
+      }
+      self.draw_cgroup(cy, name, stat.as_ref());
+      cy += 1;
+    }
+
+    // draw graph in the rest
+    self.draw_graph(cy, self.height - 2);
+
+    // draw header
+    let s = format!(
+      " Pressure (avg10/60/300) [{}: some/full] ",
+      self.graph_resource
+    );
+    mvwaddstr_color(win, 0, 1, &s, cpair::PAIR_HEAD);
+
+    wrefresh(win);
+  }
+
+  fn init_meter(
+    _parent: WINDOW,
+    wm: &mut WinManager,
+    height: i32,
+    width: i32,
+    y: i32,
+    x: i32,
+  ) -> Self {
+    let win = newwin(height, width, y, x);
+    wattron(win, COLOR_PAIR(cpair::DEFAULT));
+    wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
+    draw_box(win);
+    wrefresh(win);
+
+    let interval = wm.config.collectors.psi.interval_ms as f64 / 1000.0;
+    let mut history = History::new(&wm.config.history, interval);
+    history.fit(width.max(0) as usize);
+
+    PsiMeter {
+      width,
+      height,
+      win,
+      snapshot: PsiSnapshot::default(),
+      history,
+      graph_resource: PsiResource::Cpu,
+    }
+  }
+
+  fn resize(&mut self, _parent: WINDOW, height: i32, width: i32, y: i32, x: i32) {
+    self.height = height;
+    self.width = width;
+    self.history.fit(width.max(0) as usize);
+    wresize(self.win, height, width);
+    werase(self.win);
+    mvwin(self.win, y, x);
+
+    self.render();
+    wrefresh(self.win);
+  }
+
+  // clicking the header cycles the resource shown in the graph.
+  fn handle_click(&mut self, y: i32, _x: i32) {
+    if y == 0 {
+      self.next_graph_resource();
+    }
+  }
+}
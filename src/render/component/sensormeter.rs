@@ -0,0 +1,311 @@
+/*****
+
+Implementation of SensorMeter.
+SensorMeter shows temperatures, fans and power of hwmon, thermal zones and RAPL,
+and the transition of the hottest temperature and the package power.
+
+*******/
+
+use crate::config::GraphScale;
+use crate::history::History;
+use crate::render::graph::{draw_time_axis, draw_unit_axis, Scale, WATT_STEP};
+use crate::render::{color::*, executer::manager::WinManager, meter::*};
+use crate::resource::sensors::{RaplCounter, Sensor, SensorKind, SensorSnapshot};
+use crate::symbol::brail::b32::*;
+use crate::util::firstn_chars;
+
+use ncurses::*;
+
+static TEMP_STEP: f64 = 10.0; // [C] step of the upper limit of temperature in the graph.
+static LABEL_WIDTH: usize = 24;
+
+static TEMPCOLOR: i16 = cpair::DEFAULT;
+static POWERCOLOR: i16 = cpair::PAIR_COMM;
+
+pub struct SensorMeter {
+  pub height: i32,
+  pub width: i32,
+  pub win: WINDOW,
+  sensors: Vec<Sensor>,
+  rapl: Vec<RaplCounter>,
+  watts: Vec<(String, f64)>,    // [W] of each RAPL package
+  history: History<(f64, f64)>, // (hottest temperature, package power)
+  temp_high: f64,               // used when a sensor has no threshold of its own.
+  temp_crit: f64,
+  scroll: usize,
+}
+
+impl SensorMeter {
+  pub fn set_snapshot(&mut self, snapshot: SensorSnapshot, update_interval: f64) {
+    self.watts = snapshot
+      .rapl
+      .iter()
+      .filter_map(|cur| {
+        let prev = self.rapl.iter().find(|p| p.name == cur.name)?;
+        Some((cur.name.clone(), cur.watts(prev, update_interval)?))
+      })
+      .collect();
+    self.rapl = snapshot.rapl;
+    self.sensors = snapshot.sensors;
+    self.scroll = std::cmp::min(self.scroll, self.num_rows().saturating_sub(1));
+
+    self
+      .history
+      .push((self.hottest(), self.package_watts()), update_interval);
+  }
+
+  fn hottest(&self) -> f64 {
+    self
+      .sensors
+      .iter()
+      .filter(|s| s.kind == SensorKind::Temp)
+      .fold(0.0, |a, s| a.max(s.value))
+  }
+
+  fn package_watts(&self) -> f64 {
+    self.watts.iter().map(|(_, w)| w).sum()
+  }
+
+  fn num_rows(&self) -> usize {
+    self.watts.len() + self.sensors.len()
+  }
+
+  fn sensor_color(&self, sensor: &Sensor) -> i16 {
+    match sensor.kind {
+      SensorKind::Temp => {
+        if sensor.value >= sensor.crit.unwrap_or(self.temp_crit) {
+          cpair::PAIR_DANGER
+        } else if sensor.value >= sensor.max.unwrap_or(self.temp_high) {
+          cpair::PAIR_MED_DANGER
+        } else {
+          cpair::DEFAULT
+        }
+      }
+      // `max` of a fan is its minimum speed.
+      SensorKind::Fan => match sensor.max {
+        Some(min) if sensor.value < min => cpair::PAIR_DANGER,
+        _ => cpair::DEFAULT,
+      },
+      SensorKind::Power => {
+        if sensor.crit.is_some_and(|c| sensor.value >= c) {
+          cpair::PAIR_DANGER
+        } else if sensor.max.is_some_and(|m| sensor.value >= m) {
+          cpair::PAIR_MED_DANGER
+        } else {
+          cpair::DEFAULT
+        }
+      }
+    }
+  }
+
+  // "coretemp Package id 0       45.0 C  (80/100)"
+  fn draw_row(&self, y: i32, name: &str, value: &str, limits: &str, color: i16) {
+    let width = std::cmp::max(self.width - 2, 0) as usize;
+    let name = format!(
+      "{:<lw$} ",
+      firstn_chars(name, LABEL_WIDTH),
+      lw = LABEL_WIDTH
+    );
+    mvwaddstr_color(
+      self.win,
+      y,
+      1,
+      &firstn_chars(&name, width),
+      cpair::PAIR_COMM,
+    );
+    let cx = 1 + name.len();
+    let s = format!("{:>10} {}", value, limits);
+    mvwaddstr_color(
+      self.win,
+      y,
+      cx as i32,
+      &firstn_chars(&s, width.saturating_sub(cx - 1)),
+      color,
+    );
+  }
+
+  fn draw_list(&self, y_top: i32, num_rows: usize) {
+    let watt_rows = self.watts.iter().map(|(name, w)| {
+      (
+        format!("rapl {}", name),
+        format!("{:.1} W", w),
+        String::new(),
+        cpair::DEFAULT,
+      )
+    });
+    let sensor_rows = self.sensors.iter().map(|s| {
+      let value = match s.kind {
+        SensorKind::Temp | SensorKind::Power => format!("{:.1} {}", s.value, s.kind.unit()),
+        SensorKind::Fan => format!("{:.0} {}", s.value, s.kind.unit()),
+      };
+      let limits = match (s.max, s.crit) {
+        (Some(m), Some(c)) => format!("({:.0}/{:.0})", m, c),
+        (Some(m), None) => format!("({:.0})", m),
+        (None, Some(c)) => format!("(-/{:.0})", c),
+        (None, None) => String::new(),
+      };
+      (
+        format!("{} {}", s.chip, s.label),
+        value,
+        limits,
+        self.sensor_color(s),
+      )
+    });
+
+    for (i, (name, value, limits, color)) in watt_rows
+      .chain(sensor_rows)
+      .skip(self.scroll)
+      .take(num_rows)
+      .enumerate()
+    {
+      self.draw_row(y_top + i as i32, &name, &value, &limits, color);
+    }
+  }
+
+  fn draw_single_col(&self, bar: &[Cc], y_bottom: i32, x: i32) {
+    // draw from bottom.
+    for (i, cc) in bar.iter().enumerate() {
+      mvwaddstr_color(self.win, y_bottom - i as i32, x, &cc.ch.to_string(), cc.co);
+    }
+  }
+
+  fn draw_graph(&self, y_top: i32, y_bottom: i32) {
+    let x_start = 3;
+    let width = self.width - 2 - x_start - 3;
+    let height = y_bottom - y_top + 1;
+    if width <= 0 || height <= 1 {
+      return;
+    }
+    let hists = self.history.recent(width as usize);
+    let temps: Vec<f64> = hists.iter().map(|(t, _)| *t).collect();
+    let watts: Vec<f64> = hists.iter().map(|(_, w)| *w).collect();
+    let config = GraphScale::default();
+    // critical temperature is always in the graph.
+    let max_temp = temps.iter().fold(self.temp_crit, |a, t| a.max(*t));
+    let temp_scale = Scale::fit(&config, max_temp, TEMP_STEP, None);
+    let max_watts = watts.iter().fold(0.0_f64, |a, w| a.max(*w));
+    let watt_scale = Scale::fit(&config, max_watts, WATT_STEP, None);
+    let brails = get_brails_complement_2sep_axes_color(
+      height,
+      (0.0, temp_scale.top()),
+      (0.0, watt_scale.top()),
+      (temp_scale.map_all(&temps), TEMPCOLOR),
+      (watt_scale.map_all(&watts), POWERCOLOR),
+    );
+    for (i, col) in brails.iter().enumerate() {
+      self.draw_single_col(col, y_bottom, x_start + i as i32 + 1);
+    }
+
+    draw_unit_axis(
+      self.win,
+      (y_top, y_bottom),
+      None,
+      &temp_scale,
+      "C",
+      TEMPCOLOR,
+    );
+    if !self.rapl.is_empty() {
+      draw_unit_axis(
+        self.win,
+        (y_top, y_bottom),
+        Some(self.width),
+        &watt_scale,
+        "W",
+        POWERCOLOR,
+      );
+    }
+    draw_time_axis(
+      self.win,
+      self.height,
+      x_start,
+      self.history.span(width as usize),
+    );
+  }
+}
+
+impl Meter for SensorMeter {
+  fn render(&mut self) {
+    let win = self.win;
+    werase(win);
+    draw_box(win);
+
+    // list takes upper half at most, and graph takes the rest.
+    let inner = std::cmp::max(self.height - 2, 0);
+    let list_rows = std::cmp::min(self.num_rows() as i32, inner / 2);
+    self.draw_list(1, list_rows as usize);
+    self.draw_graph(1 + list_rows, self.height - 2);
+
+    // draw header
+    let mut s = String::from(" Sensors ");
+    if self.sensors.is_empty() && self.rapl.is_empty() {
+      s += "(no sensors) ";
+    } else {
+      s += &format!("[max {:.0}C", self.hottest());
+      if !self.watts.is_empty() {
+        s += &format!(", {:.1}W", self.package_watts());
+      }
+      s += "] ";
+    }
+    if self.scroll > 0 {
+      s += &format!("(-{}) ", self.scroll);
+    }
+    mvwaddstr_color(win, 0, 1, &s, cpair::PAIR_HEAD);
+
+    wrefresh(win);
+  }
+
+  fn init_meter(
+    _parent: WINDOW,
+    wm: &mut WinManager,
+    height: i32,
+    width: i32,
+    y: i32,
+    x: i32,
+  ) -> Self {
+    let win = newwin(height, width, y, x);
+    wattron(win, COLOR_PAIR(cpair::DEFAULT));
+    wbkgd(win, ' ' as chtype | COLOR_PAIR(cpair::DEFAULT) as chtype);
+    draw_box(win);
+    wrefresh(win);
+
+    let interval = wm.config.collectors.sensors.interval_ms as f64 / 1000.0;
+    let mut history = History::new(&wm.config.history, interval);
+    history.fit(width.max(0) as usize);
+
+    SensorMeter {
+      width,
+      height,
+      win,
+      sensors: vec![],
+      rapl: vec![],
+      watts: vec![],
+      history,
+      temp_high: wm.config.sensors.temp_high,
+      temp_crit: wm.config.sensors.temp_crit,
+      scroll: 0,
+    }
+  }
+
+  fn resize(&mut self, _parent: WINDOW, height: i32, width: i32, y: i32, x: i32) {
+    self.height = height;
+    self.width = width;
+    self.history.fit(width.max(0) as usize);
+    wresize(self.win, height, width);
+    werase(self.win);
+    mvwin(self.win, y, x);
+
+    self.render();
+    wrefresh(self.win);
+  }
+
+  fn handle_scroll(&mut self, y_diff: i32) {
+    let max_scroll = self.num_rows().saturating_sub(1);
+    self.scroll = if y_diff < 0 {
+      self.scroll.saturating_sub(y_diff.unsigned_abs() as usize)
+    } else {
+      self.scroll.saturating_add(y_diff as usize)
+    }
+    .min(max_scroll);
+    self.render();
+  }
+}
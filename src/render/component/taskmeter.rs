@@ -52,7 +52,7 @@ impl Meter for TaskMeter {
     let mut cy = y_start;
     // erase and draw box
     werase(win);
-    box_(win, 0, 0);
+    draw_box(win);
 
     let vals = match self.vals.as_ref() {
       Some(_vals) => _vals,
@@ -98,7 +98,7 @@ impl Meter for TaskMeter {
     x: i32,
   ) -> Self {
     let win = create_meter_win(height, width, y, x);
-    box_(win, 0, 0);
+    draw_box(win);
     let mut meter = TaskMeter {
       height,
       width,
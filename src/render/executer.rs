@@ -1,4 +1,5 @@
 pub mod init;
 pub mod manager;
+pub mod registry;
 pub mod resize;
 pub mod update;
@@ -5,88 +5,178 @@ functions which initialize components.
 **************************/
 
 use super::manager::WinManager;
+use super::registry::Slot;
+use super::update::*;
+use crate::collector::Collector;
+use crate::config::Config;
 use crate::layout::{calc, config::*};
 use crate::render::component::{
-  commandbox, cpugraph, cpumanager, dmesglist, dockermeter, inputmeter, iometer, memmeter,
-  netmeter, processmeter_manager, taskmeter,
+  batterymeter, commandbox, cpugraph, cpumanager, dmesglist, dockermeter, fsmeter, inputmeter,
+  iometer, memmeter, netmeter, processmeter_manager, psimeter, sensormeter, taskmeter,
 };
 use crate::render::meter::Meter;
+use crate::resource::{
+  battery, cpu, disk, dmesg, docker, fs, input, mem, net, psi, sensors, topology,
+};
 
 pub fn init_meters(wm: &mut WinManager) {
-  let layouts = read_layout_config();
-  let fixed_layouts = calc::get_fixed_layouts(&layouts, wm.screen_height, wm.screen_width);
+  let screens = read_layout_config();
 
-  wm.cur_y = 1;
-  wm.cur_x = 0;
-  for layout in &fixed_layouts {
-    let height = layout.height;
-    let width = layout.width;
-    wm.cur_y = layout.y;
-    wm.cur_x = layout.x;
-    init_meter_general(wm, layout.name.clone(), height, width);
+  // meters of all screens are created at once, so that they keep their history.
+  let mut id_base = 0;
+  for (screen, s) in screens.iter().enumerate() {
+    let fixed_layouts = calc::get_fixed_layouts(&s.layout, wm.screen_height, wm.screen_width);
+    let slots = s.layout.slots();
+    for (index, spec) in slots.iter().enumerate() {
+      // meters hidden in the current terminal are created in their minimum size,
+      // and placed when the terminal gets large enough.
+      let (height, width) = match fixed_layouts.iter().find(|l| l.slot == index) {
+        Some(layout) => {
+          wm.cur_y = layout.y;
+          wm.cur_x = layout.x;
+          (layout.height, layout.width)
+        }
+        None => {
+          let (height, width) = spec.name.min_size();
+          wm.cur_y = 1;
+          wm.cur_x = 0;
+          (
+            height.min(wm.screen_height - 1).max(1),
+            width.min(wm.screen_width).max(1),
+          )
+        }
+      };
+      // validity of the config is checked at startup.
+      let config = match spec.config {
+        Some(overrides) => wm.config.with_overrides(overrides).unwrap(),
+        None => wm.config.clone(),
+      };
+      let id = (id_base + index, screen, index);
+      if let Some(slot) = init_meter_general(wm, id, spec.name.clone(), config, height, width) {
+        wm.meters.slots.push(slot);
+      }
+    }
+    id_base += slots.len();
   }
 
-  wm.layout = layouts;
+  wm.screens = screens;
+}
+
+// create a meter of the slot `id` (id, screen, index in the layout), with its update hook.
+// while the meter is created, `wm.config` is the config of the slot.
+pub fn init_meter_general(
+  wm: &mut WinManager,
+  id: (usize, usize, usize),
+  name: MeterName,
+  config: Config,
+  height: i32,
+  width: i32,
+) -> Option<Slot> {
+  let global = std::mem::replace(&mut wm.config, config);
+  let slot = create_slot(wm, id, name, height, width);
+  wm.config = global;
+  slot
 }
 
-pub fn init_meter_general(wm: &mut WinManager, name: MeterName, height: i32, width: i32) {
+fn create_slot(
+  wm: &mut WinManager,
+  id: (usize, usize, usize),
+  name: MeterName,
+  height: i32,
+  width: i32,
+) -> Option<Slot> {
   use crate::layout::config::MeterName::*;
-  match name {
+  macro_rules! init {
+    ($meter:ty) => {
+      <$meter>::init_meter(wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x)
+    };
+  }
+  let config = wm.config.clone();
+  let conf = &config.collectors;
+  macro_rules! slot {
+    ($meter:expr) => {
+      Slot::new(id, name.clone(), config.clone(), $meter)
+    };
+  }
+
+  let slot = match name {
     CpuMeter => {
-      wm.cpumanager = Some(cpumanager::CpuManager::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
-    }
-    CpuGraph => {
-      wm.cpu_graph = Some(cpugraph::CpuGraph::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
-    }
-    TaskMeter => {
-      wm.taskmeter = Some(taskmeter::TaskMeter::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
+      let freqs = Collector::spawn("cpufreq", &conf.cpufreq, cpu::get_freqs);
+      let root = wm.plist.topology.root.clone();
+      let nodes = Collector::spawn("numa", &conf.numa, move || topology::read_nodes(&root));
+      slot!(init!(cpumanager::CpuManager)).with_update(update_cpu_meters(freqs, nodes))
     }
+    CpuGraph => slot!(init!(cpugraph::CpuGraph)).with_update(update_cpugraph),
+    TaskMeter => slot!(init!(taskmeter::TaskMeter)).with_update(update_task_meter),
     MemMeter => {
-      wm.memmeter = Some(memmeter::MemMeter::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
+      let collector = Collector::spawn("mem", &conf.mem, mem::MemInfo::new);
+      slot!(init!(memmeter::MemMeter)).with_update(update_memmeter(collector))
     }
     Inputs => {
-      wm.inputmeter = Some(inputmeter::InputMeter::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
+      let collector = Collector::spawn("inputs", &conf.inputs, input::get_devices);
+      slot!(init!(inputmeter::InputMeter)).with_update(update_inputmeter(collector))
     }
     DmesgList => {
-      wm.dmesglist = Some(dmesglist::DmesgList::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
+      let collector = spawn_dmesg_collector(&config);
+      let mut dmesglist = init!(dmesglist::DmesgList);
+      dmesglist.set_source(&collector.name);
+      slot!(dmesglist).with_update(update_dmesglist(collector))
     }
     DockerMeter => {
-      wm.dockermeter = Some(dockermeter::DockerMeter::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
+      let collector = Collector::spawn("docker", &conf.docker, docker::get_docker_ps_up_ext);
+      slot!(init!(dockermeter::DockerMeter)).with_update(update_dockermeter(collector))
     }
     ProcMeter => {
-      wm.processmanager = Some(processmeter_manager::ProcessMeterManager::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
+      slot!(init!(processmeter_manager::ProcessMeterManager)).with_update(update_process_meters)
     }
     NetMeter => {
-      wm.netmeter = Some(netmeter::NetMeter::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
+      let collector = Collector::spawn("net", &conf.net, net::get_interfaces);
+      slot!(init!(netmeter::NetMeter)).with_update(update_netmeter(collector))
     }
     IoMeter => {
-      wm.iometer = Some(iometer::IoMeter::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
+      let collector = Collector::spawn("disk", &conf.disk, disk::get_devices);
+      slot!(init!(iometer::IoMeter)).with_update(update_iometer(collector))
+    }
+    FsMeter => {
+      let collector = Collector::spawn("fs", &conf.fs, fs::get_fs_usages);
+      slot!(init!(fsmeter::FsMeter)).with_update(update_fsmeter(collector))
+    }
+    PsiMeter => {
+      let cgroups = config.psi.cgroups.clone();
+      let collector = Collector::spawn("psi", &conf.psi, move || psi::get_psi(&cgroups));
+      slot!(init!(psimeter::PsiMeter)).with_update(update_psimeter(collector))
     }
-    CommandBox => {
-      wm.commandbox = Some(commandbox::CommandBox::init_meter(
-        wm.mainwin, wm, height, width, wm.cur_y, wm.cur_x,
-      ))
+    SensorMeter => {
+      let collector = Collector::spawn("sensors", &conf.sensors, sensors::get_sensors);
+      slot!(init!(sensormeter::SensorMeter)).with_update(update_sensormeter(collector))
     }
-    Empty => {}
+    BatteryMeter => {
+      let collector = Collector::spawn("battery", &conf.battery, battery::get_power_supplies);
+      slot!(init!(batterymeter::BatteryMeter)).with_update(update_batterymeter(collector))
+    }
+    CommandBox => slot!(init!(commandbox::CommandBox)).with_update(update_commandbox),
+    Empty => return None,
   };
+  Some(slot)
+}
+
+// spawn background collector which feeds the log pane.
+// a failed read is published as the error of the source, and cleared by the next read.
+pub fn spawn_dmesg_collector(config: &Config) -> Collector<dmesg::LogRead> {
+  let mut reader = dmesg::LogReader::new(&config.log);
+  let name = reader.name().to_string();
+  Collector::spawn_with_merge(
+    &name,
+    &config.collectors.dmesg,
+    move || match reader.read_new() {
+      Ok(lines) => (lines, None),
+      Err(e) => (vec![], Some(e)),
+    },
+    |old, (lines, error)| {
+      for line in lines {
+        dmesg::push_merging(&mut old.0, line);
+      }
+      old.1 = error;
+    },
+  )
 }
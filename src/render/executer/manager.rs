@@ -5,28 +5,38 @@ WinManager is the main object of `xtop` and manages all the components.
 
 ************************/
 
+use super::registry::Registry;
 use super::{init::*, resize::*, update::*};
 use crate::command::commander;
+use crate::config;
 use crate::consts::*;
-use crate::layout::{calc, config};
+use crate::layout::{calc, config as layout_config};
 use crate::proclist::list;
+use crate::render::color;
+use crate::render::component::processmeter_manager::FilterType;
 use crate::render::component::{
-  commandbox, cpugraph, cpumanager, dmesglist, dockermeter, inputmeter, iometer, memmeter,
-  netmeter, processmeter_manager, taskmeter,
+  commandbox::CommandBox, dmesglist::DmesgList, processmeter_manager::ProcessMeterManager,
 };
-use crate::render::{color, meter::Meter};
 use crate::resource::version;
+use crate::theme::Palette;
 use ncurses::*;
 use signal_hook::{consts::*, iterator::Signals};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+// # of lines scrolled by keyboard. it's in the unit of mouse wheel.
+static SCROLL_PAGE: i32 = 5;
+static SCROLL_ALL: i32 = i32::MAX / 4;
+
 #[derive(Clone, Copy)]
 enum ThreadSignal {
   DoUpdate,
   Resize,
+  Screen(usize),
+  Zoom,
   Mouse(MEVENT),
+  Scroll(i32),
 
   Command(char),
   CommandActivate,
@@ -39,43 +49,28 @@ pub struct WinManager {
   pub screen_width: i32,
   pub plist: list::ProcList,
 
-  // CPU meters
-  pub cpumanager: Option<cpumanager::CpuManager>,
-
-  // Task Meter
-  pub taskmeter: Option<taskmeter::TaskMeter>,
-
-  // Process meters
-  pub processmanager: Option<processmeter_manager::ProcessMeterManager>,
-
-  // CPU graph
-  pub cpu_graph: Option<cpugraph::CpuGraph>,
-
-  // Net graph
-  pub netmeter: Option<netmeter::NetMeter>,
-
-  // IO Meter
-  pub iometer: Option<iometer::IoMeter>,
-
-  // Memory meter
-  pub memmeter: Option<memmeter::MemMeter>,
-
-  // Input meter
-  pub inputmeter: Option<inputmeter::InputMeter>,
+  // meters placed in the layout
+  pub meters: Registry,
 
-  // Dmesg list
-  pub dmesglist: Option<dmesglist::DmesgList>,
+  // Layout of components
+  pub layout: layout_config::ScreenLayout, // of the current screen
 
-  // Docker meter
-  pub dockermeter: Option<dockermeter::DockerMeter>,
+  // screens and index of the current one
+  pub screens: Vec<layout_config::Screen>,
+  pub screen: usize,
 
-  // Layout of components
-  pub layout: Vec<config::Layout>,
+  // general config
+  pub config: config::Config,
 
   // CommandBox
-  pub commandbox: Option<commandbox::CommandBox>,
   pub commander: Arc<Mutex<commander::Commander>>,
 
+  // slot of the meter which receives keyboard scroll and commands. changed by click.
+  pub focused: Option<usize>,
+
+  // slot maximised to the whole terminal, if any
+  pub zoomed: Option<usize>,
+
   // cursor
   pub cur_x: i32,
   pub cur_y: i32,
@@ -97,16 +92,70 @@ impl WinManager {
 
     // init each component
     init_meters(self);
+    self.switch_screen(0);
   }
 
-  fn initialize() -> WINDOW {
+  // show the screen at `screen`, and redraw all of its meters.
+  pub fn switch_screen(&mut self, screen: usize) -> Option<()> {
+    self.layout = self.screens.get(screen)?.layout.clone();
+    self.screen = screen;
+    self.meters.set_screen(screen);
+    let dmesglist = self.meters.find::<DmesgList>(None);
+    let first = self.meters.slots.iter().find(|s| s.visible).map(|s| s.id);
+    self.focused = dmesglist.filter(|&id| self.meters.slot(id).is_some_and(|s| s.visible));
+    self.focused = self.focused.or(first);
+    self.zoomed = None;
+    self.redraw();
+    Some(())
+  }
+
+  // maximise the meter of the slot `id` to the whole terminal, or restore the layout if zoomed.
+  pub fn toggle_zoom(&mut self, id: Option<usize>) -> Option<()> {
+    if self.zoomed.take().is_none() {
+      let id = self.meters.slot(id?).filter(|s| s.visible)?.id;
+      for slot in self.meters.slots.iter_mut() {
+        slot.visible = slot.id == id;
+      }
+      self.zoomed = Some(id);
+      self.focused = Some(id);
+    } else {
+      self.meters.set_screen(self.screen);
+    }
+    self.redraw();
+    Some(())
+  }
+
+  fn redraw(&mut self) {
+    clear();
+    refresh();
+    resize_meters(self);
+    update_header(self);
+    refresh();
+  }
+
+  // slot under the position of the terminal, and the position relative to the meter.
+  fn slot_from_pos(&self, pos_y: i32, pos_x: i32) -> Option<(usize, (i32, i32))> {
+    if let Some(id) = self.zoomed {
+      return (pos_y >= 1).then_some((id, (pos_y - 1, pos_x)));
+    }
+    let (index, pos) = calc::get_layout_from_click(
+      &self.layout,
+      self.screen_height,
+      self.screen_width,
+      pos_y,
+      pos_x,
+    )?;
+    Some((self.meters.slot_at(self.screen, index)?.id, pos))
+  }
+
+  fn initialize(palette: &Palette) -> WINDOW {
     setlocale(LcCategory::all, "");
     let mainwin = initscr();
     cbreak();
     intrflush(mainwin, true);
     keypad(stdscr(), true);
     noecho();
-    color::initialize_color();
+    color::initialize_color(palette);
     curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
     mousemask((ALL_MOUSE_EVENTS | REPORT_MOUSE_POSITION) as u32, None);
     refresh();
@@ -120,14 +169,7 @@ impl WinManager {
   fn handle_update_signal(&mut self) {
     self.plist.update_cpus();
     self.update_interval = update_uptime(&mut self.plist);
-    update_cpu_meters(self);
-    update_cpugraph(self);
-    update_inputmeter(self);
-    update_memmeter(self);
-    update_netmeter(self);
-    update_iometer(self);
-    update_dmesglist(self);
-    update_dockermeter(self); // XXX should reta-limit
+    self.plist.loadaverage.update();
 
     // update values
     self.plist.total_tasks = 0;
@@ -139,7 +181,7 @@ impl WinManager {
     self.plist.recurse_proc_tree(
       None,
       "/proc",
-      self.plist.aggregated_cpu.totaltime_period as f64 / self.plist.cpus.len() as f64,
+      self.plist.aggregated_cpu.totaltime_period as f64 / self.plist.num_online_cpus() as f64,
     );
 
     // delete tombed procs
@@ -153,10 +195,8 @@ impl WinManager {
       self.plist.plist.remove(&pid);
     }
 
-    update_task_meter(self);
-    update_process_meters(self);
-
-    update_commandbox(self); // should be at last
+    update_meters(self);
+    update_header(self);
 
     refresh();
   }
@@ -172,41 +212,31 @@ impl WinManager {
       &mut self.screen_width,
     );
     wresize(self.mainwin, self.screen_height, self.screen_width);
-    // resize/redraw
-    resize_meters(self);
+    // resize/redraw. meters hidden by the new size are cleared.
+    self.redraw();
 
     flushinp();
   }
 
   fn handle_mouse_signal(&mut self, mevent: MEVENT) {
-    use config::MeterName::*;
     let bstate = mevent.bstate;
     let pos_x = mevent.x;
     let pos_y = mevent.y;
     let mut scroll = 0;
 
-    if (bstate & BUTTON1_CLICKED as u32) != 0 {
-      if let Some((layout_name, (y, x))) = calc::get_layout_from_click(
-        &self.layout,
-        self.screen_height,
-        self.screen_width,
-        pos_y,
-        pos_x,
-      ) {
-        match layout_name {
-          CpuMeter => self.cpumanager.as_mut().unwrap().handle_click(y, x),
-          CpuGraph => self.cpu_graph.as_mut().unwrap().handle_click(y, x),
-          TaskMeter => self.taskmeter.as_mut().unwrap().handle_click(y, x),
-          MemMeter => self.memmeter.as_mut().unwrap().handle_click(y, x),
-          Inputs => self.inputmeter.as_mut().unwrap().handle_click(y, x),
-          ProcMeter => self.processmanager.as_mut().unwrap().handle_click(y, x),
-          DmesgList => self.dmesglist.as_mut().unwrap().handle_click(y, x),
-          NetMeter => self.netmeter.as_mut().unwrap().handle_click(y, x),
-          IoMeter => self.iometer.as_mut().unwrap().handle_click(y, x),
-          DockerMeter => self.dockermeter.as_mut().unwrap().handle_click(y, x),
-          CommandBox => {}
-          Empty => {}
-        };
+    if (bstate & BUTTON1_DOUBLE_CLICKED as u32) != 0 {
+      if let Some((id, _)) = self.slot_from_pos(pos_y, pos_x) {
+        self.toggle_zoom(Some(id));
+      }
+    } else if (bstate & BUTTON1_CLICKED as u32) != 0 {
+      if let Some((id, (y, x))) = self.slot_from_pos(pos_y, pos_x) {
+        if let Some(slot) = self.meters.slot_mut(id) {
+          self.focused = Some(id);
+          slot.meter.handle_click(y, x);
+          if slot.name == layout_config::MeterName::DmesgList {
+            self.select_event_task(id);
+          }
+        }
       }
     } else if (bstate & BUTTON4_PRESSED as u32) != 0 {
       // wheel up
@@ -218,21 +248,41 @@ impl WinManager {
 
     // handle scroll
     if scroll != 0 {
-      if let Some((layout_name, (_, _))) = calc::get_layout_from_click(
-        &self.layout,
-        self.screen_height,
-        self.screen_width,
-        pos_y,
-        pos_x,
-      ) {
-        match layout_name {
-          ProcMeter => self.processmanager.as_mut().unwrap().handle_scroll(scroll),
-          _ => {}
-        };
+      if let Some((id, _)) = self.slot_from_pos(pos_y, pos_x) {
+        self.handle_scroll(id, scroll);
       }
     }
   }
 
+  // filter processes by the task of the event line clicked in DmesgList of the slot `id`.
+  fn select_event_task(&mut self, id: usize) -> Option<()> {
+    let pid = self
+      .meters
+      .slot_mut(id)?
+      .get_mut::<DmesgList>()?
+      .take_clicked_pid()?;
+    let result = match self.plist.plist.get(&pid) {
+      Some(proc) => {
+        self
+          .meters
+          .find_mut::<ProcessMeterManager>(None)?
+          .set_filter(FilterType::Pid(pid));
+        format!("filtered by PID {} ({})", pid, proc.cmdline)
+      }
+      None => format!("PID {} is not running", pid),
+    };
+    self
+      .meters
+      .find_mut::<CommandBox>(None)?
+      .set_result(&result);
+    Some(())
+  }
+
+  fn handle_scroll(&mut self, id: usize, scroll: i32) -> Option<()> {
+    self.meters.slot_mut(id)?.meter.handle_scroll(scroll);
+    Some(())
+  }
+
   // Handle all the signal from threads.
   // if true is returned, main thread should exit immediately.
   fn handle_thread_signal(&mut self, sig: &ThreadSignal) -> bool {
@@ -250,18 +300,43 @@ impl WinManager {
         false
       }
 
+      Screen(screen) => {
+        if *screen != self.screen {
+          self.switch_screen(*screen);
+        }
+        false
+      }
+
+      Zoom => {
+        self.toggle_zoom(self.focused);
+        false
+      }
+
       Mouse(mevent) => {
         self.handle_mouse_signal(*mevent);
         false
       }
 
+      Scroll(scroll) => {
+        if let Some(id) = self.focused {
+          self.handle_scroll(id, *scroll);
+        }
+        false
+      }
+
       Command(c) => {
-        let mut commander = self.commander.lock().unwrap();
-        let commandbox = self.commandbox.as_mut().unwrap();
+        let commander = self.commander.clone();
+        let mut commander = commander.lock().unwrap();
+        let commandbox = match self.meters.find_mut::<CommandBox>(None) {
+          Some(commandbox) => commandbox,
+          None => return false,
+        };
         if *c == '\n' {
           let command = commandbox.do_enter();
-          let result = commander.execute(&command, self.processmanager.as_mut().unwrap());
-          commandbox.set_result(&result);
+          let result = commander.execute(&command, self);
+          if let Some(commandbox) = self.meters.find_mut::<CommandBox>(None) {
+            commandbox.set_result(&result);
+          }
         } else {
           commandbox.addstr(&c.to_string(), &mut commander);
         }
@@ -270,7 +345,10 @@ impl WinManager {
 
       CommandActivate => {
         let mut commander = self.commander.lock().unwrap();
-        let commandbox = self.commandbox.as_mut().unwrap();
+        let commandbox = match self.meters.find_mut::<CommandBox>(None) {
+          Some(commandbox) => commandbox,
+          None => return false,
+        };
         commander.start_input();
         commandbox.start_input(&mut commander);
 
@@ -308,6 +386,10 @@ impl WinManager {
             continue;
           }
         }
+        KEY_PPAGE => input_sender_tx.send(Scroll(-SCROLL_PAGE)).unwrap(),
+        KEY_NPAGE => input_sender_tx.send(Scroll(SCROLL_PAGE)).unwrap(),
+        KEY_HOME => input_sender_tx.send(Scroll(-SCROLL_ALL)).unwrap(),
+        KEY_END => input_sender_tx.send(Scroll(SCROLL_ALL)).unwrap(),
 
         // normal key input
         _ => {
@@ -332,6 +414,13 @@ impl WinManager {
             ';' => {
               input_sender_tx.send(CommandActivate).unwrap();
             }
+            'z' => {
+              input_sender_tx.send(Zoom).unwrap();
+            }
+            '1'..='9' => {
+              let screen = c.to_digit(10).unwrap() as usize - 1;
+              input_sender_tx.send(Screen(screen)).unwrap();
+            }
             _ => {}
           }
         }
@@ -365,26 +454,41 @@ impl WinManager {
     }
   }
 
-  fn check_validity() -> Result<(), String> {
+  fn check_validity() -> Result<config::Config, String> {
     // check validity of layout file
-    let layout = config::read_layout_config();
-    match calc::check_layout_validity(&layout) {
-      Ok(()) => {}
-      Err(s) => return Err(s),
+    let screens = layout_config::read_layout_config();
+    if screens.is_empty() {
+      return Err("layout has no screen.".into());
+    }
+    for screen in &screens {
+      if let Err(s) = calc::check_layout_validity(&screen.layout) {
+        return Err(format!("{} (screen {})", s, screen.name));
+      }
     }
 
-    Ok(())
+    // check validity of general config file, and of config of each component
+    let config = config::read_config()?;
+    for screen in &screens {
+      for overrides in screen.layout.slots().iter().filter_map(|s| s.config) {
+        config.with_overrides(overrides)?;
+      }
+    }
+    Ok(config)
   }
 
   pub fn new() -> Self {
     // before initialize, check some validity
-    if let Err(s) = Self::check_validity() {
-      eprintln!("Error: {}", s);
-      std::process::exit(1);
-    }
+    let config = match Self::check_validity() {
+      Ok(c) => c,
+      Err(s) => {
+        eprintln!("Error: {}", s);
+        std::process::exit(1);
+      }
+    };
 
-    // create windows
-    let mainwin = Self::initialize();
+    // create windows. theme is checked with the config.
+    let mainwin = Self::initialize(&config.theme.palette().unwrap());
+    crate::symbol::set_ascii(config.symbols.is_ascii());
     let mut screen_height = 0;
     let mut screen_width = 0;
     getmaxyx(stdscr(), &mut screen_height, &mut screen_width);
@@ -397,18 +501,13 @@ impl WinManager {
       plist,
       screen_height,
       screen_width,
-      cpumanager: None,
-      memmeter: None,
-      taskmeter: None,
-      processmanager: None,
-      cpu_graph: None,
-      netmeter: None,
-      iometer: None,
-      inputmeter: None,
-      dmesglist: None,
-      dockermeter: None,
-      commandbox: None,
-      layout: vec![],
+      meters: Registry::default(),
+      layout: layout_config::ScreenLayout::Flat(vec![]),
+      screens: vec![],
+      screen: 0,
+      config,
+      focused: None,
+      zoomed: None,
       cur_x: 0,
       cur_y: 0,
       commander: Arc::new(Mutex::new(commander::Commander::new())),
@@ -0,0 +1,165 @@
+/**************************
+
+Registry of meters placed in the layout.
+Each slot of the layout holds a meter and the hook which updates it,
+so that the same kind of meter can be placed more than once.
+Meters of every screen are kept and updated, but only the current screen is rendered.
+
+**************************/
+
+use super::manager::WinManager;
+use crate::config::Config;
+use crate::layout::config::MeterName;
+use crate::render::meter::Meter;
+use std::any::Any;
+
+// Meter which can be stored in the registry and found by its type.
+pub trait Component: Meter {
+  fn as_any(&self) -> &dyn Any;
+  fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Meter + Any> Component for T {
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+}
+
+// called with the meter of the slot on every update.
+// the meter should be rendered only if it is visible.
+type UpdateHook = Box<dyn FnMut(&mut dyn Component, &mut WinManager, bool)>;
+
+pub struct Slot {
+  pub id: usize,     // unique among all screens
+  pub screen: usize, // index of the screen
+  pub index: usize,  // index in the layout of the screen
+  pub name: MeterName,
+  pub config: Config, // general config overridden by `config` in the layout
+  pub meter: Box<dyn Component>,
+  pub visible: bool,
+  update: Option<UpdateHook>,
+}
+
+impl Slot {
+  pub fn new<M: Meter + 'static>(
+    (id, screen, index): (usize, usize, usize),
+    name: MeterName,
+    config: Config,
+    meter: M,
+  ) -> Self {
+    Self {
+      id,
+      screen,
+      index,
+      name,
+      config,
+      meter: Box::new(meter),
+      visible: true,
+      update: None,
+    }
+  }
+
+  // replace the update hook. hook is ignored if `M` is not the type of the meter.
+  pub fn set_update<M, F>(&mut self, mut update: F)
+  where
+    M: Meter + 'static,
+    F: FnMut(&mut M, &mut WinManager, bool) + 'static,
+  {
+    self.update = Some(Box::new(move |meter, wm, visible| {
+      if let Some(meter) = meter.as_any_mut().downcast_mut::<M>() {
+        update(meter, wm, visible);
+      }
+    }));
+  }
+
+  pub fn with_update<M, F>(mut self, update: F) -> Self
+  where
+    M: Meter + 'static,
+    F: FnMut(&mut M, &mut WinManager, bool) + 'static,
+  {
+    self.set_update(update);
+    self
+  }
+
+  pub fn update(&mut self, wm: &mut WinManager) {
+    if let Some(update) = self.update.as_mut() {
+      update(self.meter.as_mut(), wm, self.visible);
+    }
+  }
+
+  pub fn get<M: 'static>(&self) -> Option<&M> {
+    self.meter.as_any().downcast_ref::<M>()
+  }
+
+  pub fn get_mut<M: 'static>(&mut self) -> Option<&mut M> {
+    self.meter.as_any_mut().downcast_mut::<M>()
+  }
+}
+
+#[derive(Default)]
+pub struct Registry {
+  pub slots: Vec<Slot>,
+}
+
+impl Registry {
+  pub fn slot(&self, id: usize) -> Option<&Slot> {
+    self.slots.iter().find(|s| s.id == id)
+  }
+
+  pub fn slot_mut(&mut self, id: usize) -> Option<&mut Slot> {
+    self.slots.iter_mut().find(|s| s.id == id)
+  }
+
+  // first meter of type `M`.
+  pub fn get<M: 'static>(&self) -> Option<&M> {
+    self.slots.iter().find_map(|s| s.get::<M>())
+  }
+
+  // slot of the screen at `index` in its layout.
+  pub fn slot_at(&self, screen: usize, index: usize) -> Option<&Slot> {
+    self
+      .slots
+      .iter()
+      .find(|s| s.screen == screen && s.index == index)
+  }
+
+  pub fn slot_at_mut(&mut self, screen: usize, index: usize) -> Option<&mut Slot> {
+    self
+      .slots
+      .iter_mut()
+      .find(|s| s.screen == screen && s.index == index)
+  }
+
+  // id of the slot of type `M`, preferring `preferred` (usually the focused slot),
+  // and then visible ones.
+  pub fn find<M: 'static>(&self, preferred: Option<usize>) -> Option<usize> {
+    let of_type = |s: &&Slot| s.get::<M>().is_some();
+    match preferred.and_then(|id| self.slot(id)) {
+      Some(slot) if slot.get::<M>().is_some() => Some(slot.id),
+      _ => self
+        .slots
+        .iter()
+        .filter(of_type)
+        .find(|s| s.visible)
+        .or_else(|| self.slots.iter().find(of_type))
+        .map(|s| s.id),
+    }
+  }
+
+  // show only meters of `screen`.
+  pub fn set_screen(&mut self, screen: usize) {
+    for slot in self.slots.iter_mut() {
+      slot.visible = slot.screen == screen;
+    }
+  }
+
+  // meter of type `M`, preferring `preferred` (usually the focused slot).
+  pub fn find_mut<M: 'static>(&mut self, preferred: Option<usize>) -> Option<&mut M> {
+    let id = self.find::<M>(preferred)?;
+    self.slot_mut(id)?.get_mut::<M>()
+  }
+}
@@ -5,39 +5,41 @@ functions which resize components.
 **************************/
 
 use super::manager::WinManager;
-use crate::layout::config::MeterName;
-use crate::layout::{calc, config};
-use crate::render::meter::*;
+use crate::layout::calc;
+use crate::render::color::*;
 
+// resize the meter at `index` in the layout of the current screen.
 pub fn resize_meter_general(
   wm: &mut WinManager,
-  name: MeterName,
+  index: usize,
   height: i32,
   width: i32,
 ) -> Option<()> {
-  use crate::layout::config::MeterName::*;
-
-  let meter: Box<&mut dyn Meter> = match name {
-    CpuMeter => Box::new(wm.cpumanager.as_mut()?),
-    CpuGraph => Box::new(wm.cpu_graph.as_mut()?),
-    TaskMeter => Box::new(wm.taskmeter.as_mut()?),
-    MemMeter => Box::new(wm.memmeter.as_mut()?),
-    Inputs => Box::new(wm.inputmeter.as_mut()?),
-    DmesgList => Box::new(wm.dmesglist.as_mut()?),
-    DockerMeter => Box::new(wm.dockermeter.as_mut()?),
-    ProcMeter => Box::new(wm.processmanager.as_mut()?),
-    NetMeter => Box::new(wm.netmeter.as_mut()?),
-    IoMeter => Box::new(wm.iometer.as_mut()?),
-    CommandBox => Box::new(wm.commandbox.as_mut()?),
-    Empty => return Some(()),
-  };
-  meter.resize(wm.mainwin, height, width, wm.cur_y, wm.cur_x);
+  let (mainwin, y, x) = (wm.mainwin, wm.cur_y, wm.cur_x);
+  let slot = wm.meters.slot_at_mut(wm.screen, index)?;
+  slot.meter.resize(mainwin, height, width, y, x);
   Some(())
 }
 
 pub fn resize_meters(wm: &mut WinManager) {
-  let layouts = config::read_layout_config();
-  let fixed_layouts = calc::get_fixed_layouts(&layouts, wm.screen_height, wm.screen_width);
+  // zoomed meter takes the whole terminal below the header.
+  if let Some(id) = wm.zoomed {
+    let (mainwin, height, width) = (wm.mainwin, wm.screen_height - 1, wm.screen_width);
+    if let Some(slot) = wm.meters.slot_mut(id) {
+      slot.meter.resize(mainwin, height, width, 1, 0);
+    }
+    return;
+  }
+
+  let fixed_layouts = calc::get_fixed_layouts(&wm.layout, wm.screen_height, wm.screen_width);
+  // meters which don't fit in the terminal are hidden until it gets larger.
+  let screen = wm.screen;
+  for slot in wm.meters.slots.iter_mut().filter(|s| s.screen == screen) {
+    slot.visible = fixed_layouts.iter().any(|l| l.slot == slot.index);
+  }
+  if fixed_layouts.is_empty() {
+    draw_too_small(wm);
+  }
 
   wm.cur_y = 1;
   wm.cur_x = 0;
@@ -46,6 +48,19 @@ pub fn resize_meters(wm: &mut WinManager) {
     let width = layout.width;
     wm.cur_y = layout.y;
     wm.cur_x = layout.x;
-    resize_meter_general(wm, layout.name.clone(), height, width);
+    resize_meter_general(wm, layout.slot, height, width);
+  }
+}
+
+// placeholder shown when no meter fits in the terminal.
+fn draw_too_small(wm: &WinManager) {
+  let lines = [
+    "terminal too small".to_string(),
+    format!("{}x{}", wm.screen_width, wm.screen_height),
+  ];
+  let y = std::cmp::max(wm.screen_height / 2 - 1, 1);
+  for (i, s) in lines.iter().enumerate() {
+    let x = std::cmp::max((wm.screen_width - s.len() as i32) / 2, 0);
+    mvwaddstr_color(wm.mainwin, y + i as i32, x, s, cpair::PAIR_DANGER);
   }
 }
@@ -4,10 +4,25 @@ functions which updates components.
 
 **************************/
 
-use super::manager::WinManager;
+use super::{manager::WinManager, registry::Slot};
+use crate::collector::Collector;
+use crate::layout::config::MeterName;
 use crate::proclist::list::ProcList;
-use crate::render::meter::*;
-use crate::resource::{disk, dmesg, docker, mem, net};
+use crate::render::component::{
+  batterymeter::BatteryMeter, commandbox::CommandBox, cpugraph::CpuGraph, cpumanager::CpuManager,
+  dmesglist::DmesgList, dockermeter::DockerMeter, fsmeter::FsMeter, inputmeter::InputMeter,
+  iometer::IoMeter, memmeter::MemMeter, netmeter::NetMeter,
+  processmeter_manager::ProcessMeterManager, psimeter::PsiMeter, sensormeter::SensorMeter,
+  taskmeter::TaskMeter,
+};
+use crate::render::{color::*, meter::*};
+use crate::resource::kevent::EventKind;
+use crate::resource::{
+  battery, cpu, disk, dmesg, docker, fs, input, mem, net, psi, sensors, topology,
+};
+use ncurses::*;
+
+static EVENT_FLASH_SECS: u64 = 10; // header flashes for this period after a kernel event arrives.
 
 // update uptime and return interval.
 pub fn update_uptime(plist: &mut ProcList) -> f64 {
@@ -23,90 +38,296 @@ pub fn update_uptime(plist: &mut ProcList) -> f64 {
   }
 }
 
-pub fn update_cpu_meters(wm: &mut WinManager) -> Option<()> {
-  let cpumanager = wm.cpumanager.as_mut()?;
-  cpumanager.set_cpus(&wm.plist.cpus);
-  cpumanager.render();
-  Some(())
-}
+pub fn update_cpu_meters(
+  mut freqs: Collector<Vec<cpu::CpuFreqInfo>>,
+  mut nodes: Collector<Vec<topology::NumaNode>>,
+) -> impl FnMut(&mut CpuManager, &mut WinManager, bool) {
+  move |cpumanager, wm, visible| {
+    if let Some(snapshot) = freqs.take() {
+      cpu::update_freqs(&mut wm.plist.cpus, &snapshot.value);
+    }
+    if let Some(snapshot) = nodes.take() {
+      wm.plist.topology.update_nodes(&snapshot.value);
+    }
+    if !visible {
+      return;
+    }
+    // cpus are added, removed, onlined or offlined.
+    if cpumanager.cpus_generation != wm.plist.cpus_generation {
+      cpumanager.relayout(wm);
+    }
 
-pub fn update_task_meter(wm: &mut WinManager) -> Option<()> {
-  let taskmeter = wm.taskmeter.as_mut()?;
-  wm.plist.loadaverage.update();
-  wm.plist.uptime.update();
+    cpumanager.set_cpus(&wm.plist.cpus, &wm.plist.topology);
+    cpumanager.render();
+  }
+}
 
+pub fn update_task_meter(taskmeter: &mut TaskMeter, wm: &mut WinManager, visible: bool) {
+  if !visible {
+    return;
+  }
   taskmeter.set_values(&wm.plist);
   taskmeter.render();
-  Some(())
 }
 
-pub fn update_process_meters(wm: &mut WinManager) -> Option<()> {
-  let processmanager = wm.processmanager.as_mut()?;
+pub fn update_process_meters(
+  processmanager: &mut ProcessMeterManager,
+  wm: &mut WinManager,
+  visible: bool,
+) {
+  if !visible {
+    return;
+  }
   let sorted_procs = wm.plist.get_sorted_by_cpu();
   processmanager.set_sorted_procs(sorted_procs);
   processmanager.render();
-  Some(())
 }
 
-pub fn update_cpugraph(wm: &mut WinManager) -> Option<()> {
-  let cpu_graph = wm.cpu_graph.as_mut()?;
+pub fn update_cpugraph(cpu_graph: &mut CpuGraph, wm: &mut WinManager, visible: bool) {
   let ave_cpu = &wm.plist.aggregated_cpu;
 
-  cpu_graph.set_cpu(ave_cpu);
-  cpu_graph.render();
-  Some(())
+  cpu_graph.set_cpu(ave_cpu, wm.update_interval);
+  cpu_graph.set_cores(&wm.plist.cpus, wm.update_interval);
+  if visible {
+    cpu_graph.render();
+  }
+}
+
+pub fn update_netmeter(
+  mut collector: Collector<Vec<net::NetInterface>>,
+) -> impl FnMut(&mut NetMeter, &mut WinManager, bool) {
+  move |netmeter, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      netmeter.set_statistics(snapshot.value, snapshot.elapsed);
+    }
+    if visible {
+      netmeter.render();
+      mark_timeout(netmeter.win, netmeter.width, &collector);
+    }
+  }
+}
+
+pub fn update_iometer(
+  mut collector: Collector<Vec<disk::DiskDevice>>,
+) -> impl FnMut(&mut IoMeter, &mut WinManager, bool) {
+  move |iometer, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      iometer.set_statistics(snapshot.value, snapshot.elapsed);
+    }
+    if visible {
+      iometer.render();
+      mark_timeout(iometer.win, iometer.width, &collector);
+    }
+  }
+}
+
+pub fn update_memmeter(
+  mut collector: Collector<mem::MemInfo>,
+) -> impl FnMut(&mut MemMeter, &mut WinManager, bool) {
+  move |memmeter, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      memmeter.set_usage(&snapshot.value, snapshot.elapsed);
+    }
+    if visible {
+      memmeter.render();
+      mark_timeout(memmeter.win, memmeter.width, &collector);
+    }
+  }
+}
+
+pub fn update_inputmeter(
+  mut collector: Collector<Vec<input::InputDevice>>,
+) -> impl FnMut(&mut InputMeter, &mut WinManager, bool) {
+  move |inputmeter, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      inputmeter.set_devices(&snapshot.value);
+    }
+    if visible {
+      inputmeter.render();
+      mark_timeout(inputmeter.win, inputmeter.width, &collector);
+    }
+  }
+}
+
+pub fn update_dmesglist(
+  mut collector: Collector<dmesg::LogRead>,
+) -> impl FnMut(&mut DmesgList, &mut WinManager, bool) {
+  move |dmesglist, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      let (lines, error) = snapshot.value;
+      dmesglist.append_dmesg(lines);
+      dmesglist.set_error(error);
+    }
+    if visible {
+      dmesglist.render();
+      mark_timeout(dmesglist.win, dmesglist.width, &collector);
+    }
+  }
 }
 
-pub fn update_netmeter(wm: &mut WinManager) -> Option<()> {
-  let netmeter = wm.netmeter.as_mut()?;
-  let statistics = net::get_statistic_all();
+pub fn update_commandbox(commandbox: &mut CommandBox, _wm: &mut WinManager, visible: bool) {
+  if visible {
+    commandbox.render();
+  }
+}
 
-  netmeter.set_statistics(&statistics);
-  netmeter.render();
-  Some(())
+pub fn update_dockermeter(
+  mut collector: Collector<Vec<docker::DockerExtInfo>>,
+) -> impl FnMut(&mut DockerMeter, &mut WinManager, bool) {
+  move |dockermeter, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      dockermeter.set_containers(snapshot.value);
+    }
+    if visible {
+      dockermeter.render();
+      mark_timeout(dockermeter.win, dockermeter.width, &collector);
+    }
+  }
 }
 
-pub fn update_iometer(wm: &mut WinManager) -> Option<()> {
-  let iometer = wm.iometer.as_mut()?;
-  let statistics = disk::get_diskstats();
-  iometer.set_statistics(statistics, wm.update_interval);
-  iometer.render();
-  Some(())
+pub fn update_fsmeter(
+  mut collector: Collector<Vec<fs::FsUsage>>,
+) -> impl FnMut(&mut FsMeter, &mut WinManager, bool) {
+  move |fsmeter, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      fsmeter.set_usages(snapshot.value);
+    }
+    if visible {
+      fsmeter.render();
+      mark_timeout(fsmeter.win, fsmeter.width, &collector);
+    }
+  }
 }
 
-pub fn update_memmeter(wm: &mut WinManager) -> Option<()> {
-  let memmeter = wm.memmeter.as_mut()?;
-  let usage = mem::MemInfo::new();
-  memmeter.set_usage(&usage);
-  memmeter.render();
-  Some(())
+pub fn update_psimeter(
+  mut collector: Collector<psi::PsiSnapshot>,
+) -> impl FnMut(&mut PsiMeter, &mut WinManager, bool) {
+  move |psimeter, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      psimeter.set_snapshot(snapshot.value, snapshot.elapsed);
+    }
+    if visible {
+      psimeter.render();
+      mark_timeout(psimeter.win, psimeter.width, &collector);
+    }
+  }
 }
 
-pub fn update_inputmeter(wm: &mut WinManager) -> Option<()> {
-  let inputmeter = wm.inputmeter.as_mut()?;
-  inputmeter.update_inputs();
-  inputmeter.render();
-  Some(())
+pub fn update_sensormeter(
+  mut collector: Collector<sensors::SensorSnapshot>,
+) -> impl FnMut(&mut SensorMeter, &mut WinManager, bool) {
+  move |sensormeter, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      sensormeter.set_snapshot(snapshot.value, snapshot.elapsed);
+    }
+    if visible {
+      sensormeter.render();
+      mark_timeout(sensormeter.win, sensormeter.width, &collector);
+    }
+  }
 }
 
-pub fn update_dmesglist(wm: &mut WinManager) -> Option<()> {
-  let dmesglist = wm.dmesglist.as_mut()?;
-  let dmesgs = dmesg::get_kmsgs();
-  dmesglist.set_dmesg(dmesgs);
-  dmesglist.render();
-  Some(())
+pub fn update_batterymeter(
+  mut collector: Collector<battery::PowerSupplies>,
+) -> impl FnMut(&mut BatteryMeter, &mut WinManager, bool) {
+  move |batterymeter, _, visible| {
+    if let Some(snapshot) = collector.take() {
+      batterymeter.set_supplies(snapshot.value, snapshot.elapsed);
+    }
+    if visible {
+      batterymeter.render();
+      mark_timeout(batterymeter.win, batterymeter.width, &collector);
+    }
+  }
 }
 
-pub fn update_commandbox(wm: &mut WinManager) -> Option<()> {
-  let commandbox = wm.commandbox.as_mut()?;
-  commandbox.render();
-  Some(())
+// run the update hook of each meter, including ones of hidden screens
+// so that their history continues. CommandBox is updated at last.
+pub fn update_meters(wm: &mut WinManager) {
+  // hooks take `wm`, so slots are moved out while they run.
+  let mut slots = std::mem::take(&mut wm.meters.slots);
+  let (boxes, others): (Vec<&mut Slot>, Vec<&mut Slot>) = slots
+    .iter_mut()
+    .partition(|s| s.name == MeterName::CommandBox);
+  for slot in others.into_iter().chain(boxes) {
+    slot.update(wm);
+  }
+  wm.meters.slots = slots;
 }
 
-pub fn update_dockermeter(wm: &mut WinManager) -> Option<()> {
-  let dockermeter = wm.dockermeter.as_mut()?;
-  let containers = docker::get_docker_ps_up_ext();
-  dockermeter.set_containers(containers);
-  dockermeter.render();
-  Some(())
+// draw the version, tabs of screens and the counter of kernel events at the top line.
+pub fn update_header(wm: &mut WinManager) {
+  let win = wm.mainwin;
+  mvwaddstr(win, 0, 0, &wm.version);
+  wclrtoeol(win);
+
+  // tabs of screens: "  1:overview [2:procs] 3:io"
+  let mut cx = wm.version.len() as i32 + 2;
+  if wm.screens.len() > 1 {
+    for (i, screen) in wm.screens.iter().enumerate() {
+      let (s, color) = if i == wm.screen {
+        (format!("[{}:{}]", i + 1, screen.name), cpair::PAIR_HEAD)
+      } else {
+        (format!(" {}:{} ", i + 1, screen.name), cpair::PAIR_DARK)
+      };
+      mvwaddstr_color(win, 0, cx, &s, color);
+      cx += s.len() as i32;
+    }
+  }
+  let hidden = wm
+    .meters
+    .slots
+    .iter()
+    .filter(|s| s.screen == wm.screen && !s.visible)
+    .count();
+  // notes are kept inside the terminal, over the version if needed.
+  let note_x = |s: &str| std::cmp::max(std::cmp::min(cx + 1, wm.screen_width - s.len() as i32), 0);
+  if wm.zoomed.is_some() {
+    let s = "zoomed (z to restore)";
+    mvwaddstr_color(win, 0, note_x(s), s, cpair::PAIR_CUTE);
+  } else if hidden != 0 {
+    let s = format!("{} hidden: terminal too small", hidden);
+    mvwaddstr_color(win, 0, note_x(&s), &s, cpair::PAIR_MED_DANGER);
+  }
+
+  let dmesglist = wm
+    .meters
+    .find::<DmesgList>(wm.focused)
+    .and_then(|id| wm.meters.slot(id))
+    .and_then(|slot| slot.get::<DmesgList>());
+  let events = match dmesglist {
+    Some(dmesglist) if dmesglist.events.total() != 0 => &dmesglist.events,
+    _ => return,
+  };
+  let counts: Vec<String> = EventKind::all()
+    .iter()
+    .filter(|&&kind| events.count(kind) != 0)
+    .map(|&kind| format!("{}:{}", kind, events.count(kind)))
+    .collect();
+  let s = format!(" kernel events {} ", counts.join(" "));
+  let is_new = match events.last_arrival {
+    Some(t) => t.elapsed().as_secs() < EVENT_FLASH_SECS,
+    None => false,
+  };
+
+  let attr = if is_new { A_REVERSE() | A_BLINK() } else { 0 };
+  wattron(win, attr);
+  mvwaddstr_color(
+    win,
+    0,
+    std::cmp::max(wm.screen_width - s.len() as i32, 0),
+    &s,
+    cpair::PAIR_DANGER,
+  );
+  wattroff(win, attr);
+}
+
+// draw a marker at top-right of the meter when its collector is stuck.
+fn mark_timeout<T: Send + 'static>(win: WINDOW, width: i32, collector: &Collector<T>) {
+  if !collector.is_timed_out() {
+    return;
+  }
+  let s = " timeout ";
+  mvwaddstr_color(win, 0, width - s.len() as i32 - 1, s, cpair::PAIR_DANGER);
+  wrefresh(win);
 }
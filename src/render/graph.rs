@@ -0,0 +1,211 @@
+/*****
+
+Scaling and axes shared by graphs.
+The upper limit of a graph follows its recent values, is fixed, or is logarithmic,
+and the time axis tells the span of the shown history.
+
+*******/
+
+use crate::config::{GraphScale, ScaleMode};
+use crate::render::color::*;
+use crate::util::{DataSize, DataUnit::*};
+use ncurses::*;
+
+pub static WATT_STEP: f64 = 10.0; // [W] step of the upper limit of power in auto scale.
+
+// vertical scale of a graph, from 0 to `upper`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scale {
+  pub upper: f64,
+  pub log: bool,
+}
+
+impl Scale {
+  // scale fitting values up to `max_value`.
+  // auto mode rounds it up to a multiple of `step`, and log mode to a power of 10.
+  // `cap` is the largest possible value, such as 100 %.
+  pub fn fit(config: &GraphScale, max_value: f64, step: f64, cap: Option<f64>) -> Self {
+    let upper = match (config.mode, config.max) {
+      (ScaleMode::Fixed, Some(max)) | (ScaleMode::Log, Some(max)) => max,
+      (ScaleMode::Log, None) => 10_f64.powf(max_value.max(step).log10().ceil()),
+      _ => (max_value / step).ceil().max(1.0) * step,
+    };
+    Self {
+      upper: cap.map_or(upper, |cap| upper.min(cap)),
+      log: config.mode == ScaleMode::Log,
+    }
+  }
+
+  // height of `v` on the scale, from 0 to `top()`.
+  pub fn map(&self, v: f64) -> f64 {
+    if self.log {
+      v.max(0.0).ln_1p()
+    } else {
+      v
+    }
+  }
+
+  pub fn top(&self) -> f64 {
+    self.map(self.upper)
+  }
+
+  // ratio of the height of `v` to the whole graph.
+  pub fn ratio(&self, v: f64) -> f64 {
+    if self.top() > 0.0 {
+      self.map(v) / self.top()
+    } else {
+      0.0
+    }
+  }
+
+  // value at the middle of the axis.
+  pub fn middle(&self) -> f64 {
+    if self.log {
+      (self.top() / 2.0).exp_m1()
+    } else {
+      self.upper / 2.0
+    }
+  }
+
+  pub fn map_all(&self, values: &[f64]) -> Vec<f64> {
+    values.iter().map(|v| self.map(*v)).collect()
+  }
+}
+
+// span of `secs` seconds, such as "90s", "5m" or "2h30m".
+pub fn span_label(secs: f64) -> String {
+  let secs = secs.round() as u64;
+  if secs < 120 {
+    format!("{}s", secs)
+  } else if secs < 7200 {
+    format!("{}m", (secs + 30) / 60)
+  } else {
+    match (secs + 30) / 60 {
+      mins if mins % 60 == 0 => format!("{}h", mins / 60),
+      mins => format!("{}h{}m", mins / 60, mins % 60),
+    }
+  }
+}
+
+// tell the span of the shown history on the bottom border, under its oldest end.
+pub fn draw_time_axis(win: WINDOW, height: i32, x: i32, secs: f64) {
+  if secs <= 0.0 {
+    return;
+  }
+  let s = format!(" -{} ", span_label(secs));
+  mvwaddstr_color(win, height - 1, x, &s, cpair::PAIR_DARK);
+}
+
+// labels of the top and the middle of an axis of percentages.
+pub fn draw_percent_axis(win: WINDOW, y_top: i32, y_middle: i32, scale: &Scale) {
+  mvwaddstr(win, y_top, 1, &format!("{:>3.0}", scale.upper));
+  mvwaddstr(win, y_middle, 1, &format!("{:>3.0}", scale.middle()));
+}
+
+// labels of the top and the middle of an axis in `unit`, and the unit at the bottom.
+// `right` is the width of the window to put the axis on its right side.
+pub fn draw_unit_axis(
+  win: WINDOW,
+  (y_top, y_bottom): (i32, i32),
+  right: Option<i32>,
+  scale: &Scale,
+  unit: &str,
+  color: i16,
+) {
+  let y_middle = y_top + (y_bottom - y_top + 1) / 2;
+  let mut labels = vec![
+    (y_top, format!("{:>3.0}", scale.upper)),
+    (y_bottom, format!("[{}]", unit)),
+  ];
+  if y_top < y_middle && y_middle < y_bottom {
+    labels.push((y_middle, format!("{:>3.0}", scale.middle())));
+  }
+  for (y, s) in labels {
+    let x = right.map_or(1, |width| width - 1 - s.len() as i32);
+    mvwaddstr_color(win, y, x, &s, color);
+  }
+}
+
+// labels of the axes of a graph of kB/s. the right one, in its colour, is omitted if shared.
+pub fn draw_rate_axes(
+  win: WINDOW,
+  height: i32,
+  width: i32,
+  left: &Scale,
+  right: Option<(&Scale, i16)>,
+) {
+  let labels = |scale: &Scale| {
+    let unit = if scale.upper >= 2048.0 { Mb } else { Kb };
+    let label = |kb: f64| format!("{:>3.0}", DataSize::new(kb, Kb).convert(unit));
+    [
+      (1, label(scale.upper)),
+      (height / 2, label(scale.middle())),
+      (height - 2, format!("[{}]", unit)),
+    ]
+  };
+
+  for (y, s) in labels(left) {
+    mvwaddstr(win, y, 1, &s);
+  }
+  if let Some((scale, color)) = right {
+    for (y, s) in labels(scale) {
+      mvwaddstr_color(win, y, width - 1 - s.len() as i32, &s, color);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config(mode: ScaleMode, max: Option<f64>) -> GraphScale {
+    GraphScale {
+      mode,
+      max,
+      shared_axis: false,
+    }
+  }
+
+  #[test]
+  fn test_fit() {
+    let auto = config(ScaleMode::Auto, None);
+    assert_eq!(Scale::fit(&auto, 0.0, 500.0, None).upper, 500.0);
+    assert_eq!(Scale::fit(&auto, 501.0, 500.0, None).upper, 1000.0);
+    assert_eq!(Scale::fit(&auto, 30.0, 50.0, Some(100.0)).upper, 50.0);
+    assert_eq!(Scale::fit(&auto, 100.0, 50.0, Some(100.0)).upper, 100.0);
+
+    let fixed = config(ScaleMode::Fixed, Some(2000.0));
+    assert_eq!(Scale::fit(&fixed, 5000.0, 500.0, None).upper, 2000.0);
+    assert!(!Scale::fit(&fixed, 5000.0, 500.0, None).log);
+
+    let log = config(ScaleMode::Log, None);
+    let scale = Scale::fit(&log, 3000.0, 500.0, None);
+    assert_eq!(scale.upper, 10000.0);
+    assert!(scale.log);
+    assert_eq!(Scale::fit(&log, 10.0, 500.0, None).upper, 1000.0);
+    assert_eq!(Scale::fit(&log, 5.0, 50.0, Some(100.0)).upper, 100.0);
+  }
+
+  #[test]
+  fn test_log_scale() {
+    let scale = Scale::fit(&config(ScaleMode::Log, Some(9999.0)), 0.0, 1.0, None);
+    assert_eq!(scale.ratio(0.0), 0.0);
+    assert!((scale.ratio(9999.0) - 1.0).abs() < 1e-9);
+    assert!((scale.ratio(99.0) - 0.5).abs() < 1e-9);
+    assert!((scale.middle() - 99.0).abs() < 1e-6);
+
+    let scale = Scale::fit(&config(ScaleMode::Auto, None), 0.0, 50.0, None);
+    assert_eq!(scale.ratio(25.0), 0.5);
+    assert_eq!(scale.middle(), 25.0);
+  }
+
+  #[test]
+  fn test_span_label() {
+    assert_eq!(span_label(0.0), "0s");
+    assert_eq!(span_label(90.0), "90s");
+    assert_eq!(span_label(300.4), "5m");
+    assert_eq!(span_label(600.0), "10m");
+    assert_eq!(span_label(9000.0), "2h30m");
+    assert_eq!(span_label(10800.0), "3h");
+  }
+}
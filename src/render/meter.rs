@@ -29,4 +29,17 @@ pub trait Meter {
 
   // click handler.
   fn handle_click(&mut self, _y: i32, _x: i32) {}
+
+  // scroll handler. negative `y_diff` scrolls up.
+  fn handle_scroll(&mut self, _y_diff: i32) {}
+}
+
+// draw the border of a meter, in ASCII in ASCII mode.
+pub fn draw_box(win: WINDOW) {
+  if crate::symbol::is_ascii() {
+    let (v, h, c) = ('|' as chtype, '-' as chtype, '+' as chtype);
+    wborder(win, v, v, h, h, c, c, c, c);
+  } else {
+    box_(win, 0, 0);
+  }
 }
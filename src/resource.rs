@@ -1,16 +1,23 @@
+pub mod battery;
 pub mod cmdline;
 pub mod cpu;
 pub mod disk;
 pub mod dmesg;
 pub mod docker;
+pub mod fs;
 pub mod input;
+pub mod journal;
+pub mod kevent;
 pub mod loadavg;
 pub mod mem;
 pub mod net;
 pub mod process;
 pub mod procmem;
+pub mod psi;
 pub mod pstat;
+pub mod sensors;
 pub mod stat;
+pub mod topology;
 pub mod tty;
 pub mod uptime;
 pub mod version;
@@ -0,0 +1,199 @@
+/*****
+
+/sys/class/power_supply related funcs.
+cf: https://www.kernel.org/doc/Documentation/ABI/testing/sysfs-class-power
+
+Readers take the root of sysfs classes (usually "/sys/class"),
+so that they can be tested against a fake tree.
+
+*******/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub static CLASS_PATH: &str = "/sys/class";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Battery {
+  pub name: String,               // eg: "BAT0"
+  pub status: String,             // "Charging", "Discharging", "Full", "Not charging" or "Unknown"
+  pub capacity: Option<f64>,      // [%]
+  pub energy_now: Option<f64>,    // [Wh]
+  pub energy_full: Option<f64>,   // [Wh]
+  pub power_now: Option<f64>,     // [W]
+  pub time_to_empty: Option<u64>, // [sec]
+  pub time_to_full: Option<u64>,  // [sec]
+  pub cycle_count: Option<u32>,
+}
+
+impl Battery {
+  pub fn is_discharging(&self) -> bool {
+    self.status == "Discharging"
+  }
+
+  pub fn is_charging(&self) -> bool {
+    self.status == "Charging"
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PowerSupplies {
+  pub batteries: Vec<Battery>,
+  pub ac_online: Option<bool>, // None if there is no AC adapter (eg: desktops and servers).
+}
+
+fn read_string(path: &Path) -> Option<String> {
+  fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_f64(path: &Path) -> Option<f64> {
+  read_string(path)?.parse().ok()
+}
+
+// values in sysfs are in micro units (uWh, uW, uAh, uA and uV).
+fn read_battery(dir: &Path) -> Option<Battery> {
+  let name = dir.file_name()?.to_string_lossy().to_string();
+  let read = |attr: &str| read_f64(&dir.join(attr));
+  let read_micro = |attr: &str| read(attr).map(|v| v / 1_000_000.0);
+
+  // some batteries report charge [Ah] and current [A] instead of energy and power.
+  let voltage = read_micro("voltage_now").or_else(|| read_micro("voltage_min_design"));
+  let from_charge = |attr: &str| Some(read_micro(attr)? * voltage?);
+  let energy_now = read_micro("energy_now").or_else(|| from_charge("charge_now"));
+  let energy_full = read_micro("energy_full").or_else(|| from_charge("charge_full"));
+  // power_now is negative on some machines while discharging.
+  let power_now = read_micro("power_now")
+    .or_else(|| from_charge("current_now"))
+    .map(f64::abs);
+  let capacity = read("capacity").or_else(|| Some(energy_now? / energy_full? * 100.0));
+
+  let mut battery = Battery {
+    name,
+    status: read_string(&dir.join("status")).unwrap_or_else(|| "Unknown".into()),
+    capacity: capacity.map(|c| c.clamp(0.0, 100.0)),
+    energy_now,
+    energy_full,
+    power_now,
+    time_to_empty: read("time_to_empty_now").map(|t| t as u64),
+    time_to_full: read("time_to_full_now").map(|t| t as u64),
+    cycle_count: read("cycle_count").filter(|&c| c > 0.0).map(|c| c as u32),
+  };
+
+  // estimate remaining time when the driver does not tell it.
+  let power = battery.power_now.filter(|&p| p > 0.0);
+  if battery.time_to_empty.is_none() && battery.is_discharging() {
+    battery.time_to_empty = power.and_then(|p| Some((energy_now? / p * 3600.0) as u64));
+  }
+  if battery.time_to_full.is_none() && battery.is_charging() {
+    battery.time_to_full =
+      power.and_then(|p| Some(((energy_full? - energy_now?).max(0.0) / p * 3600.0) as u64));
+  }
+
+  Some(battery)
+}
+
+// power supplies under `root` (usually "/sys/class").
+pub fn get_power_supplies_at(root: &Path) -> PowerSupplies {
+  let mut dirs: Vec<PathBuf> = match fs::read_dir(root.join("power_supply")) {
+    Ok(d) => d.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+    Err(_) => vec![],
+  };
+  dirs.sort();
+
+  let mut result = PowerSupplies::default();
+  for dir in dirs {
+    // batteries of peripherals (eg: wireless mice) have "scope" of "Device".
+    if read_string(&dir.join("scope")).as_deref() == Some("Device") {
+      continue;
+    }
+    match read_string(&dir.join("type")).as_deref() {
+      Some("Battery") => result.batteries.extend(read_battery(&dir)),
+      Some("Mains") | Some("USB") => {
+        let online = read_f64(&dir.join("online")).is_some_and(|o| o > 0.0);
+        result.ac_online = Some(result.ac_online.unwrap_or(false) || online);
+      }
+      _ => {}
+    }
+  }
+  result
+}
+
+pub fn get_power_supplies() -> PowerSupplies {
+  get_power_supplies_at(Path::new(CLASS_PATH))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::fake_sysfs;
+
+  #[test]
+  fn test_energy_battery() {
+    let root = fake_sysfs(
+      "battery-energy",
+      &[
+        ("power_supply/AC/type", "Mains\n"),
+        ("power_supply/AC/online", "0\n"),
+        ("power_supply/BAT0/type", "Battery\n"),
+        ("power_supply/BAT0/status", "Discharging\n"),
+        ("power_supply/BAT0/capacity", "50\n"),
+        ("power_supply/BAT0/energy_now", "25000000\n"),
+        ("power_supply/BAT0/energy_full", "50000000\n"),
+        ("power_supply/BAT0/power_now", "10000000\n"),
+        ("power_supply/BAT0/cycle_count", "123\n"),
+        ("power_supply/hidpp_battery_0/type", "Battery\n"),
+        ("power_supply/hidpp_battery_0/scope", "Device\n"),
+      ],
+    );
+    let supplies = get_power_supplies_at(&root);
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(supplies.ac_online, Some(false));
+    assert_eq!(
+      supplies.batteries,
+      vec![Battery {
+        name: "BAT0".into(),
+        status: "Discharging".into(),
+        capacity: Some(50.0),
+        energy_now: Some(25.0),
+        energy_full: Some(50.0),
+        power_now: Some(10.0),
+        time_to_empty: Some(9000), // 25Wh / 10W = 2.5h
+        time_to_full: None,
+        cycle_count: Some(123),
+      }]
+    );
+  }
+
+  #[test]
+  fn test_charge_battery() {
+    let root = fake_sysfs(
+      "battery-charge",
+      &[
+        ("power_supply/ADP1/type", "Mains\n"),
+        ("power_supply/ADP1/online", "1\n"),
+        ("power_supply/BAT1/type", "Battery\n"),
+        ("power_supply/BAT1/status", "Charging\n"),
+        ("power_supply/BAT1/charge_now", "2000000\n"),
+        ("power_supply/BAT1/charge_full", "4000000\n"),
+        ("power_supply/BAT1/current_now", "-1000000\n"),
+        ("power_supply/BAT1/voltage_now", "10000000\n"),
+        ("power_supply/BAT1/cycle_count", "0\n"),
+      ],
+    );
+    let supplies = get_power_supplies_at(&root);
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(supplies.ac_online, Some(true));
+    let battery = &supplies.batteries[0];
+    assert_eq!(battery.capacity, Some(50.0));
+    assert_eq!(battery.power_now, Some(10.0));
+    assert_eq!(battery.time_to_full, Some(7200)); // 20Wh / 10W = 2h
+    assert_eq!(battery.cycle_count, None);
+
+    assert_eq!(
+      get_power_supplies_at(Path::new("/no/such/dir")),
+      PowerSupplies::default()
+    );
+  }
+}
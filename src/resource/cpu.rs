@@ -5,6 +5,7 @@ CPU related structures and funcs.
 *******/
 
 use crate::resource::stat;
+use crate::util::parse_cpu_list;
 
 use std::{
   fmt::{self},
@@ -34,9 +35,66 @@ impl fmt::Debug for CpuFreq {
   }
 }
 
+// cpufreq scaling governor.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Governor {
+  Performance,
+  Powersave,
+  Schedutil,
+  Ondemand,
+  Conservative,
+  Userspace,
+  Other,
+}
+
+impl Governor {
+  pub fn from(s: &str) -> Self {
+    use Governor::*;
+    match s {
+      "performance" => Performance,
+      "powersave" => Powersave,
+      "schedutil" => Schedutil,
+      "ondemand" => Ondemand,
+      "conservative" => Conservative,
+      "userspace" => Userspace,
+      _ => Other,
+    }
+  }
+}
+
+impl fmt::Display for Governor {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    use Governor::*;
+    let s = match self {
+      Performance => "perf",
+      Powersave => "save",
+      Schedutil => "sched",
+      Ondemand => "ondmd",
+      Conservative => "cons",
+      Userspace => "user",
+      Other => "?",
+    };
+    write!(f, "{}", s)
+  }
+}
+
+// classes of CPU time, in the order of stacked bars.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CpuTimeClass {
+  Nice,
+  User,
+  System,
+  Irq,
+  SoftIrq,
+  Steal,
+  Guest,
+  IoWait,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Cpu {
   pub freq: CpuFreq,
+  pub governor: Option<Governor>,
   pub id: u32,
 
   // times
@@ -77,6 +135,7 @@ impl Default for Cpu {
   fn default() -> Self {
     Self {
       freq: CpuFreq::Absent,
+      governor: None,
       id: 0,
       usertime: 0,
       nicetime: 0,
@@ -109,87 +168,192 @@ impl Default for Cpu {
 
 impl Cpu {
   pub fn new(id: u32) -> Self {
-    let freq = get_cpu_freq(id);
-
-    Self {
-      freq: CpuFreq::Valid(freq),
+    let mut cpu = Self {
       id,
       ..Default::default()
-    }
+    };
+    cpu.freq_update();
+    cpu
   }
 
   pub fn freq_update(&mut self) {
-    let freq = get_cpu_freq(self.id);
-    self.freq = CpuFreq::Valid(freq);
+    self.freq = match get_cpu_freq(self.id) {
+      Some(freq) => CpuFreq::Valid(freq),
+      None => CpuFreq::Absent,
+    };
+    self.governor = get_cpu_governor(self.id);
   }
 
+  // mark the cpu offline. times are kept so that periods continue when it comes back.
   pub fn clear_state(&mut self) {
     self.freq = CpuFreq::Offline;
+    self.governor = None;
+    self.usertime_period = 0;
+    self.nicetime_period = 0;
+    self.systemtime_period = 0;
+    self.idletime_period = 0;
+    self.iowait_period = 0;
+    self.irq_period = 0;
+    self.softirq_period = 0;
+    self.steal_period = 0;
+    self.guest_period = 0;
+    self.system_allperiod = 0;
+    self.idle_allperiod = 0;
+    self.totaltime_period = 0;
   }
 
-  pub fn percent(&self) -> f64 {
+  pub fn is_online(&self) -> bool {
+    !matches!(self.freq, CpuFreq::Offline)
+  }
+
+  // percentage of each class of time in the last period.
+  pub fn breakdown(&self) -> [(CpuTimeClass, f64); 8] {
+    use CpuTimeClass::*;
     let total = match self.totaltime_period {
       0 => 1,
       _ => self.totaltime_period,
     };
     let calc = |n| n as f64 / total as f64 * 100.0;
 
-    let nice = calc(self.nicetime_period);
-    let normal = calc(self.usertime_period);
-    let kernel = calc(self.systemtime_period);
-    let irq = calc(self.irq_period);
-    let softirq = calc(self.softirq_period);
-    let steal = calc(self.steal_period);
-    let guest = calc(self.guest_period);
-    let iowait = calc(self.iowait_period);
-
-    crate::util::clamp(
-      nice + normal + kernel + irq + softirq + steal + guest + iowait,
-      0.0,
-      100.0,
-    )
+    [
+      (Nice, calc(self.nicetime_period)),
+      (User, calc(self.usertime_period)),
+      (System, calc(self.systemtime_period)),
+      (Irq, calc(self.irq_period)),
+      (SoftIrq, calc(self.softirq_period)),
+      (Steal, calc(self.steal_period)),
+      (Guest, calc(self.guest_period)),
+      (IoWait, calc(self.iowait_period)),
+    ]
+  }
+
+  pub fn percent_of(&self, class: CpuTimeClass) -> f64 {
+    self
+      .breakdown()
+      .iter()
+      .find(|(c, _)| *c == class)
+      .map_or(0.0, |(_, p)| *p)
+  }
+
+  pub fn percent(&self) -> f64 {
+    crate::util::clamp(self.breakdown().iter().map(|(_, p)| p).sum(), 0.0, 100.0)
   }
 }
 
 pub fn init_cpus() -> Vec<Cpu> {
-  let mut cpus = vec![];
-  let avail_cpus = num_available_cpus();
-  for i in 0..avail_cpus {
-    cpus.push(Cpu::new(i));
-  }
+  rebuild_cpus(&[], &list_present_cpus(), &get_online_cpus())
+}
 
-  cpus
+// cpus for `present` ids. existing entries are kept, and ones not in `online` are marked offline.
+pub fn rebuild_cpus(cpus: &[Cpu], present: &[u32], online: &[u32]) -> Vec<Cpu> {
+  present
+    .iter()
+    .map(|&id| {
+      let is_online = online.contains(&id);
+      match cpus.iter().find(|c| c.id == id) {
+        Some(cpu) => {
+          let mut cpu = *cpu;
+          if !is_online {
+            cpu.clear_state();
+          } else if !cpu.is_online() {
+            cpu.freq_update();
+          }
+          cpu
+        }
+        None if is_online => Cpu::new(id),
+        None => {
+          let mut cpu = Cpu {
+            id,
+            ..Default::default()
+          };
+          cpu.clear_state();
+          cpu
+        }
+      }
+    })
+    .collect()
+}
+
+// ids of cpus present in the system, including offline ones.
+pub fn list_present_cpus() -> Vec<u32> {
+  let mut ids: Vec<u32> = match fs::read_dir("/sys/devices/system/cpu/") {
+    Ok(d) => d
+      .filter_map(|f| f.ok())
+      .filter_map(|f| {
+        let fname = f.file_name().to_string_lossy().to_string();
+        fname.strip_prefix("cpu")?.parse::<u32>().ok()
+      })
+      .collect(),
+    Err(_) => vec![],
+  };
+  ids.sort_unstable();
+  ids
 }
 
 pub fn num_available_cpus() -> u32 {
-  let mut total = 0;
-  let cpudir = fs::read_dir("/sys/devices/system/cpu/").unwrap();
-  for file in cpudir {
-    let fname = file.unwrap().file_name().to_string_lossy().to_string();
-    if fname.starts_with("cpu") && fname[3..].parse::<u32>().is_ok() {
-      total += 1;
-    }
-  }
-  total
+  list_present_cpus().len() as u32
 }
 
 pub fn get_cpus_freq(avail_cpus: u32) -> Vec<u64> {
   let mut result = vec![];
 
   for i in 0..avail_cpus {
-    let freq = get_cpu_freq(i);
+    let freq = get_cpu_freq(i).unwrap_or(0);
     result.push(freq);
   }
 
   result
 }
 
+// frequency and governor of an online cpu.
+#[derive(Debug, Copy, Clone)]
+pub struct CpuFreqInfo {
+  pub id: u32,
+  pub freq: CpuFreq,
+  pub governor: Option<Governor>,
+}
+
+// read frequency and governor of each online cpus. called by a collector.
+pub fn get_freqs() -> Vec<CpuFreqInfo> {
+  let online_cpus = get_online_cpus();
+  // read /proc/cpuinfo only once if cpufreq is not supported.
+  let cpuinfo_freqs = if is_scaling_cur_freq_supported() {
+    None
+  } else {
+    Some(get_cpuinfo_freqs())
+  };
+  online_cpus
+    .into_iter()
+    .map(|id| {
+      let freq = match &cpuinfo_freqs {
+        None => _get_cpu_freq(id),
+        Some(freqs) => freqs.iter().find(|(i, _)| *i == id).map(|(_, f)| *f),
+      };
+      CpuFreqInfo {
+        id,
+        freq: freq.map_or(CpuFreq::Absent, CpuFreq::Valid),
+        governor: get_cpu_governor(id),
+      }
+    })
+    .collect()
+}
+
+// update frequency and governor of each online cpus by ones read by `get_freqs`.
+pub fn update_freqs(cpus: &mut [Cpu], freqs: &[CpuFreqInfo]) {
+  for cpu in cpus.iter_mut().filter(|c| c.is_online()) {
+    if let Some(info) = freqs.iter().find(|i| i.id == cpu.id) {
+      cpu.freq = info.freq;
+      cpu.governor = info.governor;
+    }
+  }
+}
+
 pub fn is_scaling_cur_freq_supported() -> bool {
   std::path::Path::new("/sys/devices/system/cpu/cpu0/cpufreq/scaling_cur_freq").exists()
 }
 
 // get cpu freqency in kHz
-fn get_cpu_freq(cpu: u32) -> u64 {
+fn get_cpu_freq(cpu: u32) -> Option<u64> {
   if is_scaling_cur_freq_supported() {
     _get_cpu_freq(cpu)
   } else {
@@ -197,46 +361,69 @@ fn get_cpu_freq(cpu: u32) -> u64 {
   }
 }
 
-fn _get_cpu_freq(cpu: u32) -> u64 {
+// fails when the cpu is offline.
+fn _get_cpu_freq(cpu: u32) -> Option<u64> {
   let scaling_freq = fs::read_to_string(format!(
     "/sys/devices/system/cpu/cpu{}/cpufreq/scaling_cur_freq",
     cpu
   ))
-  .unwrap();
-  scaling_freq.trim().parse().unwrap()
+  .ok()?;
+  scaling_freq.trim().parse().ok()
+}
+
+fn _get_cpu_freq_fallback(cpu: u32) -> Option<u64> {
+  get_cpuinfo_freqs()
+    .into_iter()
+    .find(|(id, _)| *id == cpu)
+    .map(|(_, freq)| freq)
 }
 
-fn _get_cpu_freq_fallback(cpu: u32) -> u64 {
-  let mut is_target = false;
-  let _cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap();
-  let cpuinfo: Vec<&str> = _cpuinfo.split('\n').collect();
+// (processor, kHz) of each cpus in /proc/cpuinfo.
+fn get_cpuinfo_freqs() -> Vec<(u32, u64)> {
+  let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+  parse_cpuinfo_freqs(&cpuinfo)
+}
 
-  for l in cpuinfo {
-    let params: Vec<&str> = l.split(':').map(|p| p.trim()).collect();
-    if params.len() != 2 {
-      continue;
-    }
-    if params[0] != "processor" && params[1].parse() == Ok(cpu) {
-      is_target = true;
+fn parse_cpuinfo_freqs(cpuinfo: &str) -> Vec<(u32, u64)> {
+  let mut result = vec![];
+  let mut processor = None;
+  for l in cpuinfo.lines() {
+    let (key, value) = match l.split_once(':') {
+      Some((k, v)) => (k.trim(), v.trim()),
+      None => continue,
+    };
+    if key == "processor" {
+      processor = value.parse().ok();
     }
-    if is_target && params[0] == "cpu MHz" {
-      let freq: f64 = params[1].parse().unwrap();
-      return (freq * 1000.0) as u64;
+    if key == "cpu MHz" {
+      if let (Some(id), Ok(freq)) = (processor, value.parse::<f64>()) {
+        result.push((id, (freq * 1000.0) as u64));
+      }
     }
   }
+  result
+}
 
-  0
+fn get_cpu_governor(cpu: u32) -> Option<Governor> {
+  let governor = fs::read_to_string(format!(
+    "/sys/devices/system/cpu/cpu{}/cpufreq/scaling_governor",
+    cpu
+  ))
+  .ok()?;
+  Some(Governor::from(governor.trim()))
 }
 
+// returns true if all of `avail_cpus` are online.
 pub fn check_cpus_online(avail_cpus: u32) -> bool {
-  let (avail_start, avail_end) = online_cpus();
-  avail_cpus > (avail_end - avail_start + 1)
+  get_online_cpus().len() as u32 >= avail_cpus
 }
 
-fn online_cpus() -> (u32, u32) {
-  let online = fs::read_to_string("/sys/devices/system/cpu/online").unwrap();
-  let nums: Vec<&str> = online.split('-').collect();
-  (nums[0].parse().unwrap(), nums[1].parse().unwrap())
+// ids of online cpus. eg: "0-3,5"
+pub fn get_online_cpus() -> Vec<u32> {
+  match fs::read_to_string("/sys/devices/system/cpu/online") {
+    Ok(online) => parse_cpu_list(&online),
+    Err(_) => list_present_cpus(),
+  }
 }
 
 // update information of each cpus.
@@ -244,25 +431,15 @@ pub fn update_time_and_period(cpus: &mut Vec<Cpu>, aggregated: &mut Cpu) {
   use crate::resource::stat::CpuId;
   let cpu_times = stat::get_cpu_time(); // index 0 is aggregated CPU
 
-  for i in 0..cpu_times.len() {
-    let cpu = if i == 0 {
-      &mut *aggregated
-    } else {
-      &mut cpus[i - 1]
+  for info in cpu_times.iter() {
+    // offline cpus do not appear in /proc/stat.
+    let cpu = match info.id {
+      CpuId::Average => &mut *aggregated,
+      CpuId::Id(id) => match cpus.iter_mut().find(|c| c.id == id) {
+        Some(cpu) => cpu,
+        None => continue,
+      },
     };
-    let info = &cpu_times[i];
-    match info.id {
-      CpuId::Average => {
-        if i != 0 {
-          continue;
-        }
-      } // XXX should panic?
-      CpuId::Id(id) => {
-        if cpu.id != id {
-          continue;
-        }
-      } // XXX should panic?
-    }
 
     let saturate_diff = |a, b| if a > b { a - b } else { 0 };
 
@@ -287,7 +464,7 @@ pub fn update_time_and_period(cpus: &mut Vec<Cpu>, aggregated: &mut Cpu) {
     cpu.irq_period = saturate_diff(info.irq, cpu.irq);
     cpu.softirq_period = saturate_diff(info.softirq, cpu.softirq);
     cpu.steal_period = saturate_diff(info.steal, cpu.steal);
-    cpu.guest_period = saturate_diff(virt_alltime, cpu.guest_period);
+    cpu.guest_period = saturate_diff(virt_alltime, cpu.guesttime);
     cpu.totaltime_period = saturate_diff(totaltime, cpu.totaltime);
 
     // update absolute times
@@ -334,6 +511,71 @@ mod tests {
     println!("cpus: {:?}", &cpus);
   }
 
+  #[test]
+  fn test_rebuild_cpus() {
+    let mut cpu1 = Cpu::new(1);
+    cpu1.usertime = 100;
+    cpu1.usertime_period = 10;
+
+    // cpu1 goes offline, and cpu2 is added.
+    let cpus = rebuild_cpus(&[Cpu::new(0), cpu1], &[0, 1, 2], &[0, 2]);
+    assert_eq!(
+      cpus.iter().map(|c| c.id).collect::<Vec<u32>>(),
+      vec![0, 1, 2]
+    );
+    assert!(!cpus[1].is_online());
+    assert_eq!((cpus[1].usertime, cpus[1].usertime_period), (100, 0));
+    assert!(cpus[2].is_online());
+
+    // cpu1 comes back.
+    let cpus = rebuild_cpus(&cpus, &[0, 1, 2], &[0, 1, 2]);
+    assert!(cpus[1].is_online());
+    assert_eq!(cpus[1].usertime, 100);
+  }
+
+  #[test]
+  fn test_update_freqs() {
+    let mut cpus = rebuild_cpus(&[], &[0, 1, 2], &[0, 1]);
+    let freqs = get_freqs();
+    let info = |id| CpuFreqInfo {
+      id,
+      freq: CpuFreq::Valid(2_000_000),
+      governor: Some(Governor::Schedutil),
+    };
+    update_freqs(&mut cpus, &[info(0), info(2)]);
+    assert_eq!(cpus[0].freq.to_string(), "2000 MHz");
+    assert_eq!(cpus[0].governor, Some(Governor::Schedutil));
+    assert!(cpus[2].freq.to_string() == "offline" && cpus[2].governor.is_none());
+    assert!(freqs.iter().all(|i| !matches!(i.freq, CpuFreq::Offline)));
+  }
+
+  #[test]
+  fn test_breakdown() {
+    let cpu = Cpu {
+      usertime_period: 50,
+      systemtime_period: 20,
+      steal_period: 10,
+      iowait_period: 10,
+      idletime_period: 10,
+      totaltime_period: 100,
+      ..Default::default()
+    };
+    assert_eq!(cpu.percent_of(CpuTimeClass::User), 50.0);
+    assert_eq!(cpu.percent_of(CpuTimeClass::Steal), 10.0);
+    assert_eq!(cpu.percent(), 90.0);
+    assert_eq!(Cpu::default().percent(), 0.0);
+  }
+
+  #[test]
+  fn test_parse_cpuinfo_freqs() {
+    let cpuinfo = "processor\t: 0\ncpu MHz\t\t: 2100.000\n\nprocessor\t: 1\ncpu MHz\t\t: 800.5\n";
+    assert_eq!(
+      parse_cpuinfo_freqs(cpuinfo),
+      vec![(0, 2100000), (1, 800500)]
+    );
+    assert_eq!(Governor::from("schedutil"), Governor::Schedutil);
+  }
+
   //#[test]
   #[allow(dead_code)]
   fn test_update_cpu_time() {
@@ -7,7 +7,9 @@ xtop assumes only kernel 5.5+.
 
 *******/
 
-use std::{fs, ops};
+use std::{fs, ops, path};
+
+static BLOCK_PATH: &str = "/sys/class/block";
 
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct DiskStat {
@@ -79,8 +81,7 @@ impl DiskStat {
     } else {
       let major = p(&mut tokens) as u32;
       let minor = p(&mut tokens) as u32;
-      let name = tokens[2].into();
-      tokens.remove(0);
+      let name = tokens.remove(0).into();
       let rd_io = p(&mut tokens);
       let rd_merge = p(&mut tokens);
       let rd_sector = p(&mut tokens);
@@ -124,24 +125,72 @@ impl DiskStat {
     }
   }
 
-  pub fn tps(&self, rhs: &Self, update_interval: f64) -> f64 {
-    ((self.rd_io + self.wr_io + self.dc_io) - (rhs.rd_io + rhs.wr_io + rhs.dc_io)) as f64
-      / update_interval
-  }
+  // counters go back when the device is re-attached.
+  pub fn rate(&self, prev: &Self, update_interval: f64) -> DiskRate {
+    let diff = |cur: u64, prev: u64| cur.saturating_sub(prev) as f64;
+    let rd_io = diff(self.rd_io, prev.rd_io);
+    let wr_io = diff(self.wr_io, prev.wr_io);
+    let ticks = diff(self.rd_tick, prev.rd_tick) + diff(self.wr_tick, prev.wr_tick);
+    let interval_ms = update_interval * 1000.0;
 
-  pub fn kb_read_persec(&self, rhs: &Self, update_interval: f64) -> f64 {
-    // 1 sector is 2048-Bytes. So dividing by 2 means conversion into kB.
-    let rsectors = self.rd_sector - rhs.rd_sector;
-    (rsectors as f64 / update_interval) / 2.0
+    DiskRate {
+      rd_io: rd_io / update_interval,
+      wr_io: wr_io / update_interval,
+      rd_kb: diff(self.rd_sector, prev.rd_sector) / 2.0 / update_interval,
+      wr_kb: diff(self.wr_sector, prev.wr_sector) / 2.0 / update_interval,
+      await_ms: if rd_io + wr_io > 0.0 {
+        ticks / (rd_io + wr_io)
+      } else {
+        0.0
+      },
+      util: (diff(self.total_tick, prev.total_tick) / interval_ms * 100.0).min(100.0),
+      queue: diff(self.req_tick, prev.req_tick) / interval_ms,
+    }
   }
+}
+
+// per-second rates between two statistics.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct DiskRate {
+  pub rd_io: f64,    // read requests per sec
+  pub wr_io: f64,    // write requests per sec
+  pub rd_kb: f64,    // kB read per sec
+  pub wr_kb: f64,    // kB written per sec
+  pub await_ms: f64, // average time a request took [ms]
+  pub util: f64,     // [%] of time the device was busy
+  pub queue: f64,    // average # of requests in queue
+}
 
-  pub fn kb_write_persec(&self, rhs: &Self, update_interval: f64) -> f64 {
-    // 1 sector is 2048-Bytes. So dividing by 2 means conversion into kB.
-    let wsectors = self.wr_sector - rhs.wr_sector;
-    (wsectors as f64 / update_interval) / 2.0
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DiskDevice {
+  pub stat: DiskStat,
+  pub is_partition: bool,
+  pub is_virtual: bool, // loop, ram, dm, ...
+}
+
+impl DiskDevice {
+  // partitions and virtual devices are accounted in their underlying disks.
+  pub fn is_hidden(&self) -> bool {
+    self.is_partition || self.is_virtual
   }
 }
 
+pub fn get_devices() -> Vec<DiskDevice> {
+  get_diskstats()
+    .into_iter()
+    .map(|stat| {
+      let dir = path::PathBuf::from(format!("{}/{}", BLOCK_PATH, stat.name.replace('/', "!")));
+      DiskDevice {
+        is_partition: dir.join("partition").exists(),
+        is_virtual: fs::canonicalize(&dir)
+          .map(|p| p.starts_with("/sys/devices/virtual"))
+          .unwrap_or(false),
+        stat,
+      }
+    })
+    .collect()
+}
+
 pub fn get_diskstats() -> Vec<DiskStat> {
   let mut result = vec![];
   let invalid_stat = DiskStat::default();
@@ -173,7 +222,7 @@ mod test {
     let answer = DiskStat {
       major: 259,
       minor: 0,
-      name: "87625".into(),
+      name: "nvme0n1".into(),
       rd_io: 677898,
       rd_merge: 87625,
       rd_sector: 25725175,
@@ -195,6 +244,44 @@ mod test {
     assert_eq!(answer, diskstat);
   }
 
+  #[test]
+  fn test_diskstat_rate() {
+    let prev = DiskStat {
+      rd_io: 100,
+      wr_io: 100,
+      rd_sector: 1000,
+      rd_tick: 50,
+      wr_tick: 50,
+      total_tick: 1000,
+      req_tick: 1000,
+      ..Default::default()
+    };
+    let cur = DiskStat {
+      rd_io: 120,
+      wr_io: 120,
+      rd_sector: 3048,
+      rd_tick: 250,
+      wr_tick: 250,
+      total_tick: 2000,
+      req_tick: 5000,
+      ..Default::default()
+    };
+    let rate = cur.rate(&prev, 2.0);
+    assert_eq!(rate.rd_io, 10.0);
+    assert_eq!(rate.rd_kb, 512.0);
+    assert_eq!(rate.await_ms, 10.0);
+    assert_eq!(rate.util, 50.0);
+    assert_eq!(rate.queue, 2.0);
+  }
+
+  #[test]
+  fn test_get_devices() {
+    let devices = get_devices();
+    for device in devices.iter().filter(|d| d.stat.name.starts_with("loop")) {
+      assert!(device.is_hidden());
+    }
+  }
+
   #[test]
   fn test_get_diskstats() {
     let diskstats = get_diskstats();
@@ -1,26 +1,92 @@
 /*****
 
 /dev/kmsg related funcs.
+cf: https://www.kernel.org/doc/Documentation/ABI/testing/dev-kmsg
 
 *******/
 
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{mpsc, Arc};
-use std::thread;
-use std::time::Duration;
-use std::{
-  fs,
-  io::{self, BufRead},
-};
+use crate::config::{LogConfig, LogSource};
+use crate::resource::journal;
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::fs::OpenOptionsExt;
 
-static BLOCK_LIMIT_MS: u64 = 1;
+static KMSG_PATH: &str = "/dev/kmsg";
+static RECORD_BUFSZ: usize = 8192; // a read(2) shorter than a record fails with EINVAL.
+
+// syslog priority. cf: syslog(2)
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum LogLevel {
+  Emerg = 0,
+  Alert = 1,
+  Crit = 2,
+  Err = 3,
+  Warning = 4,
+  Notice = 5,
+  Info = 6,
+  Debug = 7,
+}
+
+impl LogLevel {
+  // accepts both a number (0-7) and a name.
+  pub fn from(s: &str) -> Option<Self> {
+    use LogLevel::*;
+    let level = match s {
+      "emerg" => Emerg,
+      "alert" => Alert,
+      "crit" => Crit,
+      "err" | "error" => Err,
+      "warn" | "warning" => Warning,
+      "notice" => Notice,
+      "info" => Info,
+      "debug" => Debug,
+      _ => Self::from_priority(s.parse().ok()?)?,
+    };
+    Some(level)
+  }
+
+  pub fn from_priority(pri: u8) -> Option<Self> {
+    use LogLevel::*;
+    let level = match pri {
+      0 => Emerg,
+      1 => Alert,
+      2 => Crit,
+      3 => Err,
+      4 => Warning,
+      5 => Notice,
+      6 => Info,
+      7 => Debug,
+      _ => return None,
+    };
+    Some(level)
+  }
+}
+
+impl std::fmt::Display for LogLevel {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    use LogLevel::*;
+    let s = match self {
+      Emerg => "emerg",
+      Alert => "alert",
+      Crit => "crit",
+      Err => "err",
+      Warning => "warn",
+      Notice => "notice",
+      Info => "info",
+      Debug => "debug",
+    };
+    write!(f, "{}", s)
+  }
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct KmsgLine {
   pub level: u8,
   pub id: u64,
   pub timestamp: u64, // microseconds
+  pub flags: char,    // '-' for a normal record, 'c' for a continuation of the previous one.
   pub log: String,
+  pub dict: Vec<(String, String)>, // key/values in continuation lines. e.g. SUBSYSTEM=usb
 }
 
 impl Default for KmsgLine {
@@ -29,94 +95,260 @@ impl Default for KmsgLine {
       level: 0,
       id: 0,
       timestamp: 0,
+      flags: '-',
       log: "[invalid log]".into(),
+      dict: vec![],
     }
   }
 }
 
 impl KmsgLine {
+  // `level` field also contains facility in its upper bits.
+  pub fn priority(&self) -> LogLevel {
+    LogLevel::from_priority(self.level & 0x7).unwrap()
+  }
+
+  pub fn facility(&self) -> u8 {
+    self.level >> 3
+  }
+
+  pub fn is_continuation(&self) -> bool {
+    self.flags == 'c'
+  }
+
+  pub fn dict_value(&self, key: &str) -> Option<&str> {
+    self
+      .dict
+      .iter()
+      .find(|(k, _)| k == key)
+      .map(|(_, v)| v.as_str())
+  }
+
+  pub fn subsystem(&self) -> Option<&str> {
+    self.dict_value("SUBSYSTEM")
+  }
+
+  pub fn device(&self) -> Option<&str> {
+    self.dict_value("DEVICE")
+  }
+
+  // parse a record. a record is a line of "prefix;message"
+  // optionally followed by continuation lines of " KEY=value".
   pub fn from(s: &str) -> Self {
-    let tokens: Vec<&str> = s.split(',').collect();
+    let mut lines = s.split('\n');
+    let first = lines.next().unwrap_or("");
+    let (prefix, message) = match first.find(';') {
+      Some(i) => (&first[..i], &first[(i + 1)..]),
+      None => return Self::default(),
+    };
+
+    let tokens: Vec<&str> = prefix.split(',').collect();
     if tokens.len() < 3 {
       return Self::default();
     }
+    let level = match tokens[0].parse() {
+      Ok(l) => l,
+      Err(_) => return Self::default(),
+    };
+    let id = match tokens[1].parse() {
+      Ok(i) => i,
+      Err(_) => return Self::default(),
+    };
+    let timestamp = match tokens[2].parse() {
+      Ok(t) => t,
+      Err(_) => return Self::default(),
+    };
+    let flags = tokens.get(3).and_then(|f| f.chars().next()).unwrap_or('-');
 
-    let level = tokens[0].parse().unwrap();
-    let id = tokens[1].parse().unwrap();
-    let timestamp = tokens[2].parse().unwrap();
-
-    /* ignore flags  */
-
-    let _rest = tokens[3..].to_vec().join(",");
-    let rest: Vec<&str> = _rest.split(';').collect();
-    let log = rest[1..].join(";");
+    let mut dict = vec![];
+    for line in lines {
+      if !line.starts_with(' ') {
+        continue;
+      }
+      if let Some((k, v)) = line[1..].split_once('=') {
+        dict.push((k.into(), unescape(v)));
+      }
+    }
 
     Self {
       level,
       id,
       timestamp,
-      log,
+      flags,
+      log: unescape(message),
+      dict,
     }
   }
 }
 
-pub fn get_kmsgs() -> Vec<KmsgLine> {
-  let mut results = vec![];
-  let lines = match read_kmsg_lines_noblock() {
-    Ok(l) => l,
-    Err(_) => return results,
-  };
+// kernel escapes non-printable chars as "\xNN".
+// bytes of multi-byte characters may be escaped one by one, so decode them together.
+fn unescape(s: &str) -> String {
+  let mut res: Vec<u8> = Vec::new();
+  let mut rest = s;
+  while let Some(i) = rest.find("\\x") {
+    res.extend_from_slice(&rest.as_bytes()[..i]);
+    let hex = rest.get((i + 2)..(i + 4));
+    match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+      Some(b) => {
+        res.push(if b.is_ascii_control() { b' ' } else { b });
+        rest = &rest[(i + 4)..];
+      }
+      None => {
+        res.extend_from_slice(b"\\x");
+        rest = &rest[(i + 2)..];
+      }
+    }
+  }
+  res.extend_from_slice(rest.as_bytes());
+  String::from_utf8_lossy(&res).into_owned()
+}
 
-  for l in lines {
-    results.push(KmsgLine::from(&l));
+// push `line` into `lines`, merging it into the last entry if it's a continuation.
+pub fn push_merging(lines: &mut Vec<KmsgLine>, line: KmsgLine) {
+  if line.is_continuation() {
+    if let Some(last) = lines.last_mut() {
+      last.log.push_str(&line.log);
+      last.dict.extend(line.dict);
+      return;
+    }
   }
+  lines.push(line);
+}
 
-  results
+// KmsgReader keeps /dev/kmsg opened and returns only records newer than the last read.
+#[derive(Default)]
+pub struct KmsgReader {
+  file: Option<fs::File>,
+  last_id: Option<u64>, // sequence number of the last record returned.
+  overrun: bool,        // a record was overwritten since the last one returned.
+  pub num_dropped: u64, // # of records overwritten before being read.
 }
 
-fn read_kmsg_lines_noblock() -> Result<Vec<String>, String> {
-  let kmsg = match fs::File::open("/dev/kmsg") {
-    Ok(f) => f,
-    Err(_) => return Err("failed to open /dev/kmsg.".into()),
-  };
-  let must_kill = Arc::new(AtomicBool::new(false));
-  let (value_tx, value_rx) = mpsc::channel();
-  let mut result = vec![];
-
-  let lines = io::BufReader::new(kmsg).lines();
-  let line_read_must_kill = must_kill.clone();
-  let _line_read_handler = thread::spawn(move || {
-    for line in lines {
-      if line_read_must_kill.load(Ordering::Relaxed) {
-        break;
+impl KmsgReader {
+  pub fn new() -> Self {
+    Self {
+      ..Default::default()
+    }
+  }
+
+  fn open(&mut self) -> Result<&mut fs::File, String> {
+    if self.file.is_none() {
+      let file = match fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(KMSG_PATH)
+      {
+        Ok(f) => f,
+        Err(e) => return Err(format!("failed to open {}: {}", KMSG_PATH, e)),
+      };
+      self.file = Some(file);
+    }
+    Ok(self.file.as_mut().unwrap())
+  }
+
+  // returns false for a record already returned, and counts the records skipped by an overrun.
+  fn accept(&mut self, id: u64) -> bool {
+    if let Some(last_id) = self.last_id {
+      // after re-opening, records already returned are read again.
+      if id <= last_id {
+        return false;
       }
-      match line {
-        Ok(s) => value_tx.send(s).unwrap(),
-        Err(_) => break,
+      if self.overrun {
+        self.num_dropped += id - last_id - 1;
       }
     }
-  });
-
-  loop {
-    match value_rx.try_recv() {
-      Ok(v) => result.push(v),
-      Err(_) => {
-        // wait for a limit and retry
-        thread::sleep(Duration::from_millis(BLOCK_LIMIT_MS));
-        match value_rx.try_recv() {
-          Ok(v) => result.push(v),
-          Err(_) => {
-            must_kill.store(true, Ordering::Relaxed);
-            break;
+    self.overrun = false;
+    self.last_id = Some(id);
+    true
+  }
+
+  // read all the records appended after the last call without blocking.
+  pub fn read_new(&mut self) -> Result<Vec<KmsgLine>, String> {
+    let mut results = vec![];
+    let mut buf = vec![0_u8; RECORD_BUFSZ];
+
+    loop {
+      let file = self.open()?;
+      match file.read(&mut buf) {
+        Ok(0) => break,
+        Ok(n) => {
+          let line = KmsgLine::from(&String::from_utf8_lossy(&buf[..n]));
+          if !self.accept(line.id) {
+            continue;
+          }
+          push_merging(&mut results, line);
+        }
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+        // the record was overwritten in the ring buffer. next read returns the oldest available one.
+        Err(e) if e.raw_os_error() == Some(libc::EPIPE) => {
+          self.overrun = true;
+          continue;
+        }
+        Err(e) => {
+          // re-open at the next call.
+          self.file = None;
+          if results.is_empty() {
+            return Err(format!("failed to read {}: {}", KMSG_PATH, e));
           }
+          break;
+        }
+      }
+    }
+
+    Ok(results)
+  }
+}
+
+// records newly read, and the error of the read if it failed.
+pub type LogRead = (Vec<KmsgLine>, Option<String>);
+
+// LogReader reads records from either /dev/kmsg or the journal.
+pub enum LogReader {
+  Kmsg(KmsgReader),
+  Journal(journal::JournalReader),
+}
+
+impl LogReader {
+  pub fn new(conf: &LogConfig) -> Self {
+    let journal = || {
+      Self::Journal(journal::JournalReader::new(
+        conf.unit.clone(),
+        conf.priority,
+      ))
+    };
+    match conf.source {
+      LogSource::Kmsg => Self::Kmsg(KmsgReader::new()),
+      LogSource::Journal => journal(),
+      LogSource::Auto => {
+        let mut reader = KmsgReader::new();
+        match reader.open() {
+          Ok(_) => Self::Kmsg(reader),
+          Err(_) => journal(),
         }
       }
     }
   }
 
-  //let _ = line_read_handler.join();
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Kmsg(_) => "dmesg",
+      Self::Journal(_) => "journal",
+    }
+  }
+
+  pub fn read_new(&mut self) -> Result<Vec<KmsgLine>, String> {
+    match self {
+      Self::Kmsg(reader) => reader.read_new(),
+      Self::Journal(reader) => reader.read_new(),
+    }
+  }
+}
 
-  Ok(result)
+// read all the records currently in the ring buffer.
+pub fn get_kmsgs() -> Vec<KmsgLine> {
+  KmsgReader::new().read_new().unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -125,10 +357,21 @@ pub mod tests {
 
   #[test]
   fn test_read_kmsg() {
-    let lines = read_kmsg_lines_noblock();
+    let mut reader = KmsgReader::new();
+    let lines = reader.read_new();
     assert_eq!(lines.is_ok(), true);
   }
 
+  #[test]
+  fn test_read_kmsg_incremental() {
+    let mut reader = KmsgReader::new();
+    let first = reader.read_new().unwrap();
+    let second = reader.read_new().unwrap();
+    if let (Some(last), Some(next)) = (first.last(), second.first()) {
+      assert!(last.id < next.id);
+    }
+  }
+
   #[test]
   fn test_parse_line() {
     let a1 = "3,122793,256982503404,-;usb usb2-port2: Cannot enable. Maybe the USB cable is bad?";
@@ -137,8 +380,58 @@ pub mod tests {
       level: 3,
       id: 122793,
       timestamp: 256982503404,
+      flags: '-',
       log: "usb usb2-port2: Cannot enable. Maybe the USB cable is bad?".into(),
+      dict: vec![],
     };
     assert_eq!(c1, b1);
   }
+
+  #[test]
+  fn test_parse_dict() {
+    let a1 =
+      "6,339,5140900,-;NET: Registered protocol family 10\\x0a(ipv6)\n SUBSYSTEM=net\n DEVICE=n8\n";
+    let b1 = KmsgLine::from(a1);
+    assert_eq!(b1.log, "NET: Registered protocol family 10 (ipv6)");
+    assert_eq!(b1.subsystem(), Some("net"));
+    assert_eq!(b1.device(), Some("n8"));
+  }
+
+  #[test]
+  fn test_unescape() {
+    assert_eq!(unescape("caf\\xc3\\xa9 \\xe2\\x9c\\x93"), "café ✓");
+    assert_eq!(unescape("a\\x1bb\\xzz"), "a b\\xzz");
+    assert_eq!(unescape("bad \\xff"), "bad \u{fffd}");
+  }
+
+  #[test]
+  fn test_merge_continuation() {
+    let mut lines = vec![];
+    push_merging(&mut lines, KmsgLine::from("4,10,100,+;foo "));
+    push_merging(&mut lines, KmsgLine::from("4,11,101,c;bar"));
+    push_merging(&mut lines, KmsgLine::from("4,12,102,-;baz"));
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].log, "foo bar");
+  }
+
+  #[test]
+  fn test_count_dropped() {
+    let mut reader = KmsgReader::new();
+    assert!(reader.accept(10));
+    assert!(!reader.accept(10));
+    reader.overrun = true;
+    assert!(reader.accept(15));
+    assert!(reader.accept(16));
+    assert_eq!(reader.num_dropped, 4);
+  }
+
+  #[test]
+  fn test_priority() {
+    let line = KmsgLine::from("12,864,3092164,-;systemd[1]: Started Journal Service.");
+    assert_eq!(line.priority(), LogLevel::Warning);
+    assert_eq!(line.facility(), 1);
+    assert_eq!(LogLevel::from("err"), Some(LogLevel::Err));
+    assert_eq!(LogLevel::from("6"), Some(LogLevel::Info));
+    assert_eq!(LogLevel::from("8"), None);
+  }
 }
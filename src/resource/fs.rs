@@ -0,0 +1,247 @@
+/*****
+
+/proc/self/mountinfo and statvfs(3) related funcs.
+cf: https://www.kernel.org/doc/Documentation/filesystems/proc.rst (3.5 /proc/<pid>/mountinfo)
+
+*******/
+
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fs;
+
+static MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+// filesystems which have no backing storage.
+static PSEUDO_FSTYPES: [&str; 24] = [
+  "autofs",
+  "binfmt_misc",
+  "bpf",
+  "cgroup",
+  "cgroup2",
+  "configfs",
+  "debugfs",
+  "devpts",
+  "devtmpfs",
+  "efivarfs",
+  "fusectl",
+  "hugetlbfs",
+  "mqueue",
+  "nsfs",
+  "proc",
+  "pstore",
+  "ramfs",
+  "rpc_pipefs",
+  "securityfs",
+  "squashfs", // read-only images such as snaps. always 100% used.
+  "sysfs",
+  "tmpfs",
+  "tracefs",
+  "fuse.gvfsd-fuse",
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MountInfo {
+  pub mount_id: u32,
+  pub major: u32,
+  pub minor: u32,
+  pub root: String,        // root of the mount within the filesystem
+  pub mount_point: String, // relative to the root of the process
+  pub fstype: String,
+  pub source: String, // eg: /dev/sda1
+}
+
+impl MountInfo {
+  // "36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue"
+  pub fn from(line: &str) -> Option<Self> {
+    let (left, right) = line.split_at(line.find(" - ")?);
+    let left: Vec<&str> = left.split_whitespace().collect();
+    let right: Vec<&str> = right[3..].split_whitespace().collect();
+    if left.len() < 6 || right.len() < 2 {
+      return None;
+    }
+    let (major, minor) = left[2].split_once(':')?;
+
+    Some(Self {
+      mount_id: left[0].parse().ok()?,
+      major: major.parse().ok()?,
+      minor: minor.parse().ok()?,
+      root: unescape(left[3]),
+      mount_point: unescape(left[4]),
+      fstype: right[0].into(),
+      source: unescape(right[1]),
+    })
+  }
+
+  pub fn is_pseudo(&self) -> bool {
+    PSEUDO_FSTYPES.contains(&self.fstype.as_str())
+  }
+}
+
+// kernel escapes space, tab, newline and backslash as "\ooo".
+fn unescape(s: &str) -> String {
+  let mut res = String::new();
+  let mut rest = s;
+  while let Some(i) = rest.find('\\') {
+    res.push_str(&rest[..i]);
+    match rest
+      .get((i + 1)..(i + 4))
+      .and_then(|o| u8::from_str_radix(o, 8).ok())
+    {
+      Some(b) => {
+        res.push(b as char);
+        rest = &rest[(i + 4)..];
+      }
+      None => {
+        res.push('\\');
+        rest = &rest[(i + 1)..];
+      }
+    }
+  }
+  res.push_str(rest);
+  res
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FsUsage {
+  pub mount: MountInfo,
+  pub size: u64,  // [Bytes]
+  pub used: u64,  // [Bytes]
+  pub avail: u64, // [Bytes] available for unprivileged users
+  pub inodes: u64,
+  pub inodes_free: u64,
+}
+
+impl FsUsage {
+  // same as `df`. reserved blocks are not counted as available.
+  pub fn used_ratio(&self) -> f64 {
+    if self.used + self.avail == 0 {
+      0.0
+    } else {
+      self.used as f64 / (self.used + self.avail) as f64
+    }
+  }
+
+  // free blocks can exceed the total on a racy or bogus statvfs as well.
+  // types of the fields differ between targets.
+  #[allow(clippy::unnecessary_cast)]
+  fn from_statvfs(mount: &MountInfo, buf: libc::statvfs) -> Self {
+    let frsize = buf.f_frsize as u64;
+    Self {
+      mount: mount.clone(),
+      size: buf.f_blocks as u64 * frsize,
+      used: (buf.f_blocks as u64).saturating_sub(buf.f_bfree as u64) * frsize,
+      avail: buf.f_bavail as u64 * frsize,
+      inodes: buf.f_files as u64,
+      inodes_free: buf.f_ffree as u64,
+    }
+  }
+
+  // some filesystems (eg: btrfs) have no fixed inode table.
+  // free inodes can exceed the total on a racy or bogus statvfs.
+  pub fn inodes_ratio(&self) -> Option<f64> {
+    if self.inodes == 0 {
+      None
+    } else {
+      Some(self.inodes.saturating_sub(self.inodes_free) as f64 / self.inodes as f64)
+    }
+  }
+}
+
+pub fn get_mounts() -> Vec<MountInfo> {
+  match fs::read_to_string(MOUNTINFO_PATH) {
+    Ok(s) => s.lines().filter_map(MountInfo::from).collect(),
+    Err(_) => vec![],
+  }
+}
+
+pub fn statvfs(mount: &MountInfo) -> Option<FsUsage> {
+  let path = CString::new(mount.mount_point.as_str()).ok()?;
+  let mut buf: libc::statvfs = unsafe { std::mem::zeroed() };
+  if unsafe { libc::statvfs(path.as_ptr(), &mut buf) } != 0 {
+    return None;
+  }
+
+  Some(FsUsage::from_statvfs(mount, buf))
+}
+
+// usages of real filesystems. a device mounted more than once (eg: bind mounts) appears once.
+pub fn get_fs_usages() -> Vec<FsUsage> {
+  let mut mounts: Vec<MountInfo> = get_mounts()
+    .into_iter()
+    .filter(|m| !m.is_pseudo())
+    .collect();
+  // keep the shortest mount point of each device.
+  mounts.sort_by_key(|m| m.mount_point.len());
+  let mut seen = HashSet::new();
+  mounts.retain(|m| seen.insert((m.major, m.minor)));
+
+  let mut result: Vec<FsUsage> = mounts
+    .iter()
+    .filter_map(statvfs)
+    .filter(|u| u.size != 0)
+    .collect();
+  result.sort_by(|a, b| a.mount.mount_point.cmp(&b.mount.mount_point));
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_mountinfo_from() {
+    let line =
+      r"36 35 98:0 /mnt1 /mnt/my\040disk rw,noatime master:1 - ext3 /dev/root rw,errors=continue";
+    let answer = MountInfo {
+      mount_id: 36,
+      major: 98,
+      minor: 0,
+      root: "/mnt1".into(),
+      mount_point: "/mnt/my disk".into(),
+      fstype: "ext3".into(),
+      source: "/dev/root".into(),
+    };
+    assert_eq!(MountInfo::from(line), Some(answer));
+    assert_eq!(MountInfo::from("invalid line"), None);
+
+    let proc = MountInfo::from("22 1 0:21 / /proc rw - proc proc rw").unwrap();
+    assert!(proc.is_pseudo());
+  }
+
+  #[test]
+  fn test_fs_usage() {
+    let usage = FsUsage {
+      used: 30,
+      avail: 70,
+      size: 110,
+      inodes: 10,
+      inodes_free: 9,
+      ..Default::default()
+    };
+    assert_eq!(usage.used_ratio(), 0.3);
+    assert_eq!(usage.inodes_ratio(), Some(0.1));
+
+    let usage = FsUsage {
+      inodes: 10,
+      inodes_free: 11,
+      ..Default::default()
+    };
+    assert_eq!(usage.inodes_ratio(), Some(0.0));
+    assert_eq!(FsUsage::default().inodes_ratio(), None);
+
+    let mut buf: libc::statvfs = unsafe { std::mem::zeroed() };
+    buf.f_frsize = 4096;
+    buf.f_blocks = 10;
+    buf.f_bfree = 12;
+    let usage = FsUsage::from_statvfs(&MountInfo::default(), buf);
+    assert_eq!((usage.size, usage.used), (40960, 0));
+  }
+
+  #[test]
+  fn test_get_fs_usages() {
+    let root = get_mounts().into_iter().find(|m| m.mount_point == "/");
+    let usage = statvfs(&root.unwrap()).unwrap();
+    assert!(usage.size >= usage.used);
+  }
+}
@@ -0,0 +1,170 @@
+/*****
+
+systemd journal related funcs.
+This is a wrapper of `journalctl -o json`, used as a log source
+when /dev/kmsg is not readable. (eg: `kernel.dmesg_restrict=1`)
+
+*******/
+
+use crate::resource::dmesg::KmsgLine;
+use std::process::Command;
+
+static JOURNALCTL: &str = "journalctl";
+static INITIAL_LINES: usize = 300; // # of entries read at the first call.
+
+#[derive(Default)]
+pub struct JournalReader {
+  unit: Option<String>,   // read only entries of this systemd unit.
+  priority: Option<u8>,   // read only entries of this priority and above.
+  cursor: Option<String>, // cursor of the last entry returned.
+  next_id: u64,
+}
+
+impl JournalReader {
+  pub fn new(unit: Option<String>, priority: Option<u8>) -> Self {
+    Self {
+      unit,
+      priority,
+      ..Default::default()
+    }
+  }
+
+  fn args(&self) -> Vec<String> {
+    let mut args: Vec<String> = vec!["-o".into(), "json".into(), "-b".into(), "-q".into()];
+    args.push("--no-pager".into());
+    match &self.cursor {
+      Some(cursor) => args.push(format!("--after-cursor={}", cursor)),
+      None => args.push(format!("--lines={}", INITIAL_LINES)),
+    }
+    if let Some(unit) = &self.unit {
+      args.push(format!("--unit={}", unit));
+    }
+    if let Some(priority) = self.priority {
+      args.push(format!("--priority={}", priority));
+    }
+    args
+  }
+
+  // read all the entries appended after the last call.
+  pub fn read_new(&mut self) -> Result<Vec<KmsgLine>, String> {
+    let output = match Command::new(JOURNALCTL).args(self.args()).output() {
+      Ok(output) => output,
+      Err(e) => return Err(format!("failed to exec {}: {}", JOURNALCTL, e)),
+    };
+    if !output.status.success() {
+      return Err(format!(
+        "{} failed: {}",
+        JOURNALCTL,
+        String::from_utf8_lossy(&output.stderr).trim()
+      ));
+    }
+
+    let mut results = vec![];
+    for entry in String::from_utf8_lossy(&output.stdout).lines() {
+      if let Some((line, cursor)) = parse_entry(entry, self.next_id) {
+        self.cursor = Some(cursor);
+        self.next_id += 1;
+        results.push(line);
+      }
+    }
+    Ok(results)
+  }
+}
+
+// journal exports a number as a string, and a non-UTF8 message as an array of bytes.
+fn field_string(value: &serde_json::Value) -> Option<String> {
+  use serde_json::Value;
+  match value {
+    Value::String(s) => Some(s.clone()),
+    Value::Array(bytes) => {
+      let bytes: Vec<u8> = bytes
+        .iter()
+        .filter_map(|b| b.as_u64())
+        .map(|b| b as u8)
+        .collect();
+      Some(String::from_utf8_lossy(&bytes).into())
+    }
+    _ => None,
+  }
+}
+
+// parse an entry of `journalctl -o json` into a record and its cursor.
+pub fn parse_entry(s: &str, id: u64) -> Option<(KmsgLine, String)> {
+  let entry: serde_json::Value = serde_json::from_str(s).ok()?;
+  let field = |key: &str| entry.get(key).and_then(field_string);
+
+  let cursor = field("__CURSOR")?;
+  let message = field("MESSAGE").unwrap_or_default();
+  // kernel messages are kept as dmesg shows them, so that events in them are detected.
+  let log = match field("SYSLOG_IDENTIFIER") {
+    Some(ident) if field("_TRANSPORT").as_deref() != Some("kernel") => {
+      format!("{}: {}", ident, message)
+    }
+    _ => message,
+  };
+  let mut dict = vec![];
+  if let Some(unit) = field("_SYSTEMD_UNIT") {
+    dict.push(("UNIT".into(), unit));
+  }
+
+  let line = KmsgLine {
+    level: field("PRIORITY").and_then(|p| p.parse().ok()).unwrap_or(6),
+    id,
+    timestamp: field("__MONOTONIC_TIMESTAMP")
+      .and_then(|t| t.parse().ok())
+      .unwrap_or(0),
+    flags: '-',
+    log: log.replace(|c: char| c.is_ascii_control(), " "),
+    dict,
+  };
+  Some((line, cursor))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::resource::dmesg::LogLevel;
+
+  #[test]
+  fn test_parse_entry() {
+    let s = r#"{"__CURSOR":"s=abc;i=1f","__MONOTONIC_TIMESTAMP":"5140900","PRIORITY":"3","SYSLOG_IDENTIFIER":"sshd","_SYSTEMD_UNIT":"ssh.service","MESSAGE":"error: kex\nfailed"}"#;
+    let (line, cursor) = parse_entry(s, 7).unwrap();
+    assert_eq!(cursor, "s=abc;i=1f");
+    assert_eq!(line.id, 7);
+    assert_eq!(line.timestamp, 5140900);
+    assert_eq!(line.priority(), LogLevel::Err);
+    assert_eq!(line.log, "sshd: error: kex failed");
+    assert_eq!(line.dict_value("UNIT"), Some("ssh.service"));
+  }
+
+  #[test]
+  fn test_parse_binary_message() {
+    let s = r#"{"__CURSOR":"c","MESSAGE":[104,105,255]}"#;
+    let (line, _) = parse_entry(s, 0).unwrap();
+    assert_eq!(line.log, "hi\u{fffd}");
+    assert!(parse_entry(r#"{"MESSAGE":"no cursor"}"#, 0).is_none());
+  }
+
+  #[test]
+  fn test_parse_kernel_entry() {
+    let s = r#"{"__CURSOR":"c","_TRANSPORT":"kernel","SYSLOG_IDENTIFIER":"kernel","MESSAGE":"a.out[1234]: segfault at 0 ip 0000 sp 0000 error 4"}"#;
+    let (line, _) = parse_entry(s, 0).unwrap();
+    assert_eq!(
+      line.log,
+      "a.out[1234]: segfault at 0 ip 0000 sp 0000 error 4"
+    );
+    let event = crate::resource::kevent::classify(&line).unwrap();
+    assert_eq!(event.pid, Some(1234));
+    assert_eq!(event.comm.as_deref(), Some("a.out"));
+  }
+
+  #[test]
+  fn test_journal_args() {
+    let mut reader = JournalReader::new(Some("ssh.service".into()), Some(4));
+    assert!(reader.args().contains(&"--lines=300".to_string()));
+    assert!(reader.args().contains(&"--unit=ssh.service".to_string()));
+    assert!(reader.args().contains(&"--priority=4".to_string()));
+    reader.cursor = Some("c".into());
+    assert!(reader.args().contains(&"--after-cursor=c".to_string()));
+  }
+}
@@ -0,0 +1,205 @@
+/*****
+
+Classification of kernel log lines into notable events,
+such as OOM kills, segfaults, hung tasks, I/O errors and MCEs.
+
+*******/
+
+use super::dmesg::KmsgLine;
+use regex::Regex;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+  OomKill,
+  Segfault,
+  HungTask,
+  IoError,
+  Mce,
+}
+
+impl EventKind {
+  pub fn all() -> [EventKind; 5] {
+    use EventKind::*;
+    [OomKill, Segfault, HungTask, IoError, Mce]
+  }
+}
+
+impl std::fmt::Display for EventKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    use EventKind::*;
+    let s = match self {
+      OomKill => "OOM",
+      Segfault => "SEGV",
+      HungTask => "HUNG",
+      IoError => "I/O",
+      Mce => "MCE",
+    };
+    write!(f, "{}", s)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KernelEvent {
+  pub kind: EventKind,
+  pub pid: Option<i32>,       // victim/offending task.
+  pub comm: Option<String>,   // comm of the task.
+  pub device: Option<String>, // device of I/O error.
+}
+
+impl KernelEvent {
+  fn new(kind: EventKind) -> Self {
+    Self {
+      kind,
+      pid: None,
+      comm: None,
+      device: None,
+    }
+  }
+
+  fn with_task(kind: EventKind, pid: &str, comm: &str) -> Self {
+    Self {
+      pid: pid.parse().ok(),
+      comm: Some(comm.into()),
+      ..Self::new(kind)
+    }
+  }
+}
+
+struct Patterns {
+  oom: Regex,
+  segfault: Regex,
+  hung: Regex,
+  io: Regex,
+  mce: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+  static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+  PATTERNS.get_or_init(|| Patterns {
+    // "Out of memory: Killed process 1234 (stress)", "Memory cgroup out of memory: Kill process 1234 (stress)"
+    oom: Regex::new(r"[Oo]ut of memory: Kill(?:ed)? process (\d+) \(([^)]*)\)").unwrap(),
+    // "stress[1234]: segfault at 0 ip ..."
+    segfault: Regex::new(r"^(.+)\[(\d+)\]: segfault at ").unwrap(),
+    // "INFO: task kworker/0:1:123 blocked for more than 120 seconds."
+    hung: Regex::new(r"task (.+):(\d+) blocked for more than \d+ seconds").unwrap(),
+    // "I/O error, dev sda, sector 1234", "Buffer I/O error on dev sda1, logical block 0"
+    io: Regex::new(r"I/O error,? (?:on )?dev ([^ ,]+)").unwrap(),
+    // "mce: [Hardware Error]: ...", "Machine check events logged"
+    mce: Regex::new(r"\[Hardware Error\]|Machine check events logged").unwrap(),
+  })
+}
+
+pub fn classify(line: &KmsgLine) -> Option<KernelEvent> {
+  use EventKind::*;
+  let p = patterns();
+  let log = &line.log;
+
+  if let Some(c) = p.oom.captures(log) {
+    return Some(KernelEvent::with_task(OomKill, &c[1], &c[2]));
+  }
+  if let Some(c) = p.segfault.captures(log) {
+    return Some(KernelEvent::with_task(Segfault, &c[2], &c[1]));
+  }
+  if let Some(c) = p.hung.captures(log) {
+    return Some(KernelEvent::with_task(HungTask, &c[2], &c[1]));
+  }
+  if let Some(c) = p.io.captures(log) {
+    return Some(KernelEvent {
+      device: Some(c[1].into()),
+      ..KernelEvent::new(IoError)
+    });
+  }
+  if p.mce.is_match(log) {
+    return Some(KernelEvent::new(Mce));
+  }
+
+  None
+}
+
+// counter of events seen so far.
+#[derive(Debug, Default)]
+pub struct EventSummary {
+  counts: Vec<(EventKind, u32)>,
+  pub last_arrival: Option<Instant>, // time the newest event arrived.
+}
+
+impl EventSummary {
+  pub fn add(&mut self, event: &KernelEvent) {
+    match self.counts.iter_mut().find(|(k, _)| *k == event.kind) {
+      Some((_, n)) => *n += 1,
+      None => self.counts.push((event.kind, 1)),
+    }
+  }
+
+  pub fn count(&self, kind: EventKind) -> u32 {
+    self
+      .counts
+      .iter()
+      .find(|(k, _)| *k == kind)
+      .map_or(0, |(_, n)| *n)
+  }
+
+  pub fn total(&self) -> u32 {
+    self.counts.iter().map(|(_, n)| n).sum()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn classify_str(log: &str) -> Option<KernelEvent> {
+    classify(&KmsgLine {
+      log: log.into(),
+      ..Default::default()
+    })
+  }
+
+  #[test]
+  fn test_classify_task_events() {
+    let oom = classify_str("Out of memory: Killed process 4242 (stress) total-vm:1000kB").unwrap();
+    assert_eq!(oom.kind, EventKind::OomKill);
+    assert_eq!(oom.pid, Some(4242));
+    assert_eq!(oom.comm, Some("stress".into()));
+
+    let segv = classify_str("a.out[1234]: segfault at 0 ip 000055d1 sp 00007ffc error 4").unwrap();
+    assert_eq!(segv.kind, EventKind::Segfault);
+    assert_eq!(segv.pid, Some(1234));
+    assert_eq!(segv.comm, Some("a.out".into()));
+
+    let hung =
+      classify_str("INFO: task kworker/0:1:123 blocked for more than 120 seconds.").unwrap();
+    assert_eq!(hung.kind, EventKind::HungTask);
+    assert_eq!(hung.pid, Some(123));
+    assert_eq!(hung.comm, Some("kworker/0:1".into()));
+  }
+
+  #[test]
+  fn test_classify_hw_events() {
+    let io =
+      classify_str("blk_update_request: I/O error, dev sda, sector 2048 op 0x0:(READ)").unwrap();
+    assert_eq!(io.kind, EventKind::IoError);
+    assert_eq!(io.device, Some("sda".into()));
+    let io =
+      classify_str("Buffer I/O error on dev sdb1, logical block 0, async page read").unwrap();
+    assert_eq!(io.device, Some("sdb1".into()));
+
+    let mce = classify_str("mce: [Hardware Error]: Machine check events logged").unwrap();
+    assert_eq!(mce.kind, EventKind::Mce);
+
+    assert_eq!(classify_str("usb 1-1: new high-speed USB device"), None);
+  }
+
+  #[test]
+  fn test_summary() {
+    let mut summary = EventSummary::default();
+    summary.add(&KernelEvent::new(EventKind::Mce));
+    summary.add(&KernelEvent::new(EventKind::Mce));
+    summary.add(&KernelEvent::new(EventKind::IoError));
+    assert_eq!(summary.count(EventKind::Mce), 2);
+    assert_eq!(summary.count(EventKind::OomKill), 0);
+    assert_eq!(summary.total(), 3);
+  }
+}
@@ -1,50 +1,104 @@
 /*****
 
 /sys/class/net/<interface>/statistics related funcs.
+cf: https://www.kernel.org/doc/Documentation/ABI/testing/sysfs-class-net
 
 *******/
 
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::{fs, path};
 
-#[derive(Debug)]
+static NET_PATH: &str = "/sys/class/net";
+
+#[derive(Debug, Clone, Default)]
 pub struct NetStatistics {
   pub interface: String,
   pub rx_bytes: u64,
   pub tx_bytes: u64,
+  pub rx_packets: u64,
+  pub tx_packets: u64,
+  pub rx_errors: u64,
+  pub tx_errors: u64,
+  pub rx_dropped: u64,
+  pub tx_dropped: u64,
+  pub rx_over_errors: u64, // receiver ring buffer overrun
+}
+
+// per-second rates between two statistics.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetRate {
+  pub rx_bytes: f64,
+  pub tx_bytes: f64,
+  pub rx_packets: f64,
+  pub tx_packets: f64,
+  pub errors: f64, // errors, drops and overruns of both directions.
+}
+
+impl NetStatistics {
+  fn errors(&self) -> u64 {
+    self.rx_errors + self.tx_errors + self.rx_dropped + self.tx_dropped + self.rx_over_errors
+  }
+
+  // counters can go back when the interface is re-created.
+  pub fn rate(&self, prev: &Self, interval: f64) -> NetRate {
+    let per_sec = |cur: u64, prev: u64| cur.saturating_sub(prev) as f64 / interval;
+    NetRate {
+      rx_bytes: per_sec(self.rx_bytes, prev.rx_bytes),
+      tx_bytes: per_sec(self.tx_bytes, prev.tx_bytes),
+      rx_packets: per_sec(self.rx_packets, prev.rx_packets),
+      tx_packets: per_sec(self.tx_packets, prev.tx_packets),
+      errors: per_sec(self.errors(), prev.errors()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NetInterface {
+  pub name: String,
+  pub operstate: String,      // "up", "down", "unknown", ...
+  pub speed: Option<u32>,     // [Mbits/sec]. not available for virtual or down interfaces.
+  pub mtu: u32,               //
+  pub is_virtual: bool,       // lo, bridges, veths, tunnels, ...
+  pub addresses: Vec<String>, // IPv4 and IPv6 addresses
+  pub statistics: NetStatistics,
 }
 
 fn list_interfaces() -> Vec<String> {
-  let interfaces_dir = fs::read_dir("/sys/class/net").unwrap();
+  let interfaces_dir = match fs::read_dir(NET_PATH) {
+    Ok(d) => d,
+    Err(_) => return vec![],
+  };
   interfaces_dir
-    .map(|d| {
-      d.unwrap()
-        .path()
-        .file_name()
-        .unwrap()
-        .to_string_lossy()
-        .to_string()
-    })
+    .filter_map(|d| d.ok())
+    .map(|d| d.file_name().to_string_lossy().to_string())
     .collect()
 }
 
-pub fn get_statistic(interface: &str) -> Option<NetStatistics> {
-  let netpath = path::PathBuf::from(format!("/sys/class/net/{}/statistics", interface));
-  let rx_file = format!("{}/rx_bytes", netpath.to_str().unwrap());
-  let tx_file = format!("{}/tx_bytes", netpath.to_str().unwrap());
+fn read_string(path: &path::Path) -> Option<String> {
+  fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
 
-  let rx_bytes = match fs::read_to_string(rx_file) {
-    Ok(s) => s.trim().parse::<u64>().unwrap(),
-    Err(_) => return None,
-  };
-  let tx_bytes = match fs::read_to_string(tx_file) {
-    Ok(s) => s.trim().parse::<u64>().unwrap(),
-    Err(_) => return None,
-  };
+fn read_u64(path: &path::Path) -> Option<u64> {
+  read_string(path)?.parse().ok()
+}
+
+pub fn get_statistic(interface: &str) -> Option<NetStatistics> {
+  let netpath = path::PathBuf::from(format!("{}/{}/statistics", NET_PATH, interface));
+  let read = |name: &str| read_u64(&netpath.join(name));
 
   Some(NetStatistics {
     interface: interface.into(),
-    rx_bytes,
-    tx_bytes,
+    rx_bytes: read("rx_bytes")?,
+    tx_bytes: read("tx_bytes")?,
+    rx_packets: read("rx_packets").unwrap_or(0),
+    tx_packets: read("tx_packets").unwrap_or(0),
+    rx_errors: read("rx_errors").unwrap_or(0),
+    tx_errors: read("tx_errors").unwrap_or(0),
+    rx_dropped: read("rx_dropped").unwrap_or(0),
+    tx_dropped: read("tx_dropped").unwrap_or(0),
+    rx_over_errors: read("rx_over_errors").unwrap_or(0),
   })
 }
 
@@ -61,6 +115,77 @@ pub fn get_statistic_all() -> Vec<NetStatistics> {
   result
 }
 
+// returns addresses of each interface.
+fn get_addresses() -> HashMap<String, Vec<String>> {
+  let mut result: HashMap<String, Vec<String>> = HashMap::new();
+  let mut ifap: *mut libc::ifaddrs = std::ptr::null_mut();
+  if unsafe { libc::getifaddrs(&mut ifap) } != 0 {
+    return result;
+  }
+
+  let mut cur = ifap;
+  while !cur.is_null() {
+    let ifa = unsafe { &*cur };
+    cur = ifa.ifa_next;
+    if ifa.ifa_addr.is_null() {
+      continue;
+    }
+    let addr = match unsafe { (*ifa.ifa_addr).sa_family } as i32 {
+      libc::AF_INET => {
+        let sin = unsafe { &*(ifa.ifa_addr as *const libc::sockaddr_in) };
+        Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr)).to_string()
+      }
+      libc::AF_INET6 => {
+        let sin6 = unsafe { &*(ifa.ifa_addr as *const libc::sockaddr_in6) };
+        Ipv6Addr::from(sin6.sin6_addr.s6_addr).to_string()
+      }
+      _ => continue,
+    };
+    let name = unsafe { CStr::from_ptr(ifa.ifa_name) }
+      .to_string_lossy()
+      .to_string();
+    result.entry(name).or_default().push(addr);
+  }
+  unsafe { libc::freeifaddrs(ifap) };
+
+  result
+}
+
+pub fn get_interface(
+  name: &str,
+  addresses: &mut HashMap<String, Vec<String>>,
+) -> Option<NetInterface> {
+  let dir = path::PathBuf::from(format!("{}/{}", NET_PATH, name));
+  // interfaces not backed by any hardware live under /sys/devices/virtual/net.
+  let is_virtual = fs::canonicalize(&dir)
+    .map(|p| p.starts_with("/sys/devices/virtual"))
+    .unwrap_or(false);
+
+  Some(NetInterface {
+    name: name.into(),
+    operstate: read_string(&dir.join("operstate")).unwrap_or_else(|| "?".into()),
+    // reading speed fails with EINVAL when the link is down.
+    speed: read_u64(&dir.join("speed"))
+      .filter(|&s| s > 0 && s < u32::MAX as u64)
+      .map(|s| s as u32),
+    mtu: read_u64(&dir.join("mtu")).unwrap_or(0) as u32,
+    is_virtual,
+    addresses: addresses.remove(name).unwrap_or_default(),
+    statistics: get_statistic(name)?,
+  })
+}
+
+pub fn get_interfaces() -> Vec<NetInterface> {
+  let mut addresses = get_addresses();
+  let mut result: Vec<NetInterface> = list_interfaces()
+    .iter()
+    .filter_map(|name| get_interface(name, &mut addresses))
+    .collect();
+  result.sort_by(|a, b| a.name.cmp(&b.name));
+
+  result
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -70,4 +195,34 @@ mod tests {
     let is = get_statistic_all();
     assert_eq!(is.is_empty(), false);
   }
+
+  #[test]
+  fn test_rate() {
+    let prev = NetStatistics {
+      rx_bytes: 1000,
+      tx_bytes: 500,
+      rx_dropped: 1,
+      ..Default::default()
+    };
+    let cur = NetStatistics {
+      rx_bytes: 3000,
+      tx_bytes: 100, // reset
+      rx_dropped: 3,
+      rx_over_errors: 2,
+      ..Default::default()
+    };
+    let rate = cur.rate(&prev, 2.0);
+    assert_eq!(rate.rx_bytes, 1000.0);
+    assert_eq!(rate.tx_bytes, 0.0);
+    assert_eq!(rate.errors, 2.0);
+  }
+
+  #[test]
+  fn test_loopback_interface() {
+    let interfaces = get_interfaces();
+    let lo = interfaces.iter().find(|i| i.name == "lo").unwrap();
+    assert!(lo.is_virtual);
+    assert!(lo.mtu > 0);
+    assert!(lo.addresses.iter().any(|a| a == "127.0.0.1" || a == "::1"));
+  }
 }
@@ -0,0 +1,192 @@
+/*****
+
+Pressure Stall Information related funcs.
+cf: https://www.kernel.org/doc/Documentation/accounting/psi.rst
+
+xtop assumes kernel 4.20+ with CONFIG_PSI=y.
+Per-cgroup pressure is available only on cgroup v2.
+
+*******/
+
+use std::fs;
+
+static PRESSURE_PATH: &str = "/proc/pressure";
+// cgroup v2 is mounted at the root, or at "unified" on hybrid hierarchy.
+static CGROUP_ROOTS: [&str; 2] = ["/sys/fs/cgroup", "/sys/fs/cgroup/unified"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PsiLine {
+  pub avg10: f64, // [%] of time stalled in the last 10 secs
+  pub avg60: f64,
+  pub avg300: f64,
+  pub total: u64, // [usec] total stall time
+}
+
+impl PsiLine {
+  // "avg10=0.00 avg60=0.00 avg300=0.00 total=0"
+  fn from(s: &str) -> Option<Self> {
+    let mut line = Self::default();
+    for kv in s.split_whitespace() {
+      let (key, val) = kv.split_once('=')?;
+      match key {
+        "avg10" => line.avg10 = val.parse().ok()?,
+        "avg60" => line.avg60 = val.parse().ok()?,
+        "avg300" => line.avg300 = val.parse().ok()?,
+        "total" => line.total = val.parse().ok()?,
+        _ => {}
+      }
+    }
+    Some(line)
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pressure {
+  pub some: PsiLine, // some tasks are stalled.
+  pub full: PsiLine, // all non-idle tasks are stalled at the same time. always 0 for system-wide cpu.
+}
+
+impl Pressure {
+  pub fn from(s: &str) -> Option<Self> {
+    let mut some = None;
+    let mut full = PsiLine::default(); // cpu has no "full" line before kernel 5.13.
+    for line in s.lines() {
+      match line.split_once(' ') {
+        Some(("some", rest)) => some = PsiLine::from(rest),
+        Some(("full", rest)) => full = PsiLine::from(rest)?,
+        _ => {}
+      }
+    }
+    Some(Self { some: some?, full })
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PsiResource {
+  Cpu,
+  Memory,
+  Io,
+}
+
+impl PsiResource {
+  pub fn all() -> [PsiResource; 3] {
+    use PsiResource::*;
+    [Cpu, Memory, Io]
+  }
+
+  fn file_name(&self) -> &str {
+    use PsiResource::*;
+    match self {
+      Cpu => "cpu",
+      Memory => "memory",
+      Io => "io",
+    }
+  }
+}
+
+impl std::fmt::Display for PsiResource {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    use PsiResource::*;
+    let s = match self {
+      Cpu => "cpu",
+      Memory => "mem",
+      Io => "io",
+    };
+    write!(f, "{}", s)
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PsiStat {
+  pub cpu: Option<Pressure>,
+  pub memory: Option<Pressure>,
+  pub io: Option<Pressure>,
+}
+
+impl PsiStat {
+  pub fn get(&self, resource: PsiResource) -> Option<&Pressure> {
+    use PsiResource::*;
+    match resource {
+      Cpu => self.cpu.as_ref(),
+      Memory => self.memory.as_ref(),
+      Io => self.io.as_ref(),
+    }
+  }
+
+  fn read(path: impl Fn(PsiResource) -> String) -> Self {
+    let read = |r: PsiResource| {
+      fs::read_to_string(path(r))
+        .ok()
+        .and_then(|s| Pressure::from(&s))
+    };
+    Self {
+      cpu: read(PsiResource::Cpu),
+      memory: read(PsiResource::Memory),
+      io: read(PsiResource::Io),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.cpu.is_none() && self.memory.is_none() && self.io.is_none()
+  }
+}
+
+// pressure of the whole system.
+pub fn get_system_psi() -> PsiStat {
+  PsiStat::read(|r| format!("{}/{}", PRESSURE_PATH, r.file_name()))
+}
+
+// pressure of a cgroup. `cgroup` is a path relative to the root of cgroup v2. eg: "system.slice/docker.service"
+pub fn get_cgroup_psi(cgroup: &str) -> Option<PsiStat> {
+  let cgroup = cgroup.trim_matches('/');
+  CGROUP_ROOTS
+    .iter()
+    .map(|root| PsiStat::read(|r| format!("{}/{}/{}.pressure", root, cgroup, r.file_name())))
+    .find(|stat| !stat.is_empty())
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PsiSnapshot {
+  pub system: PsiStat,
+  pub cgroups: Vec<(String, Option<PsiStat>)>,
+}
+
+pub fn get_psi(cgroups: &[String]) -> PsiSnapshot {
+  PsiSnapshot {
+    system: get_system_psi(),
+    cgroups: cgroups
+      .iter()
+      .map(|c| (c.clone(), get_cgroup_psi(c)))
+      .collect(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pressure_from() {
+    let s = "some avg10=2.77 avg60=3.39 avg300=2.39 total=39936223\nfull avg10=0.50 avg60=0.00 avg300=0.00 total=12\n";
+    let pressure = Pressure::from(s).unwrap();
+    assert_eq!(pressure.some.avg10, 2.77);
+    assert_eq!(pressure.some.total, 39936223);
+    assert_eq!(pressure.full.avg10, 0.50);
+
+    // cpu of old kernels has no "full" line.
+    let pressure = Pressure::from("some avg10=1.00 avg60=0.00 avg300=0.00 total=0").unwrap();
+    assert_eq!(pressure.full, PsiLine::default());
+
+    assert_eq!(Pressure::from("full avg10=0.00"), None);
+    assert_eq!(Pressure::from("some avg10=abc"), None);
+  }
+
+  #[test]
+  fn test_system_psi() {
+    // PSI can be disabled by `psi=0`.
+    if std::path::Path::new(PRESSURE_PATH).exists() {
+      assert!(get_system_psi().cpu.is_some());
+    }
+    assert_eq!(get_cgroup_psi("no/such/cgroup"), None);
+  }
+}
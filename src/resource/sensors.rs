@@ -0,0 +1,335 @@
+/*****
+
+hwmon, thermal zone and RAPL related funcs.
+cf: https://www.kernel.org/doc/Documentation/hwmon/sysfs-interface.rst
+    https://www.kernel.org/doc/Documentation/power/powercap/powercap.rst
+
+Every reader takes the root of sysfs classes (usually "/sys/class"),
+so that they can be tested against a fake tree.
+
+*******/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub static CLASS_PATH: &str = "/sys/class";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SensorKind {
+  Temp,  // [degree Celsius]
+  Fan,   // [RPM]
+  Power, // [W]
+}
+
+impl SensorKind {
+  pub fn unit(&self) -> &str {
+    use SensorKind::*;
+    match self {
+      Temp => "C",
+      Fan => "RPM",
+      Power => "W",
+    }
+  }
+
+  // prefix of hwmon attributes and the divisor into the unit.
+  fn hwmon_attr(&self) -> (&str, f64) {
+    use SensorKind::*;
+    match self {
+      Temp => ("temp", 1000.0),
+      Fan => ("fan", 1.0),
+      Power => ("power", 1_000_000.0),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+  pub chip: String,  // eg: "coretemp", "nvme", "acpitz"
+  pub label: String, // eg: "Package id 0", "temp1"
+  pub kind: SensorKind,
+  pub value: f64,
+  pub max: Option<f64>,  // value regarded as high. min for fans.
+  pub crit: Option<f64>, // value where hardware shuts down or throttles.
+}
+
+// cumulative energy counter of a RAPL domain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaplCounter {
+  pub name: String,      // eg: "package-0", "dram"
+  pub energy_uj: u64,    // [micro Joules]
+  pub max_range_uj: u64, // counter wraps around at this value.
+}
+
+impl RaplCounter {
+  // [W] between two counters.
+  // None if the counter wrapped around without a known range.
+  pub fn watts(&self, prev: &Self, interval: f64) -> Option<f64> {
+    let diff = if self.energy_uj >= prev.energy_uj {
+      self.energy_uj - prev.energy_uj
+    } else if self.max_range_uj == 0 {
+      return None;
+    } else {
+      self
+        .max_range_uj
+        .checked_sub(prev.energy_uj)?
+        .checked_add(self.energy_uj)?
+    };
+    Some(diff as f64 / 1_000_000.0 / interval)
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SensorSnapshot {
+  pub sensors: Vec<Sensor>,
+  pub rapl: Vec<RaplCounter>,
+}
+
+fn read_string(path: &Path) -> Option<String> {
+  fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_f64(path: &Path) -> Option<f64> {
+  read_string(path)?.parse().ok()
+}
+
+fn sorted_entries(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+  let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+    Ok(d) => d
+      .filter_map(|e| e.ok())
+      .filter(|e| e.file_name().to_string_lossy().starts_with(prefix))
+      .map(|e| e.path())
+      .collect(),
+    Err(_) => vec![],
+  };
+  entries.sort();
+  entries
+}
+
+// sensors of a single hwmon device. eg: /sys/class/hwmon/hwmon0
+fn read_hwmon(dir: &Path) -> Vec<Sensor> {
+  let chip = match read_string(&dir.join("name")) {
+    Some(name) => name,
+    None => return vec![],
+  };
+  let mut result = vec![];
+  for kind in [SensorKind::Temp, SensorKind::Fan, SensorKind::Power] {
+    let (prefix, divisor) = kind.hwmon_attr();
+    // "temp1_input", "fan2_input", "power1_average", ...
+    let mut indexes: Vec<u32> = sorted_entries(dir, prefix)
+      .iter()
+      .filter_map(|p| {
+        let name = p.file_name()?.to_string_lossy().to_string();
+        let (ix, attr) = name[prefix.len()..].split_once('_')?;
+        if attr == "input" || attr == "average" {
+          ix.parse().ok()
+        } else {
+          None
+        }
+      })
+      .collect();
+    indexes.sort_unstable();
+    indexes.dedup();
+
+    for ix in indexes {
+      let attr = |a: &str| dir.join(format!("{}{}_{}", prefix, ix, a));
+      let value = match read_f64(&attr("input")).or_else(|| read_f64(&attr("average"))) {
+        Some(v) => v / divisor,
+        None => continue, // eg: EAGAIN while the device sleeps.
+      };
+      let max = if kind == SensorKind::Fan {
+        read_f64(&attr("min"))
+      } else {
+        read_f64(&attr("max"))
+      };
+      result.push(Sensor {
+        chip: chip.clone(),
+        label: read_string(&attr("label")).unwrap_or_else(|| format!("{}{}", prefix, ix)),
+        kind,
+        value,
+        max: max.filter(|&m| m > 0.0).map(|m| m / divisor),
+        crit: read_f64(&attr("crit"))
+          .filter(|&c| c > 0.0)
+          .map(|c| c / divisor),
+      });
+    }
+  }
+  result
+}
+
+// temperature of a thermal zone. eg: /sys/class/thermal/thermal_zone0
+fn read_thermal_zone(dir: &Path) -> Option<Sensor> {
+  let zone_type = read_string(&dir.join("type"))?;
+  let value = read_f64(&dir.join("temp"))? / 1000.0;
+
+  // trip points: "trip_point_0_type" is one of "critical", "hot", "passive" and "active".
+  let mut max = None;
+  let mut crit = None;
+  for type_path in sorted_entries(dir, "trip_point_") {
+    let name = type_path.file_name()?.to_string_lossy().to_string();
+    let temp_path = match name.strip_suffix("_type") {
+      Some(base) => dir.join(format!("{}_temp", base)),
+      None => continue,
+    };
+    let temp = match read_f64(&temp_path) {
+      Some(t) if t > 0.0 => t / 1000.0,
+      _ => continue,
+    };
+    match read_string(&type_path).as_deref() {
+      Some("critical") => crit = Some(temp),
+      Some("hot") | Some("passive") => max = Some(max.map_or(temp, |m: f64| m.min(temp))),
+      _ => {}
+    }
+  }
+
+  Some(Sensor {
+    chip: zone_type,
+    label: dir.file_name()?.to_string_lossy().to_string(),
+    kind: SensorKind::Temp,
+    value,
+    max,
+    crit,
+  })
+}
+
+fn read_rapl(dir: &Path) -> Option<RaplCounter> {
+  Some(RaplCounter {
+    name: read_string(&dir.join("name"))?,
+    energy_uj: read_f64(&dir.join("energy_uj"))? as u64,
+    max_range_uj: read_f64(&dir.join("max_energy_range_uj")).unwrap_or(0.0) as u64,
+  })
+}
+
+// sensors under `root` (usually "/sys/class").
+pub fn get_sensors_at(root: &Path) -> SensorSnapshot {
+  let mut sensors: Vec<Sensor> = sorted_entries(&root.join("hwmon"), "hwmon")
+    .iter()
+    .flat_map(|d| read_hwmon(d))
+    .collect();
+
+  // most thermal zones are also exported as hwmon devices named after the zone type.
+  let zones: Vec<Sensor> = sorted_entries(&root.join("thermal"), "thermal_zone")
+    .iter()
+    .filter_map(|d| read_thermal_zone(d))
+    .filter(|z| !sensors.iter().any(|s| s.chip == z.chip))
+    .collect();
+  sensors.extend(zones);
+
+  // "intel-rapl:0" is a package, and "intel-rapl:0:0" is a sub-domain (core, uncore, dram) of it.
+  let rapl = sorted_entries(&root.join("powercap"), "intel-rapl:")
+    .iter()
+    .filter(|d| {
+      d.file_name()
+        .unwrap()
+        .to_string_lossy()
+        .matches(':')
+        .count()
+        == 1
+    })
+    .filter_map(|d| read_rapl(d))
+    .collect();
+
+  SensorSnapshot { sensors, rapl }
+}
+
+pub fn get_sensors() -> SensorSnapshot {
+  get_sensors_at(Path::new(CLASS_PATH))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::fake_sysfs;
+
+  #[test]
+  fn test_get_sensors_at() {
+    let root = fake_sysfs(
+      "sensors",
+      &[
+        ("hwmon/hwmon0/name", "coretemp\n"),
+        ("hwmon/hwmon0/temp1_input", "45000\n"),
+        ("hwmon/hwmon0/temp1_label", "Package id 0\n"),
+        ("hwmon/hwmon0/temp1_max", "80000\n"),
+        ("hwmon/hwmon0/temp1_crit", "100000\n"),
+        ("hwmon/hwmon0/temp2_input", "41000\n"),
+        ("hwmon/hwmon1/name", "acpitz\n"),
+        ("hwmon/hwmon1/temp1_input", "30000\n"),
+        ("hwmon/hwmon1/fan1_input", "2100\n"),
+        ("hwmon/hwmon1/power1_average", "12500000\n"),
+        ("thermal/thermal_zone0/type", "acpitz\n"),
+        ("thermal/thermal_zone0/temp", "30000\n"),
+        ("thermal/thermal_zone1/type", "x86_pkg_temp\n"),
+        ("thermal/thermal_zone1/temp", "52000\n"),
+        ("thermal/thermal_zone1/trip_point_0_type", "passive\n"),
+        ("thermal/thermal_zone1/trip_point_0_temp", "90000\n"),
+        ("thermal/thermal_zone1/trip_point_1_type", "critical\n"),
+        ("thermal/thermal_zone1/trip_point_1_temp", "105000\n"),
+        ("powercap/intel-rapl:0/name", "package-0\n"),
+        ("powercap/intel-rapl:0/energy_uj", "1000000\n"),
+        (
+          "powercap/intel-rapl:0/max_energy_range_uj",
+          "262143328850\n",
+        ),
+        ("powercap/intel-rapl:0:0/name", "core\n"),
+        ("powercap/intel-rapl:0:0/energy_uj", "500\n"),
+      ],
+    );
+    let snapshot = get_sensors_at(&root);
+    fs::remove_dir_all(&root).unwrap();
+
+    let sensors = &snapshot.sensors;
+    assert_eq!(sensors.len(), 6);
+    assert_eq!(
+      sensors[0],
+      Sensor {
+        chip: "coretemp".into(),
+        label: "Package id 0".into(),
+        kind: SensorKind::Temp,
+        value: 45.0,
+        max: Some(80.0),
+        crit: Some(100.0),
+      }
+    );
+    assert_eq!(sensors[1].label, "temp2");
+    assert!(sensors
+      .iter()
+      .any(|s| s.kind == SensorKind::Fan && s.value == 2100.0));
+    assert!(sensors
+      .iter()
+      .any(|s| s.kind == SensorKind::Power && s.value == 12.5));
+
+    // acpitz zone is already exported as hwmon1.
+    let zone = sensors.last().unwrap();
+    assert_eq!(zone.chip, "x86_pkg_temp");
+    assert_eq!((zone.max, zone.crit), (Some(90.0), Some(105.0)));
+
+    assert_eq!(snapshot.rapl.len(), 1);
+    assert_eq!(snapshot.rapl[0].name, "package-0");
+  }
+
+  #[test]
+  fn test_rapl_watts() {
+    let prev = RaplCounter {
+      name: "package-0".into(),
+      energy_uj: 9_000_000,
+      max_range_uj: 10_000_000,
+    };
+    let cur = RaplCounter {
+      energy_uj: 1_000_000, // wrapped around
+      ..prev.clone()
+    };
+    assert_eq!(cur.watts(&prev, 2.0), Some(1.0));
+
+    // the range is unknown, or smaller than the previous value.
+    for max_range_uj in [0, 8_000_000] {
+      let prev = RaplCounter {
+        max_range_uj,
+        ..prev.clone()
+      };
+      let cur = RaplCounter {
+        max_range_uj,
+        ..cur.clone()
+      };
+      assert_eq!(cur.watts(&prev, 2.0), None);
+    }
+  }
+}
@@ -0,0 +1,299 @@
+/*****
+
+CPU topology and NUMA node related funcs.
+cf: https://www.kernel.org/doc/Documentation/ABI/stable/sysfs-devices-system-cpu
+    https://www.kernel.org/doc/Documentation/ABI/stable/sysfs-devices-node
+
+Readers take the root of system devices (usually "/sys/devices/system"),
+so that they can be tested against a fake tree.
+
+*******/
+
+use crate::util::parse_cpu_list;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub static SYSTEM_PATH: &str = "/sys/devices/system";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CpuTopology {
+  pub cpu: u32,
+  pub package: u32,       // physical socket
+  pub die: u32,           //
+  pub core: u32,          // unique only inside the package
+  pub siblings: Vec<u32>, // SMT siblings sharing the core, including itself
+  pub node: Option<u32>,  // NUMA node
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NumaNode {
+  pub id: u32,
+  pub cpus: Vec<u32>,
+  pub mem_total: u64, // [KB]
+  pub mem_free: u64,  // [KB]
+}
+
+impl NumaNode {
+  pub fn mem_used(&self) -> u64 {
+    self.mem_total.saturating_sub(self.mem_free)
+  }
+}
+
+// CPUs sharing a NUMA node and a package.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CpuGroup {
+  pub node: Option<u32>,
+  pub package: u32,
+  pub cpus: Vec<u32>, // ordered by core, so SMT siblings are adjacent.
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Topology {
+  pub root: PathBuf,
+  pub cpus: Vec<CpuTopology>,
+  pub nodes: Vec<NumaNode>,
+}
+
+fn read_string(path: &Path) -> Option<String> {
+  fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_u32(path: &Path) -> Option<u32> {
+  read_string(path)?.parse().ok()
+}
+
+// offline cpus have no topology directory.
+fn read_cpu_topology(root: &Path, cpu: u32, nodes: &[NumaNode]) -> Option<CpuTopology> {
+  let dir = root.join(format!("cpu/cpu{}/topology", cpu));
+  if !dir.exists() {
+    return None;
+  }
+  Some(CpuTopology {
+    cpu,
+    package: read_u32(&dir.join("physical_package_id")).unwrap_or(0),
+    die: read_u32(&dir.join("die_id")).unwrap_or(0),
+    core: read_u32(&dir.join("core_id")).unwrap_or(cpu),
+    siblings: read_string(&dir.join("thread_siblings_list"))
+      .map(|s| parse_cpu_list(&s))
+      .unwrap_or_else(|| vec![cpu]),
+    node: nodes.iter().find(|n| n.cpus.contains(&cpu)).map(|n| n.id),
+  })
+}
+
+fn default_cpu_topology(cpu: u32) -> CpuTopology {
+  CpuTopology {
+    cpu,
+    core: cpu,
+    siblings: vec![cpu],
+    ..Default::default()
+  }
+}
+
+// "Node 0 MemTotal:        5603064 kB"
+fn read_node_meminfo(node: &mut NumaNode, root: &Path) {
+  let path = root.join(format!("node/node{}/meminfo", node.id));
+  let meminfo = read_string(&path).unwrap_or_default();
+  for line in meminfo.lines() {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 4 {
+      continue;
+    }
+    let value = tokens[3].parse().unwrap_or(0);
+    match tokens[2] {
+      "MemTotal:" => node.mem_total = value,
+      "MemFree:" => node.mem_free = value,
+      _ => {}
+    }
+  }
+}
+
+pub fn read_nodes(root: &Path) -> Vec<NumaNode> {
+  // kernels without CONFIG_NUMA have no "node" directory.
+  let ids = match read_string(&root.join("node/online")) {
+    Some(s) => parse_cpu_list(&s),
+    None => return vec![],
+  };
+  ids
+    .into_iter()
+    .map(|id| {
+      let cpus = read_string(&root.join(format!("node/node{}/cpulist", id)))
+        .map(|s| parse_cpu_list(&s))
+        .unwrap_or_default();
+      let mut node = NumaNode {
+        id,
+        cpus,
+        ..Default::default()
+      };
+      read_node_meminfo(&mut node, root);
+      node
+    })
+    .collect()
+}
+
+impl Topology {
+  // topology of `cpus` under `root` (usually "/sys/devices/system").
+  pub fn read_at(root: &Path, cpus: &[u32]) -> Self {
+    let nodes = read_nodes(root);
+    Self {
+      root: root.to_path_buf(),
+      cpus: cpus
+        .iter()
+        .map(|&cpu| {
+          read_cpu_topology(root, cpu, &nodes).unwrap_or_else(|| default_cpu_topology(cpu))
+        })
+        .collect(),
+      nodes,
+    }
+  }
+
+  // re-read topology after cpus are added, removed, onlined or offlined.
+  // cpus which went offline keep their last known place.
+  pub fn refresh(&mut self, cpus: &[u32]) {
+    let nodes = read_nodes(&self.root);
+    let mut refreshed: Vec<CpuTopology> = cpus
+      .iter()
+      .map(|&cpu| {
+        read_cpu_topology(&self.root, cpu, &nodes)
+          .or_else(|| self.get(cpu).cloned())
+          .unwrap_or_else(|| default_cpu_topology(cpu))
+      })
+      .collect();
+    // node of an offline cpu is still listed in its cpulist.
+    for t in refreshed.iter_mut() {
+      t.node = nodes
+        .iter()
+        .find(|n| n.cpus.contains(&t.cpu))
+        .map(|n| n.id)
+        .or(t.node);
+    }
+    self.cpus = refreshed;
+    self.nodes = nodes;
+  }
+
+  pub fn read(cpus: &[u32]) -> Self {
+    Self::read_at(Path::new(SYSTEM_PATH), cpus)
+  }
+
+  // only memory of nodes changes over time. `nodes` are read by a collector.
+  pub fn update_nodes(&mut self, nodes: &[NumaNode]) {
+    for node in self.nodes.iter_mut() {
+      if let Some(read) = nodes.iter().find(|n| n.id == node.id) {
+        node.mem_total = read.mem_total;
+        node.mem_free = read.mem_free;
+      }
+    }
+  }
+
+  pub fn get(&self, cpu: u32) -> Option<&CpuTopology> {
+    self.cpus.iter().find(|t| t.cpu == cpu)
+  }
+
+  pub fn node(&self, id: u32) -> Option<&NumaNode> {
+    self.nodes.iter().find(|n| n.id == id)
+  }
+
+  // group CPUs by NUMA node and package, with SMT siblings adjacent.
+  pub fn groups(&self) -> Vec<CpuGroup> {
+    let mut sorted: Vec<&CpuTopology> = self.cpus.iter().collect();
+    sorted.sort_by_key(|t| (t.node, t.package, t.die, t.core, t.cpu));
+
+    let mut groups: Vec<CpuGroup> = vec![];
+    for t in sorted {
+      match groups.last_mut() {
+        Some(g) if g.node == t.node && g.package == t.package => g.cpus.push(t.cpu),
+        _ => groups.push(CpuGroup {
+          node: t.node,
+          package: t.package,
+          cpus: vec![t.cpu],
+        }),
+      }
+    }
+    groups
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::fake_sysfs;
+
+  // 2 packages, 2 cores per package, 2 threads per core. cpuN and cpuN+4 are siblings.
+  fn fake_topology(name: &str) -> PathBuf {
+    let mut files = vec![];
+    for cpu in 0..8 {
+      let core = cpu % 4;
+      let (package, core_id) = (core / 2, core % 2);
+      let dir = format!("cpu/cpu{}/topology", cpu);
+      files.push((format!("{}/physical_package_id", dir), package.to_string()));
+      files.push((format!("{}/die_id", dir), "0".into()));
+      files.push((format!("{}/core_id", dir), core_id.to_string()));
+      files.push((
+        format!("{}/thread_siblings_list", dir),
+        format!("{},{}", core, core + 4),
+      ));
+    }
+    files.push(("node/online".into(), "0-1\n".into()));
+    files.push(("node/node0/cpulist".into(), "0-1,4-5\n".into()));
+    files.push(("node/node1/cpulist".into(), "2-3,6-7\n".into()));
+    files.push((
+      "node/node0/meminfo".into(),
+      "Node 0 MemTotal:        8000 kB\nNode 0 MemFree:         3000 kB\n".into(),
+    ));
+    let files: Vec<(&str, &str)> = files
+      .iter()
+      .map(|(p, c)| (p.as_str(), c.as_str()))
+      .collect();
+    fake_sysfs(name, &files)
+  }
+
+  #[test]
+  fn test_topology() {
+    let root = fake_topology("topology");
+    let mut topology = Topology::read_at(&root, &(0..8).collect::<Vec<u32>>());
+    fs::write(
+      root.join("node/node0/meminfo"),
+      "Node 0 MemTotal:        8000 kB\nNode 0 MemFree:         1000 kB\n",
+    )
+    .unwrap();
+    let nodes = read_nodes(&root);
+    fs::remove_dir_all(&root).unwrap();
+
+    let cpu6 = topology.get(6).unwrap();
+    assert_eq!((cpu6.package, cpu6.core, cpu6.node), (1, 0, Some(1)));
+    assert_eq!(cpu6.siblings, vec![2, 6]);
+
+    let node0 = topology.node(0).unwrap();
+    assert_eq!(node0.mem_used(), 5000);
+    assert_eq!(topology.node(1).unwrap().mem_total, 0);
+    topology.update_nodes(&nodes);
+    assert_eq!(topology.node(0).unwrap().mem_used(), 7000);
+
+    let groups = topology.groups();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].cpus, vec![0, 4, 1, 5]);
+    assert_eq!(groups[1].node, Some(1));
+    assert_eq!(groups[1].cpus, vec![2, 6, 3, 7]);
+  }
+
+  #[test]
+  fn test_refresh() {
+    let root = fake_topology("topology-refresh");
+    let mut topology = Topology::read_at(&root, &(0..8).collect::<Vec<u32>>());
+    // cpu6 goes offline, and cpu8 is added without topology.
+    fs::remove_dir_all(root.join("cpu/cpu6/topology")).unwrap();
+    topology.refresh(&(0..9).collect::<Vec<u32>>());
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(topology.get(6).unwrap().package, 1);
+    assert_eq!(topology.get(8).unwrap().core, 8);
+    assert_eq!(topology.cpus.len(), 9);
+  }
+
+  #[test]
+  fn test_no_topology() {
+    let topology = Topology::read_at(Path::new("/no/such/dir"), &[0, 1]);
+    assert!(topology.nodes.is_empty());
+    assert_eq!(topology.groups().len(), 1);
+    assert_eq!(topology.groups()[0].cpus, vec![0, 1]);
+  }
+}
@@ -1,2 +1,49 @@
+/*****
+
+Symbols used by graphs, bars and boxes.
+ASCII mode replaces them for terminals which cannot show unicode.
+
+*******/
+
 pub mod block;
 pub mod brail;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+pub fn set_ascii(ascii: bool) {
+  ASCII.store(ascii, Ordering::Relaxed);
+}
+
+pub fn is_ascii() -> bool {
+  ASCII.load(Ordering::Relaxed)
+}
+
+// whether characters are encoded in UTF-8, decided as the C library does.
+pub fn is_utf8_locale() -> bool {
+  ["LC_ALL", "LC_CTYPE", "LANG"]
+    .iter()
+    .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+    .is_some_and(|locale| is_utf8(&locale))
+}
+
+fn is_utf8(locale: &str) -> bool {
+  let locale = locale.to_lowercase();
+  locale.contains("utf-8") || locale.contains("utf8")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_is_utf8() {
+    assert!(is_utf8("en_US.UTF-8"));
+    assert!(is_utf8("ja_JP.utf8"));
+    assert!(is_utf8("C.UTF-8"));
+    assert!(!is_utf8("C"));
+    assert!(!is_utf8("POSIX"));
+    assert!(!is_utf8("en_US.ISO-8859-1"));
+  }
+}
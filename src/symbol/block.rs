@@ -16,35 +16,42 @@ pub mod lv {
   const ONE_QUARTER: &str = "▂";
   const ONE_EIGHTH: &str = "▁";
 
+  // blocks in ASCII mode.
+  const ASCII_FULL: &str = "#";
+  const ASCII_HALF: &str = "=";
+  const ASCII_LOW: &str = "_";
+
   pub fn get_bar(maxheight: i32, r: f64) -> String {
     let r = if r > 1.0 { 1.0 } else { r };
+    let ascii = crate::symbol::is_ascii();
+    let block = |unicode, ascii_block| if ascii { ascii_block } else { unicode };
 
     let mut res = String::new();
     let mut n = maxheight as f64 * r; // num of full blocks
     loop {
       if n >= 1.0 {
-        res.push_str(FULL);
+        res.push_str(block(FULL, ASCII_FULL));
         n -= 1.0;
       } else if n >= 0.875 {
-        res.push_str(SEVEN_EIGHTHS);
+        res.push_str(block(SEVEN_EIGHTHS, ASCII_FULL));
         break;
       } else if n >= 0.75 {
-        res.push_str(THREE_QUARTERS);
+        res.push_str(block(THREE_QUARTERS, ASCII_FULL));
         break;
       } else if n >= 0.625 {
-        res.push_str(FIVE_EIGHTHS);
+        res.push_str(block(FIVE_EIGHTHS, ASCII_HALF));
         break;
       } else if n >= 0.5 {
-        res.push_str(HALF);
+        res.push_str(block(HALF, ASCII_HALF));
         break;
       } else if n >= 0.375 {
-        res.push_str(THREE_EIGHTHS);
+        res.push_str(block(THREE_EIGHTHS, ASCII_LOW));
         break;
       } else if n >= 0.25 {
-        res.push_str(ONE_QUARTER);
+        res.push_str(block(ONE_QUARTER, ASCII_LOW));
         break;
       } else if n >= 0.125 {
-        res.push_str(ONE_EIGHTH);
+        res.push_str(block(ONE_EIGHTH, ASCII_LOW));
         break;
       } else {
         break;
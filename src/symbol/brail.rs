@@ -158,6 +158,30 @@ pub mod b32 {
     ["⠇", "⠧", "⠗", "⠷", "⠏", "⠯", "⠟", "⠿"],
   ];
 
+  // brail of the dots, or ASCII tracing them in ASCII mode.
+  fn dots(li: usize, ri: usize) -> &'static str {
+    if crate::symbol::is_ascii() {
+      ascii_dots(li, ri)
+    } else {
+      DOTS[li][ri]
+    }
+  }
+
+  // a line in a single row, or a slope when the cols differ in height.
+  pub(super) fn ascii_dots(li: usize, ri: usize) -> &'static str {
+    let top = |i: usize| (0..3).rev().find(|y| i >> y & 1 == 1);
+    if li != 0 && ri != 0 && top(li) != top(ri) {
+      return if top(li) < top(ri) { "/" } else { "\\" };
+    }
+    match li | ri {
+      0 => " ",
+      1 => "_",
+      2 => "-",
+      4 => "'",
+      _ => "|",
+    }
+  }
+
   // convert dots into index.
   fn dots_to_index(dots: Vec<i32>) -> usize {
     let dots = if dots.len() > 3 { vec![] } else { dots };
@@ -184,7 +208,7 @@ pub mod b32 {
   fn get_brail(d0: i32) -> &'static str {
     let dot = value_to_dots(d0);
     let i = dots_to_index(dot);
-    DOTS[i][0]
+    dots(i, 0)
   }
 
   // convert one data into brails.
@@ -262,7 +286,7 @@ pub mod b32 {
         }
         let li = dots_to_index(dots0.clone().into_iter().collect());
         let ri = dots_to_index(dots1.clone().into_iter().collect());
-        let brail = DOTS[li][ri];
+        let brail = dots(li, ri);
         brails.push_str(brail);
         dots0.clear();
         dots1.clear();
@@ -382,9 +406,9 @@ pub mod b32 {
         let li1 = dots_to_index(dots10.clone().into_iter().collect());
         let ri1 = dots_to_index(dots11.clone().into_iter().collect());
         let brail = if (li0, ri0) != (0, 0) {
-          Cc::from(DOTS[li0][ri0].chars().next().unwrap(), c0)
+          Cc::from(dots(li0, ri0).chars().next().unwrap(), c0)
         } else {
-          Cc::from(DOTS[li1][ri1].chars().next().unwrap(), c1)
+          Cc::from(dots(li1, ri1).chars().next().unwrap(), c1)
         };
         res.push(brail);
         dots00.clear();
@@ -502,6 +526,41 @@ pub mod b32 {
     res
   }
 
+  // convert any number of data into colorized line-charts overlaid in the same range.
+  // where lines cross, the one given earlier is drawn.
+  pub fn get_brails_complement_multi_color(
+    maxheight: i32,
+    min: f64,
+    max: f64,
+    data: Vec<(Vec<f64>, i16)>,
+  ) -> Vec<Vec<Cc>> {
+    let width = data.first().map_or(0, |(d, _)| d.len());
+    if data.iter().any(|(d, _)| d.len() != width) {
+      eprintln!("Error: get_brails_complement_multi_color(): len of data differs.");
+      return vec![];
+    }
+
+    let lines: Vec<(Vec<String>, i16)> = data
+      .into_iter()
+      .map(|(d, color)| (get_brails_complement(maxheight, min, max, d), color))
+      .collect();
+    (0..width)
+      .map(|i| {
+        (0..maxheight as usize)
+          .map(|y| {
+            lines
+              .iter()
+              .find_map(|(brails, color)| {
+                let ch = brails[i].chars().nth(y)?;
+                (ch != ' ').then(|| Cc::from(ch, *color))
+              })
+              .unwrap_or_else(|| Cc::from(' ', lines[0].1))
+          })
+          .collect()
+      })
+      .collect()
+  }
+
   // convert given data into vec of braills
   pub fn get_brails(maxheight: i32, min: f64, max: f64, d0: Vec<f64>) -> Vec<String> {
     let maxheight = (maxheight * 3) as u64;
@@ -626,4 +685,35 @@ mod test {
     let brail = b32::get_brails_complement(height, 0.0, 30.0, d0);
     show_brail(brail, height);
   }
+
+  #[test]
+  fn test_brail32_multi() {
+    let d0 = vec![0.0, 0.0, 0.0, 0.0];
+    let d1 = vec![11.0, 11.0, 11.0, 11.0];
+    let cols = b32::get_brails_complement_multi_color(4, 0.0, 12.0, vec![(d0, 1), (d1, 2)]);
+    assert_eq!(cols.len(), 4);
+    // the last column has no next value to draw a line to.
+    for col in cols.iter().take(3) {
+      assert_eq!(col.len(), 4);
+      assert_eq!((col[0].ch != ' ', col[0].co), (true, 1));
+      assert_eq!((col[3].ch != ' ', col[3].co), (true, 2));
+      assert!(col[1].ch == ' ' && col[2].ch == ' ');
+    }
+    assert!(
+      b32::get_brails_complement_multi_color(4, 0.0, 1.0, vec![(vec![0.0], 1), (vec![], 2)])
+        .is_empty()
+    );
+  }
+
+  #[test]
+  fn test_ascii_dots() {
+    assert_eq!(b32::ascii_dots(0, 0), " ");
+    assert_eq!(b32::ascii_dots(1, 1), "_");
+    assert_eq!(b32::ascii_dots(0, 2), "-");
+    assert_eq!(b32::ascii_dots(4, 0), "'");
+    assert_eq!(b32::ascii_dots(1, 2), "/");
+    assert_eq!(b32::ascii_dots(7, 3), "\\");
+    assert_eq!(b32::ascii_dots(7, 7), "|");
+    assert_eq!(b32::ascii_dots(5, 0), "|");
+  }
 }
@@ -0,0 +1,88 @@
+/*****
+
+Themes of colours.
+A palette gives a colour to each role, such as header or danger,
+and is either built in or defined in the config.
+
+*******/
+
+// colours of a theme by role, in 0xRRGGBB.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+  pub background: u32,
+  pub foreground: u32,
+  pub dim: u32,           // inactive items. also background of scroll bars.
+  pub accent: u32,        // names and secondary values. PAIR_COMM
+  pub header: u32,        // PAIR_HEAD
+  pub danger: u32,        // PAIR_DANGER
+  pub medium_danger: u32, // PAIR_MED_DANGER
+  pub highlight: u32,     // command box and notes. PAIR_CUTE
+  pub rx: u32,
+  pub tx: u32,
+  pub read: u32,
+  pub write: u32,
+}
+
+pub static BUILTIN_THEMES: [&str; 3] = ["dark", "light", "high-contrast"];
+
+// gruvbox-like dark palette.
+static DARK: Palette = Palette {
+  background: 0x32302F,
+  foreground: 0xEBD8B2,
+  dim: 0x504945,
+  accent: 0x84A87F,
+  header: 0x4E9A06,
+  danger: 0xCC241D,
+  medium_danger: 0xFE8019,
+  highlight: 0xD3869B,
+  rx: 0xEBD8B2,
+  tx: 0x84A87F,
+  read: 0xEBD8B2,
+  write: 0x84A87F,
+};
+
+static LIGHT: Palette = Palette {
+  background: 0xFBF1C7,
+  foreground: 0x3C3836,
+  dim: 0xD5C4A1,
+  accent: 0x427B58,
+  header: 0x79740E,
+  danger: 0x9D0006,
+  medium_danger: 0xAF3A03,
+  highlight: 0x8F3F71,
+  rx: 0x3C3836,
+  tx: 0x076678,
+  read: 0x3C3836,
+  write: 0x076678,
+};
+
+// Okabe-Ito colours on black, distinguishable with colour vision deficiency.
+static HIGH_CONTRAST: Palette = Palette {
+  background: 0x000000,
+  foreground: 0xFFFFFF,
+  dim: 0x6E6E6E,
+  accent: 0x56B4E9,
+  header: 0x009E73,
+  danger: 0xD55E00,
+  medium_danger: 0xF0E442,
+  highlight: 0xCC79A7,
+  rx: 0x56B4E9,
+  tx: 0xE69F00,
+  read: 0x56B4E9,
+  write: 0xE69F00,
+};
+
+pub fn builtin_palette(name: &str) -> Option<Palette> {
+  match name {
+    "dark" => Some(DARK.clone()),
+    "light" => Some(LIGHT.clone()),
+    "high-contrast" => Some(HIGH_CONTRAST.clone()),
+    _ => None,
+  }
+}
+
+impl Default for Palette {
+  fn default() -> Self {
+    DARK.clone()
+  }
+}
@@ -189,6 +189,79 @@ pub fn get_dir_file(full_path: &str) -> (String, String) {
   }
 }
 
+// short human readable size. eg: "12.3K"
+pub fn human_size(bytes: f64) -> String {
+  let units = ["", "K", "M", "G", "T"];
+  let mut val = bytes;
+  let mut i = 0;
+  while val >= 1024.0 && i < units.len() - 1 {
+    val /= 1024.0;
+    i += 1;
+  }
+  if i == 0 {
+    format!("{:.0}", val)
+  } else {
+    format!("{:.1}{}", val, units[i])
+  }
+}
+
+// parse the list format of sysfs. eg: "0-3,8,10-11" -> [0, 1, 2, 3, 8, 10, 11]
+pub fn parse_cpu_list(s: &str) -> Vec<u32> {
+  let mut result = vec![];
+  for range in s.trim().split(',').filter(|r| !r.is_empty()) {
+    match range.split_once('-') {
+      Some((from, to)) => {
+        if let (Ok(from), Ok(to)) = (from.parse::<u32>(), to.parse::<u32>()) {
+          result.extend(from..=to);
+        }
+      }
+      None => result.extend(range.parse::<u32>().ok()),
+    }
+  }
+  result
+}
+
+/* selection related funcs */
+
+// selection of devices/interfaces by name.
+// explicitly selected names take priority over the exclude pattern and hidden ones.
+#[derive(Clone, Debug, Default)]
+pub struct NameSelection {
+  pub names: Vec<String>,
+  pub exclude: Option<regex::Regex>,
+  pub show_hidden: bool,
+}
+
+impl NameSelection {
+  pub fn is_selected(&self, name: &str, is_hidden: bool) -> bool {
+    if !self.names.is_empty() {
+      return self.names.iter().any(|n| n == name);
+    }
+    if is_hidden && !self.show_hidden {
+      return false;
+    }
+    !self.exclude.as_ref().is_some_and(|re| re.is_match(name))
+  }
+
+  // toggle `name`. `selected` is the names selected now, used when no name is selected explicitly.
+  // the last selected name is kept, since no explicit name means all of them.
+  pub fn toggle(&mut self, name: &str, selected: Vec<String>) {
+    let mut names = if self.names.is_empty() {
+      selected
+    } else {
+      self.names.clone()
+    };
+    match names.iter().position(|n| n == name) {
+      Some(_) if names.len() == 1 => return,
+      Some(i) => {
+        names.remove(i);
+      }
+      None => names.push(name.into()),
+    }
+    self.names = names;
+  }
+}
+
 /* string related funcs */
 
 pub fn firstn(s: &str, n: usize) -> String {
@@ -199,10 +272,94 @@ pub fn firstn(s: &str, n: usize) -> String {
   }
 }
 
+// same as `firstn`, but counts chars instead of bytes.
+pub fn firstn_chars(s: &str, n: usize) -> String {
+  s.chars().take(n).collect()
+}
+
+/* time related funcs */
+
+// convert UNIX time into "MM/DD hh:mm:ss" in local timezone.
+pub fn local_time_string(epoch: i64) -> String {
+  let t = epoch as libc::time_t;
+  let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+  if unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+    return "??/?? ??:??:??".into();
+  }
+  format!(
+    "{:02}/{:02} {:02}:{:02}:{:02}",
+    tm.tm_mon + 1,
+    tm.tm_mday,
+    tm.tm_hour,
+    tm.tm_min,
+    tm.tm_sec
+  )
+}
+
+// creates `files` (list of (relative path, content)) under a temporary directory, and returns the directory.
+// used to test readers of sysfs against a fake tree.
+#[cfg(test)]
+pub fn fake_sysfs(name: &str, files: &[(&str, &str)]) -> path::PathBuf {
+  let root = std::env::temp_dir().join(format!("xtop-test-{}-{}", name, std::process::id()));
+  let _ = fs::remove_dir_all(&root);
+  for (file, content) in files {
+    let file = root.join(file);
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    fs::write(file, content).unwrap();
+  }
+  root
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_parse_cpu_list() {
+    assert_eq!(parse_cpu_list("0-3,8,10-11\n"), vec![0, 1, 2, 3, 8, 10, 11]);
+    assert_eq!(parse_cpu_list("0"), vec![0]);
+    assert_eq!(parse_cpu_list("\n"), Vec::<u32>::new());
+  }
+
+  #[test]
+  fn test_human_size() {
+    assert_eq!(human_size(1000.0), "1000");
+    assert_eq!(human_size(1536.0), "1.5K");
+    assert_eq!(human_size(3.0 * 1024.0 * 1024.0 * 1024.0), "3.0G");
+  }
+
+  #[test]
+  fn test_name_selection() {
+    let mut sel = NameSelection {
+      exclude: Some(regex::Regex::new("^docker").unwrap()),
+      ..Default::default()
+    };
+    assert!(sel.is_selected("eth0", false));
+    assert!(!sel.is_selected("docker0", false));
+    assert!(!sel.is_selected("lo", true));
+    sel.show_hidden = true;
+    assert!(sel.is_selected("lo", true));
+
+    sel.toggle("eth0", vec!["eth0".into(), "lo".into()]);
+    assert_eq!(sel.names, vec!["lo".to_string()]);
+    assert!(sel.is_selected("lo", true));
+    assert!(!sel.is_selected("eth0", false));
+    sel.toggle("docker0", vec![]);
+    assert!(sel.is_selected("docker0", false));
+
+    // deselecting the last one doesn't select all.
+    sel.toggle("lo", vec![]);
+    assert_eq!(sel.names, vec!["docker0".to_string()]);
+    sel.toggle("docker0", vec![]);
+    assert_eq!(sel.names, vec!["docker0".to_string()]);
+    assert!(!sel.is_selected("lo", true));
+
+    let mut sel = NameSelection::default();
+    sel.toggle("eth0", vec!["eth0".into()]);
+    assert!(sel.names.is_empty());
+    assert!(sel.is_selected("eth0", false));
+  }
+
   #[test]
   fn test_dev_number() {
     let nr1 = 34841;
@@ -231,4 +388,10 @@ mod tests {
     assert_eq!(ex3_dir, "");
     assert_eq!(ex3_file, "xtop");
   }
+
+  #[test]
+  fn test_firstn_chars() {
+    assert_eq!(firstn_chars("⣿⣿⣿abc", 4), "⣿⣿⣿a");
+    assert_eq!(firstn_chars("ab", 4), "ab");
+  }
 }